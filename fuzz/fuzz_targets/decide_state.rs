@@ -0,0 +1,90 @@
+#![no_main]
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use atlantis::models::ability_map::{COLORS, FLAVORS};
+use atlantis::models::state::{Edge, Layer, Pearl, State, Worker};
+use atlantis::processing::fuzz::fuzz_state;
+
+/// A state that always parses, with flavors, colors and neighbors picked
+/// by index so most inputs also pass validation
+#[derive(Arbitrary, Debug)]
+struct FuzzState {
+    workers: Vec<FuzzWorker>,
+    neighbor_map: Vec<(u8, u8)>,
+    edges: Vec<FuzzEdge>,
+    score: u32,
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzEdge {
+    workers: (u8, u8),
+    cost: Option<u8>,
+    capacity: Option<u8>,
+    one_way: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzWorker {
+    flavor: u8,
+    desk: Vec<Vec<(u8, u32, Option<u32>)>>,
+}
+
+impl FuzzState {
+    fn to_state(&self) -> State {
+        let n = self.workers.len().max(1);
+        let mut next_pearl_id: u32 = 0;
+        let mut workers: Vec<Worker> = Vec::new();
+
+        for (id, w) in self.workers.iter().enumerate() {
+            let mut desk: Vec<Pearl> = Vec::new();
+            for layers in &w.desk {
+                desk.push(Pearl {
+                    id: next_pearl_id,
+                    layers: layers
+                        .iter()
+                        .map(|&(c, t, o)| Layer {
+                            color: COLORS[c as usize % COLORS.len()].to_string(),
+                            thickness: t,
+                            original_thickness: o,
+                        })
+                        .collect(),
+                });
+                next_pearl_id += 1;
+            }
+
+            workers.push(Worker {
+                id: id as u32,
+                flavor: FLAVORS[w.flavor as usize % FLAVORS.len()].to_string(),
+                desk: desk,
+            });
+        }
+
+        return State {
+            workers: workers,
+            neighbor_map: self
+                .neighbor_map
+                .iter()
+                .map(|&(a, b)| vec![(a as usize % n) as u32, (b as usize % n) as u32])
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|e| Edge {
+                    workers: [
+                        (e.workers.0 as usize % n) as u32,
+                        (e.workers.1 as usize % n) as u32,
+                    ],
+                    cost: e.cost.map(|c| c as u32),
+                    capacity: e.capacity.map(|c| c as u32),
+                    one_way: e.one_way,
+                })
+                .collect(),
+            score: self.score,
+        };
+    }
+}
+
+fuzz_target!(|input: FuzzState| {
+    fuzz_state(&input.to_state());
+});
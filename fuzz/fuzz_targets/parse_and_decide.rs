@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use atlantis::processing::fuzz::fuzz_bytes;
+
+// Arbitrary bytes, treated as a line of input from the harness
+fuzz_target!(|data: &[u8]| {
+    fuzz_bytes(data);
+});
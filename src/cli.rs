@@ -0,0 +1,360 @@
+use log::info;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+#[cfg(unix)]
+use std::sync::atomic::AtomicBool;
+#[cfg(unix)]
+use std::sync::Arc;
+use std::time::Duration;
+
+use atlantis::command::{Command, USAGE};
+use atlantis::models;
+use atlantis::processing;
+use models::config::Config;
+use models::state::State;
+use processing::bound::{bound_report, bound_to_json, bound_to_text};
+use processing::config::load_ability_map;
+use processing::explain::{explain_state, explanation_to_json, explanation_to_text};
+#[cfg(feature = "http")]
+use processing::http::HttpServer;
+use processing::layout::{advise_layout, layout_to_json, layout_to_text};
+use processing::pearl_pipeline::run_pearl_processing;
+use processing::render::{render_state_dot, render_state_text};
+use processing::scenario::{check_scenario, generate_scenarios, parse_color_weights};
+use processing::server::{parse_listen_addr, Server, ServerOptions};
+use processing::simulator::simulate;
+use processing::solver::{compare_to_optimal, solved_to_fixture, solved_to_json, solved_to_text};
+use processing::topology::{analyse_topology, topology_to_json, topology_to_text};
+use processing::utils::get_action_string;
+use processing::validate::validate_state;
+
+/// Reads the non-empty lines from a file, or from stdin if no file is given.
+fn read_lines(file: &Option<String>) -> Result<Vec<String>, String> {
+    let contents = match file {
+        Some(f) => match fs::read_to_string(f) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Could not read {}: {}", f, e)),
+        },
+        None => {
+            let mut c = String::new();
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(l) => {
+                        c += &l;
+                        c += "\n";
+                    }
+                    Err(e) => return Err(format!("Error reading from stdin: {}", e)),
+                }
+            }
+            c
+        }
+    };
+
+    return Ok(contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.to_string())
+        .collect());
+}
+
+/// Parses a single JSON state, saying which line it came from on failure.
+fn parse_state(line: &str, line_no: usize) -> Result<State, String> {
+    return match serde_json::from_str(line) {
+        Ok(s) => Ok(s),
+        Err(e) => Err(format!("line {}: not a valid state: {}", line_no, e)),
+    };
+}
+
+/// Reads the single state a subcommand works on, given on the command line
+/// or as `-` to read it from stdin, and checks that it is valid.
+///
+/// # Arguments
+///
+/// * `state` - The JSON state, or `-` for stdin
+/// * `config` - The config for this run, with the gatekeeper's ID
+///
+/// # Returns
+///
+/// * `Result<State, String>` - The state, or every problem found with it
+fn read_single_state(state: &str, config: &Config) -> Result<State, String> {
+    let line = if state == "-" {
+        read_lines(&None)?.into_iter().next().unwrap_or_default()
+    } else {
+        state.to_string()
+    };
+    let data = parse_state(&line, 1)?;
+
+    let problems = validate_state(&data, config.gatekeeper_id);
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+
+    return Ok(data);
+}
+
+/// Reads an `--idle-timeout` in seconds, where 0 means no timeout.
+fn idle_timeout_duration(secs: u64) -> Option<Duration> {
+    return match secs {
+        0 => None,
+        s => Some(Duration::from_secs(s)),
+    };
+}
+
+/// Sets `flag` on `SIGINT` or `SIGTERM`, so a server can let what it is
+/// working on finish before shutting down.
+#[cfg(unix)]
+fn register_shutdown_signals(flag: &Arc<AtomicBool>) -> Result<(), String> {
+    for signal in &[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        if let Err(e) = signal_hook::flag::register(*signal, flag.clone()) {
+            return Err(format!("Could not handle signal {}: {}", signal, e));
+        }
+    }
+
+    return Ok(());
+}
+
+/// Runs a subcommand to completion.
+///
+/// # Arguments
+///
+/// * `command` - The subcommand to run
+/// * `config` - The config for this run
+///
+/// # Returns
+///
+/// * `Result<(), String>` - A message describing the failure, if the
+///                          command failed
+pub fn run_command(command: &Command, config: &Config) -> Result<(), String> {
+    match command {
+        Command::Help => print!("{}", USAGE),
+        Command::Version => println!("atlantis {}", env!("CARGO_PKG_VERSION")),
+        Command::Run => run_pearl_processing(config)?,
+        Command::Simulate { file, turns } => {
+            let lines = read_lines(file)?;
+            let initial = match lines.first() {
+                Some(l) => parse_state(l, 1)?,
+                None => return Err(String::from("No state to simulate from")),
+            };
+            let problems = validate_state(&initial, config.gatekeeper_id);
+            if !problems.is_empty() {
+                return Err(problems.join("\n"));
+            }
+            let ability_map = load_ability_map(config)?;
+
+            let (played, end) = simulate(&initial, &ability_map, config, *turns);
+            for (i, t) in played.into_iter().enumerate() {
+                println!(
+                    "turn {}: score {} actions {}",
+                    i,
+                    t.state.score,
+                    get_action_string(t.actions)
+                );
+            }
+
+            let left: usize = end.workers.iter().map(|w| w.desk.len()).sum();
+            println!("final score {} with {} pearls left", end.score, left);
+        }
+        Command::Validate { file } => {
+            let lines = read_lines(&Some(file.clone()))?;
+            let mut failures = 0;
+
+            for (i, l) in lines.iter().enumerate() {
+                let problems = match parse_state(l, i + 1) {
+                    Ok(s) => validate_state(&s, config.gatekeeper_id),
+                    Err(e) => vec![e],
+                };
+
+                for p in &problems {
+                    println!("line {}: {}", i + 1, p);
+                }
+                if !problems.is_empty() {
+                    failures += 1;
+                }
+            }
+
+            if failures > 0 {
+                return Err(format!(
+                    "{} of {} states in {} are invalid",
+                    failures,
+                    lines.len(),
+                    file
+                ));
+            }
+            println!("{} states in {} are valid", lines.len(), file);
+        }
+        Command::Explain { state, json } => {
+            let data = read_single_state(state, config)?;
+
+            let ability_map = load_ability_map(config)?;
+            let explanation = explain_state(&data, &ability_map, config);
+
+            if *json {
+                println!("{}", explanation_to_json(&explanation));
+            } else {
+                print!("{}", explanation_to_text(&explanation));
+            }
+        }
+        Command::Topology { state, hops, json } => {
+            let data = read_single_state(state, config)?;
+
+            let report = analyse_topology(&data, config, *hops);
+            if *json {
+                println!("{}", topology_to_json(&report));
+            } else {
+                print!("{}", topology_to_text(&report));
+            }
+        }
+        Command::AdviseLayout {
+            state,
+            colors,
+            json,
+        } => {
+            let data = read_single_state(state, config)?;
+
+            let mut config = config.clone();
+            if let Some(c) = colors {
+                config.scenario.colors = parse_color_weights(c)?;
+            }
+
+            let ability_map = load_ability_map(&config)?;
+            let advice = advise_layout(&data, &ability_map, &config)?;
+            if *json {
+                println!("{}", layout_to_json(&advice));
+            } else {
+                print!("{}", layout_to_text(&advice));
+            }
+        }
+        Command::Bound {
+            state,
+            scenario,
+            turns,
+            json,
+        } => {
+            let data = read_single_state(state, config)?;
+
+            let ability_map = load_ability_map(config)?;
+            let report = if *scenario {
+                let problems = check_scenario(&config.scenario);
+                if !problems.is_empty() {
+                    return Err(problems.join("\n"));
+                }
+                let games = generate_scenarios(&data, &config.scenario, config.gatekeeper_id);
+                let max_turns = turns.unwrap_or(config.scenario.turns);
+                bound_report(&games, &ability_map, config, max_turns)
+            } else {
+                bound_report(&[data], &ability_map, config, turns.unwrap_or(1000))
+            };
+
+            if *json {
+                println!("{}", bound_to_json(&report));
+            } else {
+                print!("{}", bound_to_text(&report));
+            }
+        }
+        Command::Solve {
+            state,
+            scenario,
+            max_states,
+            turns,
+            fixtures,
+            json,
+        } => {
+            let data = read_single_state(state, config)?;
+
+            let ability_map = load_ability_map(config)?;
+            let (games, max_turns) = if *scenario {
+                let problems = check_scenario(&config.scenario);
+                if !problems.is_empty() {
+                    return Err(problems.join("\n"));
+                }
+                (
+                    generate_scenarios(&data, &config.scenario, config.gatekeeper_id),
+                    turns.unwrap_or(config.scenario.turns),
+                )
+            } else {
+                (vec![data], turns.unwrap_or(1000))
+            };
+
+            let mut solved = Vec::new();
+            for (i, g) in games.iter().enumerate() {
+                match compare_to_optimal(g, &ability_map, config, *max_states, max_turns) {
+                    Ok(s) => solved.push(s),
+                    Err(e) => return Err(format!("game {}: {}", i, e)),
+                }
+            }
+
+            if let Some(f) = fixtures {
+                let mut file = match OpenOptions::new().create(true).append(true).open(f) {
+                    Ok(file) => file,
+                    Err(e) => return Err(format!("Could not open {}: {}", f, e)),
+                };
+                for (g, s) in games.iter().zip(&solved) {
+                    if !s.greedy_is_suboptimal() {
+                        continue;
+                    }
+                    if let Err(e) = writeln!(file, "{}", solved_to_fixture(g, s)) {
+                        return Err(format!("Could not write to {}: {}", f, e));
+                    }
+                }
+            }
+
+            if *json {
+                println!("{}", solved_to_json(&solved));
+            } else {
+                print!("{}", solved_to_text(&solved));
+            }
+        }
+        Command::Render { file, dot } => {
+            for (i, l) in read_lines(file)?.iter().enumerate() {
+                let data = parse_state(l, i + 1)?;
+                if *dot {
+                    print!("{}", render_state_dot(&data, config.gatekeeper_id));
+                } else {
+                    print!("{}", render_state_text(&data, config.gatekeeper_id));
+                }
+            }
+        }
+        Command::Serve {
+            listen,
+            idle_timeout,
+        } => {
+            let addr = parse_listen_addr(listen)?;
+            let options = ServerOptions {
+                idle_timeout: idle_timeout_duration(*idle_timeout),
+                ..ServerOptions::default()
+            };
+            let server = Server::bind(&addr, config.clone(), options)?;
+
+            #[cfg(unix)]
+            register_shutdown_signals(&server.shutdown_flag())?;
+
+            info!(addr = server.local_addr().as_str(); "listening");
+            server.run()?;
+        }
+        #[cfg(feature = "http")]
+        Command::Http {
+            listen,
+            idle_timeout,
+        } => {
+            let options = ServerOptions {
+                idle_timeout: idle_timeout_duration(*idle_timeout),
+                ..ServerOptions::default()
+            };
+            let mut server = HttpServer::bind(listen, config.clone(), options)?;
+
+            #[cfg(unix)]
+            register_shutdown_signals(&server.shutdown_flag())?;
+
+            info!(addr = server.local_addr().as_str(); "listening for http");
+            server.run()?;
+        }
+        #[cfg(not(feature = "http"))]
+        Command::Http { .. } => {
+            return Err(String::from(
+                "This build doesn't include the HTTP API, rebuild with --features http",
+            ));
+        }
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,330 @@
+use crate::processing::config::{parse_config_args, ConfigArgs};
+use crate::processing::solver::DEFAULT_MAX_STATES;
+
+/// The exit code when the command line can't be understood
+pub const EXIT_USAGE: i32 = 2;
+
+/// The exit code when the command itself fails
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Printed for `--help`, and whenever the command line can't be understood
+pub const USAGE: &str = "\
+Automates Atlantis' pearl processing pipeline.
+
+Usage: atlantis [OPTIONS] [COMMAND]
+
+Commands:
+  run                    Read states from stdin and write actions to stdout (default)
+  simulate [FILE]        Play a game forward from the first state in FILE (or stdin)
+      --turns <N>        Stop after N turns [default: 1000]
+  validate <FILE>        Check every state in FILE, one JSON state per line
+  explain <STATE>        Show why each worker takes the action it does for a single
+                         JSON state, or read it from stdin if STATE is -
+      --json             Write the explanation as JSON instead of text
+  render [FILE]          Draw every state in FILE (or stdin)
+      --dot              Draw as a Graphviz graph instead of text
+  topology <STATE>       Report the components, distances and weak spots of the
+                         floor in a single JSON state, or read it from stdin if
+                         STATE is -
+      --hops <K>         List the flavors within K hops of the gatekeeper
+                         [default: 2]
+      --json             Write the report as JSON instead of text
+  advise-layout <STATE>  Suggest a flavor for every worker on the floor in a single
+                         JSON state, or read it from stdin if STATE is -, by
+                         simulating the games in the scenario section of the config
+      --colors <WEIGHTS> How likely each color is, e.g. Red=2,Green=1,Blue=1
+                         [default: the scenario config]
+      --json             Write the advice as JSON instead of text
+  bound <STATE>          Compare how long greedy takes to deliver every pearl in a
+                         single JSON state, or read it from stdin if STATE is -,
+                         to a lower bound on how long any strategy could take
+      --scenario         Play the games in the scenario section of the config on
+                         the state's floor instead of its own pearls
+      --turns <N>        Stop greedy after N turns [default: 1000, or the
+                         scenario's turns]
+      --json             Write the report as JSON instead of text
+  solve <STATE>          Find the fewest turns it takes to deliver every pearl in a
+                         single JSON state, or read it from stdin if STATE is -,
+                         and compare greedy to it. Only small games can be solved
+      --scenario         Solve the games in the scenario section of the config on
+                         the state's floor instead of its own pearls
+      --max-states <N>   Give up after searching N states [default: 1000000]
+      --turns <N>        Stop greedy after N turns [default: 1000, or the
+                         scenario's turns]
+      --fixtures <FILE>  Append every game greedy doesn't solve optimally to FILE
+      --json             Write the results as JSON instead of text
+  serve                  Play many games at once, one per connection, until
+                         interrupted
+      --listen <ADDR>    Listen on tcp:<HOST>:<PORT> or unix:<PATH>
+      --idle-timeout <S> Close a connection after S idle seconds, 0 for never
+                         [default: 300]
+  http                   Answer decisions over HTTP (needs the http feature)
+      --listen <ADDR>    Listen on <HOST>:<PORT>
+      --idle-timeout <S> Forget a session after S idle seconds, 0 for never
+                         [default: 300]
+
+Options:
+  --config <PATH>        Read the config from PATH [default: atlantis.json]
+  --set <KEY>=<VALUE>    Override a single config key, e.g. strategy.min_pass_gain=2
+  --gatekeeper-id <ID>   Shorthand for --set gatekeeper_id=<ID>
+  --ability-map <PATH>   Shorthand for --set ability_map_path=<PATH>
+  -h, --help             Print this help
+  -V, --version          Print the version
+";
+
+/// A subcommand, along with its own arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run,
+    Simulate {
+        file: Option<String>,
+        turns: u32,
+    },
+    Validate {
+        file: String,
+    },
+    Explain {
+        state: String,
+        json: bool,
+    },
+    Render {
+        file: Option<String>,
+        dot: bool,
+    },
+    Topology {
+        state: String,
+        hops: u32,
+        json: bool,
+    },
+    AdviseLayout {
+        state: String,
+        colors: Option<String>,
+        json: bool,
+    },
+    Bound {
+        state: String,
+        scenario: bool,
+        turns: Option<u32>,
+        json: bool,
+    },
+    Solve {
+        state: String,
+        scenario: bool,
+        max_states: u64,
+        turns: Option<u32>,
+        fixtures: Option<String>,
+        json: bool,
+    },
+    Serve {
+        listen: String,
+        idle_timeout: u64,
+    },
+    Http {
+        listen: String,
+        idle_timeout: u64,
+    },
+    Help,
+    Version,
+}
+
+/// Works out which subcommand to run from the arguments left over once the
+/// config flags have been removed. With no arguments at all, the pipeline
+/// runs as it always has.
+///
+/// # Arguments
+///
+/// * `args` - The remaining command line arguments
+///
+/// # Returns
+///
+/// * `Result<Command, String>` - The command, or a message describing what
+///                               was wrong with the arguments
+pub fn parse_command(args: &[String]) -> Result<Command, String> {
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        return Ok(Command::Help);
+    }
+    if args.iter().any(|a| a == "-V" || a == "--version") {
+        return Ok(Command::Version);
+    }
+
+    let name = match args.first() {
+        Some(n) => n.as_str(),
+        None => return Ok(Command::Run),
+    };
+
+    let mut positional: Vec<String> = Vec::new();
+    let mut turns: Option<u32> = None;
+    let mut hops: u32 = 2;
+    let mut dot = false;
+    let mut scenario = false;
+    let mut json = false;
+    let mut colors: Option<String> = None;
+    let mut max_states: u64 = DEFAULT_MAX_STATES;
+    let mut fixtures: Option<String> = None;
+    let mut listen: Option<String> = None;
+    let mut idle_timeout: u64 = 300;
+    let mut iter = args[1..].iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--turns" if name == "simulate" || name == "bound" || name == "solve" => {
+                turns = match iter.next().map(|t| t.parse::<u32>()) {
+                    Some(Ok(t)) => Some(t),
+                    _ => return Err(String::from("--turns needs a whole number")),
+                };
+            }
+            "--hops" if name == "topology" => {
+                hops = match iter.next().map(|h| h.parse::<u32>()) {
+                    Some(Ok(h)) => h,
+                    _ => return Err(String::from("--hops needs a whole number")),
+                };
+            }
+            "--dot" if name == "render" => dot = true,
+            "--scenario" if name == "bound" || name == "solve" => scenario = true,
+            "--max-states" if name == "solve" => {
+                max_states = match iter.next().map(|m| m.parse::<u64>()) {
+                    Some(Ok(m)) => m,
+                    _ => return Err(String::from("--max-states needs a whole number")),
+                };
+            }
+            "--fixtures" if name == "solve" => {
+                fixtures = match iter.next() {
+                    Some(f) => Some(f.clone()),
+                    None => return Err(String::from("--fixtures needs a file")),
+                };
+            }
+            "--colors" if name == "advise-layout" => {
+                colors = match iter.next() {
+                    Some(c) => Some(c.clone()),
+                    None => return Err(String::from("--colors needs color weights")),
+                };
+            }
+            "--json"
+                if name == "explain"
+                    || name == "topology"
+                    || name == "advise-layout"
+                    || name == "bound"
+                    || name == "solve" =>
+            {
+                json = true
+            }
+            "--listen" if name == "serve" || name == "http" => {
+                listen = match iter.next() {
+                    Some(l) => Some(l.clone()),
+                    None => return Err(String::from("--listen needs an address")),
+                };
+            }
+            "--idle-timeout" if name == "serve" || name == "http" => {
+                idle_timeout = match iter.next().map(|t| t.parse::<u64>()) {
+                    Some(Ok(t)) => t,
+                    _ => return Err(String::from("--idle-timeout needs a whole number")),
+                };
+            }
+            "-" => positional.push(arg.clone()),
+            a if a.starts_with('-') => {
+                return Err(format!("Unknown option {} for {}", a, name));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let max_positional = match name {
+        "run" | "serve" | "http" => 0,
+        _ => 1,
+    };
+    if positional.len() > max_positional {
+        return Err(format!("Too many arguments for {}", name));
+    }
+
+    return match name {
+        "run" => Ok(Command::Run),
+        "simulate" => Ok(Command::Simulate {
+            file: positional.pop(),
+            turns: turns.unwrap_or(1000),
+        }),
+        "validate" => match positional.pop() {
+            Some(f) => Ok(Command::Validate { file: f }),
+            None => Err(String::from("validate needs a file to check")),
+        },
+        "explain" => match positional.pop() {
+            Some(s) => Ok(Command::Explain {
+                state: s,
+                json: json,
+            }),
+            None => Err(String::from("explain needs a state to explain")),
+        },
+        "render" => Ok(Command::Render {
+            file: positional.pop(),
+            dot: dot,
+        }),
+        "topology" => match positional.pop() {
+            Some(s) => Ok(Command::Topology {
+                state: s,
+                hops: hops,
+                json: json,
+            }),
+            None => Err(String::from("topology needs a state to analyse")),
+        },
+        "advise-layout" => match positional.pop() {
+            Some(s) => Ok(Command::AdviseLayout {
+                state: s,
+                colors: colors,
+                json: json,
+            }),
+            None => Err(String::from("advise-layout needs a state with a floor")),
+        },
+        "bound" => match positional.pop() {
+            Some(s) => Ok(Command::Bound {
+                state: s,
+                scenario: scenario,
+                turns: turns,
+                json: json,
+            }),
+            None => Err(String::from("bound needs a state to bound")),
+        },
+        "solve" => match positional.pop() {
+            Some(s) => Ok(Command::Solve {
+                state: s,
+                scenario: scenario,
+                max_states: max_states,
+                turns: turns,
+                fixtures: fixtures,
+                json: json,
+            }),
+            None => Err(String::from("solve needs a state to solve")),
+        },
+        "serve" => match listen {
+            Some(l) => Ok(Command::Serve {
+                listen: l,
+                idle_timeout: idle_timeout,
+            }),
+            None => Err(String::from("serve needs an address to --listen on")),
+        },
+        "http" => match listen {
+            Some(l) => Ok(Command::Http {
+                listen: l,
+                idle_timeout: idle_timeout,
+            }),
+            None => Err(String::from("http needs an address to --listen on")),
+        },
+        _ => Err(format!("Unknown command {}", name)),
+    };
+}
+
+/// Splits a whole command line into the config flags and the subcommand,
+/// as `parse_config_args` and then `parse_command` would.
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments, not including the program name
+///
+/// # Returns
+///
+/// * `Result<(ConfigArgs, Command), String>` - The config flags and the
+///                                             command, or a message
+///                                             describing what was wrong
+pub fn parse_args(args: &[String]) -> Result<(ConfigArgs, Command), String> {
+    let config_args = parse_config_args(args)?;
+    let command = parse_command(&config_args.rest)?;
+    return Ok((config_args, command));
+}
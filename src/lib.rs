@@ -0,0 +1,36 @@
+//! Automating Atlantis' pearl processing production pipeline.
+//!
+//! The `models` module holds the data types read from and written to the
+//! harness, `processing` holds the decision logic, and `command` reads the
+//! command line. Most embedders only
+//! need [`Pipeline`], which decides the actions for one game a turn at a
+//! time:
+//!
+//! ```no_run
+//! use atlantis::models::config::Config;
+//! use atlantis::models::state::State;
+//! use atlantis::Pipeline;
+//!
+//! let mut pipeline = Pipeline::new(Config::default()).unwrap();
+//! # let line = String::new();
+//! let state: State = serde_json::from_str(&line).unwrap();
+//! let actions = pipeline.step(&state);
+//! ```
+
+// The codebase favours explicit returns and aligned argument docs
+#![allow(
+    clippy::needless_return,
+    clippy::doc_overindented_list_items,
+    clippy::redundant_field_names,
+    clippy::len_zero,
+    clippy::needless_late_init,
+    clippy::unwrap_or_default
+)]
+
+pub mod command;
+
+pub mod models;
+
+pub mod processing;
+
+pub use processing::pearl_pipeline::Pipeline;
@@ -0,0 +1,54 @@
+use env_logger::{Builder, Target, WriteStyle};
+use log::LevelFilter;
+use std::env;
+use std::fs::OpenOptions;
+
+/// The environment variable holding the log filter
+pub const LOG_ENV: &str = "ATLANTIS_LOG";
+
+/// Sets up logging for the binary. Log messages never go to `stdout`, which
+/// is kept for the actions; they go to `stderr`, or are appended to
+/// `log_path` if it is set.
+///
+/// Which messages are logged is read from `ATLANTIS_LOG`, which takes a
+/// level (`error`, `warn`, `info`, `debug` or `trace`) and optionally
+/// different levels for different modules, e.g.
+/// `ATLANTIS_LOG=warn,atlantis::processing::utils=trace`. Without it, `info`
+/// and above are logged.
+///
+/// # Arguments
+///
+/// * `log_path` - The file to append log messages to, if not `stderr`
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the log file couldn't be opened
+pub fn init_logging(log_path: Option<&str>) -> Result<(), String> {
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info);
+
+    if let Ok(filters) = env::var(LOG_ENV) {
+        builder.parse_filters(&filters);
+    }
+
+    match log_path {
+        Some(path) => {
+            let file = match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(f) => f,
+                Err(e) => return Err(format!("Could not open log file {}: {}", path, e)),
+            };
+            builder.target(Target::Pipe(Box::new(file)));
+        }
+        None => {
+            builder.target(Target::Stderr);
+        }
+    }
+
+    // Colors would only end up as escape codes in a file or a harness' log
+    builder.write_style(WriteStyle::Never);
+
+    return match builder.try_init() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Could not set up logging: {}", e)),
+    };
+}
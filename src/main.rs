@@ -1,13 +1,43 @@
-pub mod models;
+// The codebase favours explicit returns and aligned argument docs
+#![allow(
+    clippy::needless_return,
+    clippy::doc_overindented_list_items,
+    clippy::redundant_field_names
+)]
 
-pub mod processing;
-use processing::pearl_pipeline::run_pearl_processing;
+mod cli;
+mod logging;
+use atlantis::command::{parse_args, EXIT_FAILURE, EXIT_USAGE, USAGE};
+use atlantis::processing::config::load_config;
+use cli::run_command;
+use logging::init_logging;
 
-/// Main method for program, simply runs the `run_pearl_processing`
-/// method defined in the `processing` module.
+/// Main method for program. Pulls the config flags out of the command line
+/// and loads the config (from `atlantis.json` or the `--config` flag, with
+/// any `--set` overrides applied), sets up logging, then runs the requested
+/// subcommand.
+///
+/// Exits with code 2 if the command line can't be understood, and code 1
+/// if the command itself fails.
 fn main() {
-    run_pearl_processing(
-        // &mut std::io::stdin(),
-        // &mut std::io::stdout()
-    );
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (config_args, command) = match parse_args(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    let result =
+        load_config(config_args.path.as_deref(), &config_args.overrides).and_then(|config| {
+            init_logging(config.log_path.as_deref())?;
+            return run_command(&command, &config);
+        });
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(EXIT_FAILURE);
+    }
 }
@@ -1,7 +1,13 @@
-use serde::Deserialize;
-use std::ops::Index;
+use serde::{Deserialize, Serialize};
+use std::ops::{Index, IndexMut};
 
-#[derive(Deserialize, Debug)]
+/// Every flavor of nautiloid an `AbilityMap` knows about
+pub const FLAVORS: [&str; 3] = ["General", "Vector", "Matrix"];
+
+/// Every color of pearl layer a `Color` knows about
+pub const COLORS: [&str; 3] = ["Red", "Green", "Blue"];
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Color {
     pub red: u32,
@@ -9,7 +15,7 @@ pub struct Color {
     pub blue: u32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct AbilityMap {
     pub general: Color,
@@ -17,6 +23,53 @@ pub struct AbilityMap {
     pub matrix: Color,
 }
 
+impl AbilityMap {
+    /// How much of a layer of `color` a worker of `flavor` gets through in
+    /// one nom. Unlike indexing, an unknown flavor or color is an error
+    /// rather than a panic.
+    ///
+    /// # Arguments
+    ///
+    /// * `flavor` - The flavor of the worker nomming
+    /// * `color` - The color of the layer being nommed
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, String>` - The rate, or an error naming the flavor or
+    ///                           color that isn't known
+    pub fn rate(&self, flavor: &str, color: &str) -> Result<u32, String> {
+        let colors = match flavor {
+            "General" => &self.general,
+            "Vector" => &self.vector,
+            "Matrix" => &self.matrix,
+            _ => return Err(format!("Ability Map Error: unknown flavor {}", flavor)),
+        };
+
+        return colors.rate(color);
+    }
+}
+
+impl Color {
+    /// How much of a layer of `color` is nommed in one go.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The color of the layer being nommed
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, String>` - The rate, or an error if the color isn't
+    ///                           known
+    pub fn rate(&self, color: &str) -> Result<u32, String> {
+        return match color {
+            "Red" => Ok(self.red),
+            "Green" => Ok(self.green),
+            "Blue" => Ok(self.blue),
+            _ => Err(format!("Ability Map Error: unknown color {}", color)),
+        };
+    }
+}
+
 impl Index<&'_ str> for AbilityMap {
     type Output = Color;
     fn index(&self, s: &str) -> &Color {
@@ -40,3 +93,25 @@ impl Index<&'_ str> for Color {
         }
     }
 }
+
+impl IndexMut<&'_ str> for AbilityMap {
+    fn index_mut(&mut self, s: &str) -> &mut Color {
+        match s {
+            "General" => &mut self.general,
+            "Vector" => &mut self.vector,
+            "Matrix" => &mut self.matrix,
+            _ => panic!("Type AbilityMap does not have field {}", s),
+        }
+    }
+}
+
+impl IndexMut<&'_ str> for Color {
+    fn index_mut(&mut self, s: &str) -> &mut u32 {
+        match s {
+            "Red" => &mut self.red,
+            "Green" => &mut self.green,
+            "Blue" => &mut self.blue,
+            _ => panic!("Type AbilityMap does not have field {}", s),
+        }
+    }
+}
@@ -1,16 +1,21 @@
-#[derive(Debug)]
+use std::collections::HashMap;
+
+/// The action each worker takes in a turn, keyed by worker ID
+pub type Actions = HashMap<u32, ActionType>;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pass {
     pub from_id: u32,
     pub pearl_id: u32,
     pub to_id: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Nom {
     pub nautiloid_id: u32,
     pub pearl_id: u32,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ActionType {
     Pass(Pass),
     Nom(Nom),
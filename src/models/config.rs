@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::gatekeeper_policy::GatekeeperPolicy;
+
+/// How to choose between options that are otherwise equally good.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum TieBreak {
+    /// Keep the first option found
+    First,
+    /// Keep the last option found
+    Last,
+    /// Keep the option with the lowest pearl ID
+    LowestId,
+    /// Keep the option with the highest pearl ID
+    HighestId,
+}
+
+/// The thresholds and tie-break rules used by the greedy strategy.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// How many turns faster a neighbor must be before a pearl is passed
+    /// to them
+    pub min_pass_gain: u32,
+    /// A pearl may also be passed to a neighbor that is exactly as fast,
+    /// as long as the worker has at least this many pearls on their desk
+    pub equal_pass_min_desk: usize,
+    /// How many more turns faster a neighbor must be to be passed a pearl
+    /// that is part way through a layer. Such pearls are never passed to a
+    /// neighbor that is only as fast unless this is 0.
+    pub partial_pass_gain: u32,
+    /// Which candidate wins when several passes qualify
+    pub pass_tie_break: TieBreak,
+    /// Which pearl is nommed when several are equally fast to process
+    pub nom_tie_break: TieBreak,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> StrategyConfig {
+        return StrategyConfig {
+            min_pass_gain: 1,
+            equal_pass_min_desk: 2,
+            partial_pass_gain: 0,
+            pass_tie_break: TieBreak::Last,
+            nom_tie_break: TieBreak::First,
+        };
+    }
+}
+
+/// The cost of passing a pearl between two particular workers, in either
+/// direction.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EdgeCost {
+    pub workers: [u32; 2],
+    pub cost: u32,
+}
+
+/// How many turns a pass costs a pearl on top of the turn spent passing it,
+/// while it is in transit and nobody can work on it.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransferConfig {
+    /// The cost of a pass over any edge not listed in `edges`
+    pub cost: u32,
+    /// Edges that cost something other than `cost`
+    pub edges: Vec<EdgeCost>,
+}
+
+/// How to generate the pearls for a simulated game on a given floor, and
+/// how many games to play. Every pearl starts on the gatekeeper's desk.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScenarioConfig {
+    /// How many pearls each game starts with
+    pub pearls: u32,
+    /// The fewest and most layers a pearl can have
+    pub layers: [u32; 2],
+    /// The thinnest and thickest a layer can be
+    pub thickness: [u32; 2],
+    /// How likely each color of layer is, relative to the others. Every
+    /// color is equally likely when this is empty.
+    pub colors: HashMap<String, f64>,
+    /// How many games to average over
+    pub samples: u32,
+    /// The most turns each game is played for
+    pub turns: u32,
+    /// Seeds the pearls generated, so results can be repeated
+    pub seed: u64,
+}
+
+impl Default for ScenarioConfig {
+    fn default() -> ScenarioConfig {
+        return ScenarioConfig {
+            pearls: 20,
+            layers: [1, 3],
+            thickness: [5, 15],
+            colors: HashMap::new(),
+            samples: 5,
+            turns: 200,
+            seed: 0,
+        };
+    }
+}
+
+/// Which strategy decides each turn.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Planner {
+    /// The rule based strategy described in the README
+    Greedy,
+    /// Monte Carlo tree search, configured by the `mcts` section
+    Mcts,
+}
+
+/// How the Monte Carlo tree search plays out the rest of a game from each
+/// position it tries.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Rollout {
+    /// Every worker takes a random legal action
+    Random,
+    /// Every worker does what the greedy strategy would
+    Greedy,
+}
+
+/// The settings for the Monte Carlo tree search strategy.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MctsConfig {
+    /// How many playouts to run each turn, unless `turn_budget_ms` runs out
+    /// first
+    pub iterations: u32,
+    /// Seeds the search, so the same game always gets the same actions
+    pub seed: u64,
+    /// How playouts continue once they leave the tree
+    pub rollout: Rollout,
+    /// How many turns ahead each playout looks
+    pub depth: u32,
+    /// How strongly the search favours trying actions it knows little about
+    /// over ones that have done well
+    pub exploration: f64,
+    /// How many random sets of actions are tried alongside the greedy ones
+    /// at each position
+    pub candidates: u32,
+    /// How many new pearls reach the gatekeeper each turn on average, drawn
+    /// from a Poisson distribution. Their layers are drawn as described by
+    /// the `scenario` section.
+    pub arrival_rate: f64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> MctsConfig {
+        return MctsConfig {
+            iterations: 200,
+            seed: 0,
+            rollout: Rollout::Greedy,
+            depth: 30,
+            exploration: 1.0,
+            candidates: 8,
+            arrival_rate: 0.0,
+        };
+    }
+}
+
+/// Where the metrics gathered during a run are written when it ends.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Write the metrics here as JSON. They go to `stderr` when this is not
+    /// set.
+    pub json_path: Option<String>,
+    /// Also write the metrics here in the Prometheus text format
+    pub prometheus_path: Option<String>,
+}
+
+/// Everything about the pipeline that can be tuned at startup. Any key left
+/// out of the config file keeps its default value.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The ID of the nautiloid that pearls enter and leave through
+    pub gatekeeper_id: u32,
+    /// Where to read the ability map from. The copy built into the binary
+    /// is used when this is not set.
+    pub ability_map_path: Option<String>,
+    /// Make decisions with the ability map learned from the states as they
+    /// arrive, instead of the static one
+    pub learn_ability_map: bool,
+    /// How many turns ahead the score projection estimates throughput over
+    pub score_horizon: u32,
+    /// How many milliseconds an anytime strategy, such as `Mcts`, may spend
+    /// deciding a turn. It stops early with its best plan so far, and the
+    /// greedy actions are used if it has none. `Greedy` always runs to
+    /// completion, as it is the fallback. Unlimited when not set.
+    pub turn_budget_ms: Option<u64>,
+    /// Append log messages to this file instead of writing them to `stderr`.
+    /// Which messages are logged is set by the `ATLANTIS_LOG` environment
+    /// variable.
+    pub log_path: Option<String>,
+    /// Which strategy decides each turn
+    pub planner: Planner,
+    pub strategy: StrategyConfig,
+    pub gatekeeper: GatekeeperPolicy,
+    pub transfer: TransferConfig,
+    pub scenario: ScenarioConfig,
+    pub mcts: MctsConfig,
+    pub metrics: MetricsConfig,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        return Config {
+            gatekeeper_id: 0,
+            ability_map_path: None,
+            learn_ability_map: false,
+            score_horizon: 10,
+            turn_budget_ms: None,
+            log_path: None,
+            planner: Planner::Greedy,
+            strategy: StrategyConfig::default(),
+            gatekeeper: GatekeeperPolicy::default(),
+            transfer: TransferConfig::default(),
+            scenario: ScenarioConfig::default(),
+            mcts: MctsConfig::default(),
+            metrics: MetricsConfig::default(),
+        };
+    }
+}
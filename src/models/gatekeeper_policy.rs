@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// Tunable parameters for how the gatekeeper handles the
+/// pearls that arrive on its desk. The defaults are read from
+/// `gatekeeper_policy.json`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GatekeeperPolicy {
+    /// The most pearls allowed away from the gatekeeper's desk at once.
+    /// Once this many are out in the network the gatekeeper noms instead
+    /// of dispatching.
+    pub max_in_flight: u32,
+    /// Estimated number of turns a single pass costs a pearl.
+    pub hop_cost: u32,
+    /// How many turns the network must beat the gatekeeper by before a
+    /// pearl is dispatched instead of nommed in place.
+    pub self_nom_margin: u32,
+}
+
+impl Default for GatekeeperPolicy {
+    fn default() -> GatekeeperPolicy {
+        let json_data = include_str!("../static_files/gatekeeper_policy.json");
+
+        let policy: GatekeeperPolicy = match serde_json::from_str(json_data) {
+            Ok(p) => p,
+            Err(e) => {
+                panic!("File read error: {}", e)
+            }
+        };
+
+        return policy;
+    }
+}
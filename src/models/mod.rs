@@ -1,3 +1,5 @@
 pub mod ability_map;
 pub mod action;
+pub mod config;
+pub mod gatekeeper_policy;
 pub mod state;
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
+use std::ops::Index;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 // These types are helpful for making code cleaner and more readable,
 // especially in other files that reference them
@@ -8,7 +9,8 @@ pub type Desk = Vec<Pearl>;
 pub type Layers = Vec<Layer>;
 pub type Workers = Vec<Worker>;
 pub type NeighborMap = Vec<Vec<u32>>;
-pub type NeighborGraph = HashMap<u32, Vec<u32>>;
+pub type Edges = Vec<Edge>;
+pub type PearlPaths = HashMap<u32, Vec<u32>>;
 
 #[derive(Debug)]
 pub struct WorkerPearlIDs {
@@ -16,28 +18,214 @@ pub struct WorkerPearlIDs {
     pub pearl_id: u32,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Layer {
     pub color: String,
+    /// How much of the layer is left to process
     pub thickness: u32,
+    /// How thick the layer was before anyone started processing it, if the
+    /// harness says or the pipeline has seen it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_thickness: Option<u32>,
 }
 
-#[derive(Deserialize, Clone)]
+impl Layer {
+    /// How thick the layer was to start with, as far as is known
+    pub fn original(&self) -> u32 {
+        return self.original_thickness.unwrap_or(0).max(self.thickness);
+    }
+
+    /// How much of the layer has already been processed
+    pub fn processed(&self) -> u32 {
+        return self.original() - self.thickness;
+    }
+
+    /// Whether some, but not all, of the layer has been processed
+    pub fn is_partial(&self) -> bool {
+        return self.processed() > 0;
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Pearl {
     pub id: u32,
     pub layers: Layers,
 }
 
-#[derive(Deserialize, Clone)]
+impl Pearl {
+    /// Whether work has already been invested in the outermost layer, so
+    /// the pearl is part way through a layer
+    pub fn is_partial(&self) -> bool {
+        return self.layers.first().is_some_and(|l| l.is_partial());
+    }
+
+    /// How much of the pearl's remaining layers has already been processed,
+    /// from 0.0 to 1.0. Layers that were dissolved entirely are gone from
+    /// the state, so they aren't counted. A finished pearl is 1.0.
+    pub fn progress(&self) -> f64 {
+        let original: u64 = self.layers.iter().map(|l| l.original() as u64).sum();
+        let processed: u64 = self.layers.iter().map(|l| l.processed() as u64).sum();
+
+        if original == 0 {
+            return 1.0;
+        }
+
+        return processed as f64 / original as f64;
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Worker {
     pub desk: Desk,
     pub flavor: String,
     pub id: u32,
 }
 
-#[derive(Deserialize, Clone)]
+/// Extra detail about the connection between two workers. An edge joins
+/// its workers even if the neighbor map doesn't.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// The two workers joined by the edge. A one-way edge only carries
+    /// pearls from the first to the second.
+    pub workers: [u32; 2],
+    /// How many turns a pearl spends in transit over the edge, instead of
+    /// the cost in the config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<u32>,
+    /// The most pearls that can be in transit over the edge at once, each
+    /// way. An edge with a capacity of 0 is closed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<u32>,
+    #[serde(default)]
+    pub one_way: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct State {
     pub workers: Workers,
     pub neighbor_map: NeighborMap,
+    /// Attributes of particular edges. Pairs in the neighbor map that
+    /// aren't listed here are free, uncapped and go both ways.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Edges,
     pub score: u32,
 }
+
+/// The cost and capacity of passing from one worker to another.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EdgeAttributes {
+    pub cost: Option<u32>,
+    pub capacity: Option<u32>,
+}
+
+/// Who each worker can pass pearls to, and what passing to them costs.
+/// Every edge is directed, so a pair of workers that can pass both ways
+/// has an edge each way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NeighborGraph {
+    neighbors: HashMap<u32, Vec<u32>>,
+    /// The same edges the other way round, kept sorted, so routing home
+    /// doesn't have to search every worker's neighbors
+    incoming: HashMap<u32, Vec<u32>>,
+    attributes: HashMap<(u32, u32), EdgeAttributes>,
+}
+
+impl NeighborGraph {
+    pub fn new() -> NeighborGraph {
+        return NeighborGraph::default();
+    }
+
+    /// Adds a worker with no neighbors, if it isn't in the graph already
+    pub fn add_worker(&mut self, id: u32) {
+        self.neighbors.entry(id).or_default();
+        self.incoming.entry(id).or_default();
+    }
+
+    /// Lets `from` pass pearls to `to`
+    pub fn add_edge(&mut self, from: u32, to: u32) {
+        self.add_worker(from);
+        self.add_worker(to);
+
+        let nbrs = self.neighbors.entry(from).or_default();
+        if !nbrs.contains(&to) {
+            nbrs.push(to);
+        }
+
+        let from_ids = self.incoming.entry(to).or_default();
+        if let Err(i) = from_ids.binary_search(&from) {
+            from_ids.insert(i, from);
+        }
+    }
+
+    /// Stops `from` passing pearls to `to`
+    pub fn remove_edge(&mut self, from: u32, to: u32) {
+        if let Some(nbrs) = self.neighbors.get_mut(&from) {
+            nbrs.retain(|&n| n != to);
+        }
+        if let Some(from_ids) = self.incoming.get_mut(&to) {
+            from_ids.retain(|&n| n != from);
+        }
+        self.attributes.remove(&(from, to));
+    }
+
+    /// Sets the cost and capacity of passing from `from` to `to`
+    pub fn set_attributes(&mut self, from: u32, to: u32, attributes: EdgeAttributes) {
+        self.attributes.insert((from, to), attributes);
+    }
+
+    /// The workers a worker can pass pearls to, in the order they were added
+    pub fn get(&self, id: &u32) -> Option<&Vec<u32>> {
+        return self.neighbors.get(id);
+    }
+
+    /// Whether `from` can pass pearls to `to`
+    pub fn has_edge(&self, from: u32, to: u32) -> bool {
+        return self.neighbors.get(&from).is_some_and(|n| n.contains(&to));
+    }
+
+    /// The attributes of the edge from `from` to `to`, if any were given
+    pub fn attributes(&self, from: u32, to: u32) -> Option<&EdgeAttributes> {
+        return self.attributes.get(&(from, to));
+    }
+
+    /// The cost given for the edge from `from` to `to`, or 0
+    pub fn cost(&self, from: u32, to: u32) -> u32 {
+        return self.attributes(from, to).and_then(|a| a.cost).unwrap_or(0);
+    }
+
+    /// The capacity of the edge from `from` to `to`, or None if unlimited
+    pub fn capacity(&self, from: u32, to: u32) -> Option<u32> {
+        return self.attributes(from, to).and_then(|a| a.capacity);
+    }
+
+    /// Every edge with attributes, and what they are
+    pub fn attributed_edges(&self) -> impl Iterator<Item = (&(u32, u32), &EdgeAttributes)> {
+        return self.attributes.iter();
+    }
+
+    /// The IDs of every worker in the graph, sorted
+    pub fn worker_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.neighbors.keys().copied().collect();
+        ids.sort();
+        return ids;
+    }
+
+    /// The workers that can pass pearls to a worker, sorted
+    pub fn incoming(&self, id: u32) -> &[u32] {
+        return match self.incoming.get(&id) {
+            Some(from) => from,
+            None => &[],
+        };
+    }
+}
+
+impl Index<&u32> for NeighborGraph {
+    type Output = Vec<u32>;
+
+    fn index(&self, id: &u32) -> &Vec<u32> {
+        match self.neighbors.get(id) {
+            Some(n) => n,
+            None => panic!("Neighbor Graph Error: {} is not in the graph", id),
+        }
+    }
+}
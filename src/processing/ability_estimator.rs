@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::models;
+use models::ability_map::{AbilityMap, COLORS, FLAVORS};
+use models::state::{Pearl, State};
+
+/// Everything seen so far about how fast one flavor dissolves one color.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateObservations {
+    /// How many times each exact rate was observed, from noms that left
+    /// part of the layer behind
+    pub exact: HashMap<u32, u32>,
+    /// The largest layer that was dissolved completely in a single nom. The
+    /// true rate is at least this large.
+    pub lower_bound: u32,
+}
+
+impl RateObservations {
+    /// The most commonly observed exact rate, preferring the larger rate
+    /// on ties
+    pub fn estimate(&self) -> Option<u32> {
+        return self
+            .exact
+            .iter()
+            .max_by_key(|&(rate, count)| (*count, *rate))
+            .map(|(rate, _)| *rate);
+    }
+}
+
+/// A place where the learned rates don't line up with a reference map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement {
+    pub flavor: String,
+    pub color: String,
+    /// The rate in the reference map
+    pub expected: u32,
+    /// The learned rate, or the lower bound if no exact rate has been seen
+    pub observed: u32,
+}
+
+/// Learns the ability map online by comparing consecutive `State`s. When a
+/// pearl stays on the same worker's desk between turns and its outermost
+/// layer gets thinner, the difference is exactly that worker's rate for the
+/// layer's color. When the outermost layer disappears entirely, the rate is
+/// only known to be at least the layer's thickness.
+#[derive(Debug, Clone, Default)]
+pub struct AbilityEstimator {
+    previous: Option<State>,
+    observations: HashMap<(String, String), RateObservations>,
+}
+
+impl AbilityEstimator {
+    pub fn new() -> AbilityEstimator {
+        return AbilityEstimator::default();
+    }
+
+    /// Compares a new state against the previous one and records what each
+    /// worker's nom did to the pearls that stayed on their desk.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A reference to the newest state of the pipeline
+    ///
+    /// # Returns
+    ///
+    /// * ()
+    pub fn observe(&mut self, state: &State) {
+        if let Some(prev) = self.previous.take() {
+            for old_worker in &prev.workers {
+                let new_worker = match state.workers.iter().find(|w| w.id == old_worker.id) {
+                    Some(w) => w,
+                    None => continue,
+                };
+
+                for old_pearl in &old_worker.desk {
+                    if let Some(new_pearl) = new_worker.desk.iter().find(|p| p.id == old_pearl.id) {
+                        self.record(&old_worker.flavor, old_pearl, new_pearl);
+                    }
+                }
+            }
+        }
+
+        self.previous = Some(state.clone());
+    }
+
+    /// Records the change in a single pearl between two turns.
+    fn record(&mut self, flavor: &str, old_pearl: &Pearl, new_pearl: &Pearl) {
+        let old_layer = match old_pearl.layers.first() {
+            Some(l) => l,
+            None => return,
+        };
+
+        let key = (flavor.to_string(), old_layer.color.clone());
+
+        if new_pearl.layers.len() == old_pearl.layers.len() {
+            let new_layer = &new_pearl.layers[0];
+            if new_layer.color == old_layer.color && new_layer.thickness < old_layer.thickness {
+                let rate = old_layer.thickness - new_layer.thickness;
+                let obs = self.observations.entry(key).or_default();
+                *obs.exact.entry(rate).or_insert(0) += 1;
+            }
+        } else if new_pearl.layers.len() + 1 == old_pearl.layers.len() {
+            let obs = self.observations.entry(key).or_default();
+            if old_layer.thickness > obs.lower_bound {
+                obs.lower_bound = old_layer.thickness;
+            }
+        }
+    }
+
+    /// Returns what has been observed for a flavor and color, if anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `flavor` - The flavor of nautiloid
+    /// * `color` - The color of pearl layer
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&RateObservations>` - The observations, if there are any
+    pub fn observations(&self, flavor: &str, color: &str) -> Option<&RateObservations> {
+        return self
+            .observations
+            .get(&(flavor.to_string(), color.to_string()));
+    }
+
+    /// Builds an ability map from the learned rates, so it can be used in
+    /// place of `get_ability_map`. Rates that haven't been observed exactly
+    /// are taken from `fallback`, raised to the observed lower bound if the
+    /// fallback is too slow.
+    ///
+    /// # Arguments
+    ///
+    /// * `fallback` - The map to take unobserved rates from
+    ///
+    /// # Returns
+    ///
+    /// * `AbilityMap` - The learned ability map
+    pub fn ability_map(&self, fallback: &AbilityMap) -> AbilityMap {
+        let mut map = fallback.clone();
+
+        for flavor in FLAVORS.iter() {
+            for color in COLORS.iter() {
+                if let Some(obs) = self.observations(flavor, color) {
+                    let rate = match obs.estimate() {
+                        Some(r) => r,
+                        None => map[flavor][color].max(obs.lower_bound),
+                    };
+                    map[flavor][color] = rate;
+                }
+            }
+        }
+
+        return map;
+    }
+
+    /// Lists every flavor and color where the observations contradict a
+    /// reference ability map, such as the static one in `ability_map.json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - The ability map to check against
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Disagreement>` - The contradictions found, in the order of
+    ///                         `FLAVORS` then `COLORS`
+    pub fn disagreements(&self, reference: &AbilityMap) -> Vec<Disagreement> {
+        let mut found: Vec<Disagreement> = Vec::new();
+
+        for flavor in FLAVORS.iter() {
+            for color in COLORS.iter() {
+                let obs = match self.observations(flavor, color) {
+                    Some(o) => o,
+                    None => continue,
+                };
+
+                let expected = reference[flavor][color];
+                let observed = match obs.estimate() {
+                    Some(r) if r != expected => Some(r),
+                    Some(_) => None,
+                    None if obs.lower_bound > expected => Some(obs.lower_bound),
+                    None => None,
+                };
+
+                if let Some(o) = observed {
+                    found.push(Disagreement {
+                        flavor: flavor.to_string(),
+                        color: color.to_string(),
+                        expected: expected,
+                        observed: o,
+                    });
+                }
+            }
+        }
+
+        return found;
+    }
+}
@@ -0,0 +1,455 @@
+use serde::Serialize;
+use serde_json;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use super::gatekeeper::edge_weight;
+use super::simulator::simulate;
+use super::utils::{build_state_graph, get_time_to_process};
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::config::Config;
+use models::state::{NeighborGraph, Pearl, State, Worker};
+
+/// The fewest turns one pearl could take to be delivered, if it had the
+/// floor to itself.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PearlBound {
+    pub pearl_id: u32,
+    pub holder_id: u32,
+    /// Turns to process every layer at the fastest worker it can get to and
+    /// carry the pearl home
+    pub turns: u32,
+    /// Noms it needs, each layer done by the fastest flavor it can reach
+    pub work: u32,
+}
+
+/// A lower bound on how many turns it takes to deliver every pearl in a
+/// state. No strategy can finish sooner.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LowerBound {
+    /// The pearl that takes longest on its own
+    pub critical_path: u32,
+    /// Every pearl's noms shared evenly between the workers
+    pub parallel: u32,
+    /// The larger of `critical_path` and `parallel`
+    pub lower_bound: u32,
+    pub total_work: u32,
+    pub workers: u32,
+    /// Every pearl that can be delivered, in ID order
+    pub pearls: Vec<PearlBound>,
+    /// Pearls that can never reach the gatekeeper processed, in ID order
+    pub stranded: Vec<u32>,
+}
+
+/// How the greedy strategy did against the lower bound on one game.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GameBound {
+    pub bound: LowerBound,
+    /// How many turns greedy played
+    pub greedy_turns: u32,
+    /// How many pearls greedy delivered
+    pub greedy_score: u32,
+    /// Whether greedy delivered every pearl within the turn limit
+    pub finished: bool,
+    /// How many turns greedy took beyond the bound, if it finished
+    pub gap: Option<u32>,
+}
+
+/// The gap between greedy and the lower bound over one or more games.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BoundReport {
+    pub games: Vec<GameBound>,
+    pub mean_lower_bound: f64,
+    pub mean_greedy_turns: f64,
+    /// The average gap over the games greedy finished, if it finished any
+    pub mean_gap: Option<f64>,
+    /// How many games greedy didn't finish
+    pub unfinished: usize,
+}
+
+/// How many noms a worker needs to get through one layer of a pearl, or
+/// None if it can't process that color at all.
+fn layer_time(
+    pearl: &Pearl,
+    depth: usize,
+    worker: &Worker,
+    ability_map: &AbilityMap,
+) -> Option<u32> {
+    let layer = &pearl.layers[depth];
+    if ability_map.rate(&worker.flavor, &layer.color).unwrap_or(0) == 0 {
+        return None;
+    }
+
+    let single = Pearl {
+        id: pearl.id,
+        layers: vec![layer.clone()],
+    };
+    return Some(get_time_to_process(&single, worker, ability_map));
+}
+
+/// Finds the workers a pearl can visit on its way home: those its holder
+/// can pass to, that can also pass back to the gatekeeper.
+fn workers_on_the_way(
+    holder_id: u32,
+    gatekeeper_id: u32,
+    neighbor_graph: &NeighborGraph,
+) -> HashSet<u32> {
+    let mut forward: HashSet<u32> = HashSet::from([holder_id]);
+    let mut stack = vec![holder_id];
+    while let Some(id) = stack.pop() {
+        for &nbr in neighbor_graph.get(&id).map(|n| n.as_slice()).unwrap_or(&[]) {
+            if forward.insert(nbr) {
+                stack.push(nbr);
+            }
+        }
+    }
+
+    let mut home: HashSet<u32> = HashSet::from([gatekeeper_id]);
+    let mut stack = vec![gatekeeper_id];
+    while let Some(id) = stack.pop() {
+        for &from in neighbor_graph.incoming(id) {
+            if home.insert(from) {
+                stack.push(from);
+            }
+        }
+    }
+
+    return forward.intersection(&home).copied().collect();
+}
+
+/// Works out the fewest turns a pearl could take to be delivered on its
+/// own, with a shortest path search over where the pearl is and how many
+/// of its layers are done. Each step either noms a whole layer at the
+/// current worker or passes the pearl on. A layer split between workers
+/// can't take fewer noms than the fastest of them would alone, so no
+/// real game beats this.
+///
+/// # Arguments
+///
+/// * `pearl` - A reference to the pearl
+/// * `holder_id` - The ID of the worker holding it
+/// * `workers` - Every worker, by ID
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `gatekeeper_id` - The ID of the gatekeeper
+///
+/// # Returns
+///
+/// * `Option<PearlBound>` - The bound for the pearl, or None if it can't
+///                          be delivered
+fn bound_pearl(
+    pearl: &Pearl,
+    holder_id: u32,
+    workers: &HashMap<u32, &Worker>,
+    neighbor_graph: &NeighborGraph,
+    ability_map: &AbilityMap,
+    gatekeeper_id: u32,
+) -> Option<PearlBound> {
+    let on_the_way = workers_on_the_way(holder_id, gatekeeper_id, neighbor_graph);
+    let layers = pearl.layers.len();
+
+    let mut work: u32 = 0;
+    for depth in 0..layers {
+        let fastest = on_the_way
+            .iter()
+            .filter_map(|id| layer_time(pearl, depth, workers[id], ability_map))
+            .min()?;
+        work = work.saturating_add(fastest);
+    }
+
+    let mut turns: HashMap<(u32, usize), u32> = HashMap::new();
+    let mut queue: BinaryHeap<Reverse<(u32, u32, usize)>> = BinaryHeap::new();
+    turns.insert((holder_id, 0), 0);
+    queue.push(Reverse((0, holder_id, 0)));
+
+    while let Some(Reverse((t, id, done))) = queue.pop() {
+        if id == gatekeeper_id && done == layers {
+            // Delivery happens at the end of a turn, so even a finished
+            // pearl on the gatekeeper's desk takes one
+            return Some(PearlBound {
+                pearl_id: pearl.id,
+                holder_id: holder_id,
+                turns: t.max(1),
+                work: work,
+            });
+        }
+        if turns.get(&(id, done)).is_some_and(|&best| best < t) {
+            continue;
+        }
+
+        let mut steps: Vec<(u32, usize, u32)> = Vec::new();
+        if done < layers {
+            if let Some(n) = layer_time(pearl, done, workers[&id], ability_map) {
+                steps.push((id, done + 1, n));
+            }
+        }
+        for &nbr in neighbor_graph.get(&id).map(|n| n.as_slice()).unwrap_or(&[]) {
+            if on_the_way.contains(&nbr) {
+                steps.push((nbr, done, edge_weight(neighbor_graph, id, nbr)));
+            }
+        }
+
+        for (next, next_done, cost) in steps {
+            let through = t.saturating_add(cost);
+            if turns
+                .get(&(next, next_done))
+                .is_some_and(|&best| best <= through)
+            {
+                continue;
+            }
+            turns.insert((next, next_done), through);
+            queue.push(Reverse((through, next, next_done)));
+        }
+    }
+
+    return None;
+}
+
+/// Works out a lower bound on how many turns it takes to deliver every
+/// pearl in a state. Each pearl needs at least as long as it would take
+/// with the floor to itself, every layer processed by the fastest flavor
+/// it can reach and carried home over the quickest edges. And since each
+/// worker noms once a turn, the workers together need at least as long as
+/// the noms the pearls need, shared evenly between them.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to bound
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config, for the gatekeeper and transfer costs
+///
+/// # Returns
+///
+/// * `LowerBound` - The bound, along with each pearl's share of it
+pub fn estimate_lower_bound(
+    state: &State,
+    ability_map: &AbilityMap,
+    config: &Config,
+) -> LowerBound {
+    let neighbor_graph = build_state_graph(state, &config.transfer);
+    return estimate_lower_bound_with_graph(
+        state,
+        &neighbor_graph,
+        ability_map,
+        config.gatekeeper_id,
+    );
+}
+
+/// Works out the same lower bound as `estimate_lower_bound`, on a neighbor
+/// graph that has already been built, for searches that bound many states
+/// on the same floor.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to bound
+/// * `neighbor_graph` - The graph detailing who each worker can pass to,
+///                      with its transfer costs
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `gatekeeper_id` - The ID of the gatekeeper
+///
+/// # Returns
+///
+/// * `LowerBound` - The bound, along with each pearl's share of it
+pub fn estimate_lower_bound_with_graph(
+    state: &State,
+    neighbor_graph: &NeighborGraph,
+    ability_map: &AbilityMap,
+    gatekeeper_id: u32,
+) -> LowerBound {
+    let workers: HashMap<u32, &Worker> = state.workers.iter().map(|w| (w.id, w)).collect();
+
+    let mut pearls: Vec<PearlBound> = Vec::new();
+    let mut stranded: Vec<u32> = Vec::new();
+    for w in &state.workers {
+        for p in &w.desk {
+            match bound_pearl(
+                p,
+                w.id,
+                &workers,
+                neighbor_graph,
+                ability_map,
+                gatekeeper_id,
+            ) {
+                Some(b) => pearls.push(b),
+                None => stranded.push(p.id),
+            }
+        }
+    }
+    pearls.sort_by_key(|b| b.pearl_id);
+    stranded.sort();
+
+    let critical_path = pearls.iter().map(|b| b.turns).max().unwrap_or(0);
+    let total_work = pearls.iter().fold(0u32, |n, b| n.saturating_add(b.work));
+    let worker_count = state.workers.len() as u32;
+    let parallel = total_work.div_ceil(worker_count.max(1));
+
+    return LowerBound {
+        critical_path: critical_path,
+        parallel: parallel,
+        lower_bound: critical_path.max(parallel),
+        total_work: total_work,
+        workers: worker_count,
+        pearls: pearls,
+        stranded: stranded,
+    };
+}
+
+/// Plays a game with the greedy strategy and compares how long it took to
+/// the lower bound.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to start from
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config to make decisions with
+/// * `max_turns` - The most turns to let greedy play
+///
+/// # Returns
+///
+/// * `GameBound` - The bound, and how greedy did against it
+pub fn compare_to_greedy(
+    state: &State,
+    ability_map: &AbilityMap,
+    config: &Config,
+    max_turns: u32,
+) -> GameBound {
+    let bound = estimate_lower_bound(state, ability_map, config);
+    let pearl_count: usize = state.workers.iter().map(|w| w.desk.len()).sum();
+
+    let (turns, end) = simulate(state, ability_map, config, max_turns);
+    let greedy_score = end.score.saturating_sub(state.score);
+    let finished = greedy_score as usize >= pearl_count;
+    let greedy_turns = turns.len() as u32;
+
+    return GameBound {
+        gap: if finished {
+            Some(greedy_turns.saturating_sub(bound.lower_bound))
+        } else {
+            None
+        },
+        bound: bound,
+        greedy_turns: greedy_turns,
+        greedy_score: greedy_score,
+        finished: finished,
+    };
+}
+
+/// Compares greedy to the lower bound over a set of games, such as those
+/// generated from the `scenario` section of the config.
+///
+/// # Arguments
+///
+/// * `games` - The starting state of each game
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config to make decisions with
+/// * `max_turns` - The most turns to let greedy play each game
+///
+/// # Returns
+///
+/// * `BoundReport` - How greedy did against the bound in each game, and
+///                   on average
+pub fn bound_report(
+    games: &[State],
+    ability_map: &AbilityMap,
+    config: &Config,
+    max_turns: u32,
+) -> BoundReport {
+    let results: Vec<GameBound> = games
+        .iter()
+        .map(|g| compare_to_greedy(g, ability_map, config, max_turns))
+        .collect();
+
+    let count = results.len().max(1) as f64;
+    let gaps: Vec<u32> = results.iter().filter_map(|r| r.gap).collect();
+
+    return BoundReport {
+        mean_lower_bound: results
+            .iter()
+            .map(|r| r.bound.lower_bound as f64)
+            .sum::<f64>()
+            / count,
+        mean_greedy_turns: results.iter().map(|r| r.greedy_turns as f64).sum::<f64>() / count,
+        mean_gap: if gaps.is_empty() {
+            None
+        } else {
+            Some(gaps.iter().map(|g| *g as f64).sum::<f64>() / gaps.len() as f64)
+        },
+        unfinished: results.iter().filter(|r| !r.finished).count(),
+        games: results,
+    };
+}
+
+/// Writes a bound report out as readable text.
+///
+/// # Arguments
+///
+/// * `report` - A reference to the report to write
+///
+/// # Returns
+///
+/// * `String` - The report, one line per game and a summary
+pub fn bound_to_text(report: &BoundReport) -> String {
+    let mut out = String::new();
+
+    for (i, g) in report.games.iter().enumerate() {
+        let b = &g.bound;
+        out += &format!(
+            "game {}: bound {} (critical path {}, {} noms over {} workers takes {})",
+            i, b.lower_bound, b.critical_path, b.total_work, b.workers, b.parallel
+        );
+        match g.gap {
+            Some(gap) => {
+                out += &format!(", greedy {} turns, gap {}\n", g.greedy_turns, gap);
+            }
+            None => {
+                out += &format!(
+                    ", greedy delivered {} in {} turns without finishing\n",
+                    g.greedy_score, g.greedy_turns
+                );
+            }
+        }
+        if !b.stranded.is_empty() {
+            out += &format!(
+                "  WARNING pearls that can't be delivered: {:?}\n",
+                b.stranded
+            );
+        }
+    }
+
+    out += &format!(
+        "mean bound {:.1}, mean greedy {:.1} turns",
+        report.mean_lower_bound, report.mean_greedy_turns
+    );
+    match report.mean_gap {
+        Some(gap) => out += &format!(", mean gap {:.1}", gap),
+        None => out += ", no game finished",
+    }
+    if report.unfinished > 0 {
+        out += &format!(", {} unfinished", report.unfinished);
+    }
+    out += "\n";
+
+    return out;
+}
+
+/// Writes a bound report out as JSON.
+///
+/// # Arguments
+///
+/// * `report` - A reference to the report to write
+///
+/// # Returns
+///
+/// * `String` - The report as a single line of JSON
+pub fn bound_to_json(report: &BoundReport) -> String {
+    return match serde_json::to_string(report) {
+        Ok(s) => s,
+        Err(e) => panic!("Bound Error: could not write report: {}", e),
+    };
+}
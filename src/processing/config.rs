@@ -0,0 +1,203 @@
+use serde_json::{self, Value};
+use std::fs;
+use std::path::Path;
+
+use super::utils::get_ability_map;
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::config::Config;
+
+/// The config file read at startup when no `--config` flag is given
+pub const DEFAULT_CONFIG_PATH: &str = "atlantis.json";
+
+/// The config related flags pulled out of the command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigArgs {
+    /// The path given with `--config`, if any
+    pub path: Option<String>,
+    /// Every `key=value` override, in the order they were given
+    pub overrides: Vec<String>,
+    /// All of the arguments that weren't config flags
+    pub rest: Vec<String>,
+}
+
+/// Pulls the config flags out of a list of command line arguments.
+///
+/// * `--config <path>` reads the config from `path`
+/// * `--set <key>=<value>` overrides a single key, using dots to reach into
+///   sections, e.g. `--set strategy.min_pass_gain=2`
+/// * `--gatekeeper-id <id>` and `--ability-map <path>` are shorthands for
+///   setting `gatekeeper_id` and `ability_map_path`
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments, not including the program name
+///
+/// # Returns
+///
+/// * `Result<ConfigArgs, String>` - The parsed flags, or a message saying
+///                                  which flag was missing its value
+pub fn parse_config_args(args: &[String]) -> Result<ConfigArgs, String> {
+    let mut parsed = ConfigArgs::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let flag = arg.as_str();
+        match flag {
+            "--config" | "--set" | "--gatekeeper-id" | "--ability-map" => {
+                let value = match iter.next() {
+                    Some(v) => v.clone(),
+                    None => return Err(format!("{} needs a value", flag)),
+                };
+
+                match flag {
+                    "--config" => parsed.path = Some(value),
+                    "--set" => parsed.overrides.push(value),
+                    "--gatekeeper-id" => parsed.overrides.push(format!("gatekeeper_id={}", value)),
+                    _ => parsed
+                        .overrides
+                        .push(format!("ability_map_path={}", Value::String(value))),
+                }
+            }
+            _ => parsed.rest.push(arg.clone()),
+        }
+    }
+
+    return Ok(parsed);
+}
+
+/// Copies every key in `overlay` onto `base`, descending into objects that
+/// exist in both so that a partial section only replaces the keys it names.
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(b), Value::Object(o)) => {
+            for (k, v) in o {
+                match b.get_mut(&k) {
+                    Some(existing) => merge_values(existing, v),
+                    None => {
+                        b.insert(k, v);
+                    }
+                }
+            }
+        }
+        (b, o) => *b = o,
+    }
+}
+
+/// Applies a single `key=value` override to a config. The value is read as
+/// JSON when possible, and as a plain string otherwise.
+///
+/// # Arguments
+///
+/// * `config` - The config, as a JSON value
+/// * `assignment` - The override, e.g. `gatekeeper.hop_cost=2`
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the override is malformed or names
+///                          a key that doesn't exist
+pub fn apply_override(config: &mut Value, assignment: &str) -> Result<(), String> {
+    let (key, raw) = match assignment.find('=') {
+        Some(i) => (&assignment[..i], &assignment[i + 1..]),
+        None => {
+            return Err(format!(
+                "Override {} is not of the form key=value",
+                assignment
+            ))
+        }
+    };
+
+    let value: Value = match serde_json::from_str(raw) {
+        Ok(v) => v,
+        Err(_) => Value::String(raw.to_string()),
+    };
+
+    let mut target = config;
+    for part in key.split('.') {
+        target = match target.get_mut(part) {
+            Some(t) => t,
+            None => return Err(format!("Unknown config key {}", key)),
+        };
+    }
+    *target = value;
+
+    return Ok(());
+}
+
+/// Loads the config used for this run. Starting from the defaults, the
+/// config file is layered on top, followed by each override in turn.
+///
+/// If no path is given, `atlantis.json` in the working directory is used
+/// when it exists, and the defaults are used when it doesn't. A key in the
+/// file that isn't part of the config is an error, as it is for `--set`.
+///
+/// # Arguments
+///
+/// * `path` - The config file to read, if one was given
+/// * `overrides` - The `key=value` overrides from the command line
+///
+/// # Returns
+///
+/// * `Result<Config, String>` - The config, or a message describing why
+///                              it couldn't be loaded
+pub fn load_config(path: Option<&str>, overrides: &[String]) -> Result<Config, String> {
+    let mut value = match serde_json::to_value(Config::default()) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Config error: {}", e)),
+    };
+
+    let file = match path {
+        Some(p) => Some(p),
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => Some(DEFAULT_CONFIG_PATH),
+        None => None,
+    };
+
+    if let Some(f) = file {
+        let contents = match fs::read_to_string(f) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Could not read config file {}: {}", f, e)),
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(v) => merge_values(&mut value, v),
+            Err(e) => return Err(format!("Could not parse config file {}: {}", f, e)),
+        }
+    }
+
+    for o in overrides {
+        apply_override(&mut value, o)?;
+    }
+
+    return match serde_json::from_value(value) {
+        Ok(c) => Ok(c),
+        Err(e) => Err(format!("Invalid config: {}", e)),
+    };
+}
+
+/// Reads the ability map named in the config, falling back on the copy
+/// built into the binary.
+///
+/// # Arguments
+///
+/// * `config` - A reference to the config for this run
+///
+/// # Returns
+///
+/// * `Result<AbilityMap, String>` - The ability map, or a message describing
+///                                  why it couldn't be read
+pub fn load_ability_map(config: &Config) -> Result<AbilityMap, String> {
+    let path = match &config.ability_map_path {
+        Some(p) => p,
+        None => return Ok(get_ability_map()),
+    };
+
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("Could not read ability map {}: {}", path, e)),
+    };
+
+    return match serde_json::from_str(&contents) {
+        Ok(m) => Ok(m),
+        Err(e) => Err(format!("Could not parse ability map {}: {}", path, e)),
+    };
+}
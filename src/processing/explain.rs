@@ -0,0 +1,444 @@
+use serde::Serialize;
+use serde_json::{self, json, Value};
+use std::collections::HashMap;
+
+use super::gatekeeper::{count_pearls_in_flight, get_next_hop_home, rank_intake, IntakeEstimate};
+use super::transfer::TransferCosts;
+use super::utils::{
+    build_state_graph, determine_actions, get_time_to_process, get_worker_pearl_counts,
+    pass_qualifies,
+};
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::action::ActionType;
+use models::config::Config;
+use models::state::{NeighborGraph, State, Worker};
+
+/// How long one neighbor would take to process a pearl.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NeighborTime {
+    pub worker_id: u32,
+    pub time: u32,
+}
+
+/// The processing times of a single pearl on a worker's desk.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PearlTimes {
+    pub pearl_id: u32,
+    pub finished: bool,
+    /// How much of the remaining layers has already been processed, from
+    /// 0.0 to 1.0
+    pub progress: f64,
+    /// How long the worker holding the pearl would take
+    pub own_time: u32,
+    pub neighbor_times: Vec<NeighborTime>,
+}
+
+/// A pass that `get_best_neighbor` considered, and what it made of it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PassCandidate {
+    pub neighbor_id: u32,
+    pub pearl_id: u32,
+    pub holder_time: u32,
+    pub neighbor_time: u32,
+    /// How many turns the pass would leave the pearl in transit
+    pub transfer_cost: u32,
+    pub chosen: bool,
+    pub reason: String,
+}
+
+/// Everything that went into one worker's decision.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WorkerExplanation {
+    pub worker_id: u32,
+    pub flavor: String,
+    pub neighbors: Vec<u32>,
+    pub pearls: Vec<PearlTimes>,
+    /// Every pass considered, for workers that went through
+    /// `get_best_neighbor`
+    pub candidates: Vec<PassCandidate>,
+    /// The gatekeeper's intake ranking, for the gatekeeper only
+    pub intake: Option<Vec<IntakeEstimate>>,
+    /// Pearls away from the gatekeeper, for the gatekeeper only
+    pub in_flight: Option<u32>,
+    /// The action taken, in the same format as the actions on `stdout`
+    pub action: Option<Value>,
+    /// A sentence saying why the action was taken
+    pub summary: String,
+}
+
+/// The decisions of every worker for a single state.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub score: u32,
+    pub workers: Vec<WorkerExplanation>,
+}
+
+/// Converts a single action into the JSON the harness expects, e.g.
+/// `{"Nom": 42}`.
+///
+/// # Arguments
+///
+/// * `action` - A reference to the action to convert
+///
+/// # Returns
+///
+/// * `Value` - The action as JSON
+pub fn action_to_value(action: &ActionType) -> Value {
+    return match action {
+        ActionType::Pass(p) => json!({"Pass": {"pearl_id": p.pearl_id, "to_worker": p.to_id}}),
+        ActionType::Nom(n) => json!({ "Nom": n.pearl_id }),
+    };
+}
+
+/// Works through the pass candidates for a worker the same way
+/// `get_best_neighbor` does, recording why each one was or wasn't taken.
+fn explain_candidates(
+    worker: &Worker,
+    workers: &HashMap<u32, &Worker>,
+    pearl_counts: &HashMap<u32, u32>,
+    neighbors: &[(u32, u32)],
+    ability_map: &AbilityMap,
+    config: &Config,
+    chosen: Option<(u32, u32)>,
+) -> Vec<PassCandidate> {
+    let strategy = &config.strategy;
+    let mut candidates: Vec<PassCandidate> = Vec::new();
+
+    for (nbr_id, transfer_cost) in neighbors {
+        let nbr = match workers.get(nbr_id) {
+            Some(n) => n,
+            None => continue,
+        };
+
+        for p in worker.desk.iter().filter(|p| !p.layers.is_empty()) {
+            let holder_time = get_time_to_process(p, worker, ability_map);
+            let neighbor_time = get_time_to_process(p, nbr, ability_map);
+            let transfer_cost = *transfer_cost;
+            // Time in transit counts against the neighbor
+            let there = neighbor_time.saturating_add(transfer_cost);
+            let is_chosen = chosen == Some((*nbr_id, p.id));
+            let extra_gain = if p.is_partial() {
+                strategy.partial_pass_gain
+            } else {
+                0
+            };
+
+            let reason = if is_chosen {
+                format!(
+                    "chosen, {} turns there instead of {} here",
+                    there, holder_time
+                )
+            } else if pearl_counts.get(nbr_id) != Some(&0) {
+                String::from("the neighbor's desk is not empty")
+            } else if pass_qualifies(
+                holder_time,
+                neighbor_time,
+                transfer_cost,
+                worker.desk.len(),
+                p.is_partial(),
+                strategy,
+            ) {
+                String::from("qualified, but lost the tie break")
+            } else if there == holder_time && extra_gain > 0 {
+                String::from("equally fast, but the pearl is part way through a layer")
+            } else if there == holder_time {
+                format!(
+                    "equally fast, but only {} pearls on the desk (needs {})",
+                    worker.desk.len(),
+                    strategy.equal_pass_min_desk
+                )
+            } else {
+                format!(
+                    "{} turns there against {} here, needs to be at least {} faster",
+                    there,
+                    holder_time,
+                    strategy.min_pass_gain + extra_gain
+                )
+            };
+
+            candidates.push(PassCandidate {
+                neighbor_id: *nbr_id,
+                pearl_id: p.id,
+                holder_time: holder_time,
+                neighbor_time: neighbor_time,
+                transfer_cost: transfer_cost,
+                chosen: is_chosen,
+                reason: reason,
+            });
+        }
+    }
+
+    return candidates;
+}
+
+/// Explains what every worker decides to do for a single state, and why.
+/// The actions are the ones `determine_actions` issues, and the reasoning
+/// is rebuilt by replaying its decisions in the same order.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to explain
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config to make decisions with
+///
+/// # Returns
+///
+/// * `Explanation` - The reasoning behind every worker's action, in
+///                   worker ID order
+pub fn explain_state(state: &State, ability_map: &AbilityMap, config: &Config) -> Explanation {
+    let neighbor_graph: NeighborGraph = build_state_graph(state, &config.transfer);
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(&neighbor_graph);
+    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+    let actions = determine_actions(
+        state,
+        ability_map,
+        &neighbor_graph,
+        &mut pearl_paths,
+        config,
+    );
+
+    let mut workers: HashMap<u32, &Worker> = HashMap::new();
+    for w in &state.workers {
+        workers.insert(w.id, w);
+    }
+
+    // Workers decide in the order they appear in the state, and each pass
+    // fills a neighbor's desk for the workers that come after
+    let mut pearl_counts = get_worker_pearl_counts(&state.workers);
+    let mut explained: Vec<WorkerExplanation> = Vec::new();
+
+    for w in &state.workers {
+        let mut neighbors = neighbor_graph.get(&w.id).cloned().unwrap_or_default();
+        neighbors.sort();
+
+        let mut pearls: Vec<PearlTimes> = Vec::new();
+        for p in &w.desk {
+            let mut neighbor_times: Vec<NeighborTime> = Vec::new();
+            for n in &neighbors {
+                if let Some(nbr) = workers.get(n) {
+                    neighbor_times.push(NeighborTime {
+                        worker_id: *n,
+                        time: get_time_to_process(p, nbr, ability_map),
+                    });
+                }
+            }
+
+            pearls.push(PearlTimes {
+                pearl_id: p.id,
+                finished: p.layers.is_empty(),
+                progress: p.progress(),
+                own_time: get_time_to_process(p, w, ability_map),
+                neighbor_times: neighbor_times,
+            });
+        }
+
+        let action = actions.get(&w.id);
+        let is_gatekeeper = w.id == config.gatekeeper_id;
+        // A finished pearl being passed is on its way home, and any other
+        // finished pearl is held for now
+        let returning = match action {
+            Some(ActionType::Pass(p)) => w
+                .desk
+                .iter()
+                .find(|prl| prl.id == p.pearl_id && prl.layers.is_empty()),
+            _ => None,
+        };
+        let held = w
+            .desk
+            .iter()
+            .find(|p| p.layers.is_empty() && returning.is_none());
+        let why_held = || {
+            return match get_next_hop_home(w.id, config.gatekeeper_id, &neighbor_graph) {
+                None => "there is no route home",
+                Some(_) => "its next stop home has already been sent a pearl this turn",
+            };
+        };
+
+        let mut candidates: Vec<PassCandidate> = Vec::new();
+        let mut intake: Option<Vec<IntakeEstimate>> = None;
+        let mut in_flight: Option<u32> = None;
+
+        let summary = if w.desk.is_empty() {
+            String::from("nothing to do, the desk is empty")
+        } else if is_gatekeeper {
+            intake = Some(rank_intake(w, state, ability_map, &neighbor_graph, config));
+            in_flight = Some(count_pearls_in_flight(state, w.id));
+
+            match action {
+                Some(ActionType::Pass(p)) => format!(
+                    "dispatches pearl {} to {}, the network can process it faster",
+                    p.pearl_id, p.to_id
+                ),
+                Some(ActionType::Nom(n)) => format!(
+                    "noms pearl {} itself, with {} of {} pearls in flight",
+                    n.pearl_id,
+                    in_flight.unwrap_or(0),
+                    config.gatekeeper.max_in_flight
+                ),
+                None => String::from("nothing to do, only finished pearls on the desk"),
+            }
+        } else if let (Some(f), Some(ActionType::Pass(p))) = (returning, action) {
+            format!(
+                "returns finished pearl {} towards the gatekeeper via {}",
+                f.id, p.to_id
+            )
+        } else if let (Some(f), None) = (held, action) {
+            format!("holds finished pearl {}, {}", f.id, why_held())
+        } else {
+            let chosen = match action {
+                Some(ActionType::Pass(p)) => Some((p.to_id, p.pearl_id)),
+                _ => None,
+            };
+            let neighbor_costs: Vec<(u32, u32)> = neighbors
+                .iter()
+                .map(|&n| (n, transfer_costs.cost(w.id, n)))
+                .collect();
+            candidates = explain_candidates(
+                w,
+                &workers,
+                &pearl_counts,
+                &neighbor_costs,
+                ability_map,
+                config,
+                chosen,
+            );
+
+            let summary = match action {
+                Some(ActionType::Pass(p)) => format!(
+                    "passes pearl {} to {}, who can process it at least as fast",
+                    p.pearl_id, p.to_id
+                ),
+                Some(ActionType::Nom(n)) => format!(
+                    "no neighbor is worth passing to, so noms pearl {}, its fastest",
+                    n.pearl_id
+                ),
+                None => String::from("nothing to do"),
+            };
+            match held {
+                Some(f) => format!(
+                    "{}, and holds finished pearl {}, {}",
+                    summary,
+                    f.id,
+                    why_held()
+                ),
+                None => summary,
+            }
+        };
+
+        // Every pass fills the receiver's desk for the workers that come
+        // after, but a returned finished pearl stays on the holder's count
+        if let Some(ActionType::Pass(p)) = action {
+            let moves_unfinished = w
+                .desk
+                .iter()
+                .any(|prl| prl.id == p.pearl_id && !prl.layers.is_empty());
+            if moves_unfinished {
+                let count = pearl_counts.entry(w.id).or_insert(0);
+                *count = count.saturating_sub(1);
+            }
+            *pearl_counts.entry(p.to_id).or_insert(0) += 1;
+        }
+
+        explained.push(WorkerExplanation {
+            worker_id: w.id,
+            flavor: w.flavor.clone(),
+            neighbors: neighbors,
+            pearls: pearls,
+            candidates: candidates,
+            intake: intake,
+            in_flight: in_flight,
+            action: action.map(action_to_value),
+            summary: summary,
+        });
+    }
+
+    explained.sort_by_key(|e| e.worker_id);
+
+    return Explanation {
+        score: state.score,
+        workers: explained,
+    };
+}
+
+/// Writes an explanation out as human readable text.
+///
+/// # Arguments
+///
+/// * `explanation` - A reference to the explanation to write
+///
+/// # Returns
+///
+/// * `String` - The explanation as text
+pub fn explanation_to_text(explanation: &Explanation) -> String {
+    let mut out = String::new();
+
+    for w in &explanation.workers {
+        out += &format!(
+            "worker {} ({}), neighbors {:?}\n",
+            w.worker_id, w.flavor, w.neighbors
+        );
+
+        for p in &w.pearls {
+            let others: Vec<String> = p
+                .neighbor_times
+                .iter()
+                .map(|n| format!("{} takes {}", n.worker_id, n.time))
+                .collect();
+            let state = if p.finished {
+                String::from(" (finished)")
+            } else if p.progress > 0.0 {
+                format!(" ({:.0}% processed)", p.progress * 100.0)
+            } else {
+                String::new()
+            };
+            out += &format!(
+                "  pearl {}{}: {} turns here; {}\n",
+                p.pearl_id,
+                state,
+                p.own_time,
+                others.join(", ")
+            );
+        }
+
+        if let Some(intake) = &w.intake {
+            for est in intake {
+                out += &format!(
+                    "  intake {}: {} turns here, {} turns best in the network\n",
+                    est.pearl_id,
+                    est.self_time,
+                    est.total_cost()
+                );
+            }
+        }
+
+        for c in &w.candidates {
+            out += &format!("  pass {} -> {}: {}\n", c.pearl_id, c.neighbor_id, c.reason);
+        }
+
+        let action = match &w.action {
+            Some(a) => a.to_string(),
+            None => String::from("none"),
+        };
+        out += &format!("  action: {}\n  because: {}\n", action, w.summary);
+    }
+
+    return out;
+}
+
+/// Writes an explanation out as JSON.
+///
+/// # Arguments
+///
+/// * `explanation` - A reference to the explanation to write
+///
+/// # Returns
+///
+/// * `String` - The explanation as a single line of JSON
+pub fn explanation_to_json(explanation: &Explanation) -> String {
+    return match serde_json::to_string(explanation) {
+        Ok(s) => s,
+        Err(e) => panic!("Explain Error: could not write explanation: {}", e),
+    };
+}
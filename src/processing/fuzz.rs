@@ -0,0 +1,51 @@
+use super::pearl_pipeline::{process_line, Pipeline};
+use super::utils::get_ability_map;
+use crate::models;
+use models::config::Config;
+use models::state::State;
+
+/// Runs arbitrary bytes through `process_line`, the same path as a line read
+/// from `stdin` or a server connection: parsing, validation and deciding the
+/// turn. Used by the fuzz targets in `fuzz/`, which only look for panics.
+/// The greedy actions are expected to be legal, so any legality issue is
+/// treated as a crash.
+///
+/// # Arguments
+///
+/// * `data` - The bytes to treat as a line of input
+///
+/// # Returns
+///
+/// * `Option<String>` - The line of actions decided, or None if the bytes
+///                      aren't a valid state
+pub fn fuzz_bytes(data: &[u8]) -> Option<String> {
+    // Reading a line from stdin fails on anything that isn't UTF-8
+    let line = std::str::from_utf8(data).ok()?;
+    let mut pipeline = Pipeline::with_ability_map(Config::default(), get_ability_map());
+    let actions = process_line(&mut pipeline, line).ok()?;
+
+    if !pipeline.legality_issues().is_empty() {
+        panic!(
+            "Fuzz Error: illegal actions decided: {:?}",
+            pipeline.legality_issues()
+        );
+    }
+
+    return Some(actions);
+}
+
+/// Writes a state out as a line of input and runs it through `fuzz_bytes`,
+/// for fuzz targets that build their states directly.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to decide a turn for
+///
+/// # Returns
+///
+/// * `Option<String>` - The line of actions decided, or None if the state
+///                      isn't valid
+pub fn fuzz_state(state: &State) -> Option<String> {
+    let line = serde_json::to_string(state).ok()?;
+    return fuzz_bytes(line.as_bytes());
+}
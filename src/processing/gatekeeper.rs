@@ -0,0 +1,475 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::transfer::TransferCosts;
+use super::utils::{
+    get_best_pearl_to_nom, get_time_to_process, make_nom, make_pass, pass_qualifies,
+};
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::action::ActionType;
+use models::config::Config;
+use models::gatekeeper_policy::GatekeeperPolicy;
+use models::state::{NeighborGraph, Pearl, State, Worker};
+
+/// The gatekeeper's estimate of what it would cost to process a pearl,
+/// either in place or out in the network.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IntakeEstimate {
+    pub pearl_id: u32,
+    /// Turns for the gatekeeper to process the pearl itself
+    pub self_time: u32,
+    /// Best estimated round trip cost through each of the gatekeeper's
+    /// neighbors, sorted cheapest first
+    pub hop_costs: Vec<(u32, u32)>,
+}
+
+impl IntakeEstimate {
+    /// The cheapest way to get this pearl processed, anywhere in the network
+    pub fn total_cost(&self) -> u32 {
+        match self.hop_costs.first() {
+            Some((_, c)) if *c < self.self_time => *c,
+            _ => self.self_time,
+        }
+    }
+}
+
+/// Reads the default gatekeeper policy that ships with the binary.
+///
+/// # Arguments
+///
+/// * None
+///
+/// # Returns
+///
+/// * `GatekeeperPolicy` - The parameters controlling the gatekeeper's intake
+pub fn get_gatekeeper_policy() -> GatekeeperPolicy {
+    return GatekeeperPolicy::default();
+}
+
+/// How long a pass over an edge takes a pearl: the turn spent passing it,
+/// plus however long it is in transit.
+///
+/// # Arguments
+///
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `from_id` - The ID of the worker passing the pearl
+/// * `to_id` - The ID of the worker receiving it
+///
+/// # Returns
+///
+/// * `u32` - How many turns the pass takes
+pub fn edge_weight(neighbor_graph: &NeighborGraph, from_id: u32, to_id: u32) -> u32 {
+    return neighbor_graph.cost(from_id, to_id).saturating_add(1);
+}
+
+/// Searches backward from the gatekeeper for how many turns it takes to
+/// get a pearl home from every worker, following edges the way they allow
+/// and counting their transfer costs. On a floor where every pair passes
+/// both ways for free, this is the same distance as the gatekeeper's route
+/// out to the worker.
+///
+/// # Arguments
+///
+/// * `gatekeeper_id` - The ID of the gatekeeper
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+///
+/// # Returns
+///
+/// * `HashMap<u32, u32>` - The distance home from every worker that can
+///                         reach the gatekeeper, including the gatekeeper
+///                         itself at 0
+pub fn get_distances_home(gatekeeper_id: u32, neighbor_graph: &NeighborGraph) -> HashMap<u32, u32> {
+    let mut distances: HashMap<u32, u32> = HashMap::new();
+    let mut queue: BinaryHeap<Reverse<(u32, u32)>> = BinaryHeap::new();
+
+    distances.insert(gatekeeper_id, 0);
+    queue.push(Reverse((0, gatekeeper_id)));
+
+    while let Some(Reverse((distance, id))) = queue.pop() {
+        if distances.get(&id).is_some_and(|&d| d < distance) {
+            continue;
+        }
+
+        for &from in neighbor_graph.incoming(id) {
+            let through = distance.saturating_add(edge_weight(neighbor_graph, from, id));
+            if distances.get(&from).is_some_and(|&d| d <= through) {
+                continue;
+            }
+
+            distances.insert(from, through);
+            queue.push(Reverse((through, from)));
+        }
+    }
+
+    return distances;
+}
+
+/// Picks the neighbor a worker should pass a pearl to in order to get it
+/// home soonest, given how far every worker is from home. Ties go to the
+/// neighbor with the lower ID.
+fn next_hop_home(
+    worker_id: u32,
+    distances: &HashMap<u32, u32>,
+    neighbor_graph: &NeighborGraph,
+) -> Option<u32> {
+    let mut best: Option<(u32, u32)> = None;
+
+    for &nbr in neighbor_graph.get(&worker_id)? {
+        let distance = match distances.get(&nbr) {
+            Some(d) => d.saturating_add(edge_weight(neighbor_graph, worker_id, nbr)),
+            None => continue,
+        };
+
+        if best.is_none() || Some((distance, nbr)) < best {
+            best = Some((distance, nbr));
+        }
+    }
+
+    return best.map(|(_, id)| id);
+}
+
+/// Finds which neighbor a worker should pass a pearl to in order to get it
+/// back to the gatekeeper as quickly as possible. Ties go to the neighbor
+/// with the lower ID. When routing several workers' pearls home in the
+/// same turn, use `get_next_hops_home` instead, which searches only once.
+///
+/// # Arguments
+///
+/// * `worker_id` - The ID of the worker holding the pearl
+/// * `gatekeeper_id` - The ID of the gatekeeper
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+///
+/// # Returns
+///
+/// * `Option<u32>` - The neighbor to pass to, or None if the worker can't
+///                   reach the gatekeeper at all
+pub fn get_next_hop_home(
+    worker_id: u32,
+    gatekeeper_id: u32,
+    neighbor_graph: &NeighborGraph,
+) -> Option<u32> {
+    let distances = get_distances_home(gatekeeper_id, neighbor_graph);
+    return next_hop_home(worker_id, &distances, neighbor_graph);
+}
+
+/// Finds the next hop home for every worker at once, as `get_next_hop_home`
+/// would, from a single search outward from the gatekeeper.
+///
+/// # Arguments
+///
+/// * `gatekeeper_id` - The ID of the gatekeeper
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+///
+/// # Returns
+///
+/// * `HashMap<u32, u32>` - The neighbor each worker should pass to, for
+///                         every worker that can reach the gatekeeper
+pub fn get_next_hops_home(gatekeeper_id: u32, neighbor_graph: &NeighborGraph) -> HashMap<u32, u32> {
+    let distances = get_distances_home(gatekeeper_id, neighbor_graph);
+    let mut hops: HashMap<u32, u32> = HashMap::new();
+
+    for id in neighbor_graph.worker_ids() {
+        if let Some(hop) = next_hop_home(id, &distances, neighbor_graph) {
+            hops.insert(id, hop);
+        }
+    }
+
+    return hops;
+}
+
+/// Counts the pearls currently out in the network, i.e. on any desk other
+/// than the gatekeeper's.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state of the pipeline
+/// * `gatekeeper_id` - The ID of the gatekeeper
+///
+/// # Returns
+///
+/// * `u32` - The number of pearls away from the gatekeeper
+pub fn count_pearls_in_flight(state: &State, gatekeeper_id: u32) -> u32 {
+    let mut count: u32 = 0;
+
+    for w in &state.workers {
+        if w.id != gatekeeper_id {
+            count += w.desk.len() as u32;
+        }
+    }
+
+    return count;
+}
+
+/// Follows a pearl handed to one of the gatekeeper's neighbors along the
+/// passes the greedy rule in `pass_qualifies` would make with it, assuming
+/// every desk on the way is free, and finds who ends up processing it.
+/// At each step the pearl goes to whichever qualifying neighbor is
+/// quickest once the transfer cost is counted, with ties going to the
+/// lower ID. The gatekeeper only chooses the first hop, so this is the
+/// worker its estimate can actually count on.
+///
+/// # Arguments
+///
+/// * `pearl` - A reference to the pearl being handed off
+/// * `first_hop` - The gatekeeper's neighbor the pearl is handed to
+/// * `workers` - Every worker on the floor, by ID
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `config` - The config for this run, with the gatekeeper's ID, the
+///              transfer costs and the strategy's pass thresholds
+///
+/// # Returns
+///
+/// * `Option<(u32, u32)>` - The worker that processes the pearl, and how
+///                          many turns the passes after the first one take,
+///                          or None if the first hop isn't on the floor
+pub fn follow_hand_offs(
+    pearl: &Pearl,
+    first_hop: u32,
+    workers: &HashMap<u32, &Worker>,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    config: &Config,
+) -> Option<(u32, u32)> {
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(neighbor_graph);
+    let mut holder = *workers.get(&first_hop)?;
+    let mut visited: Vec<u32> = vec![config.gatekeeper_id, first_hop];
+    let mut travel: u32 = 0;
+
+    loop {
+        let holder_time = get_time_to_process(pearl, holder, ability_map);
+        let mut best: Option<(u32, u32)> = None;
+
+        let mut nbrs = neighbor_graph.get(&holder.id).cloned().unwrap_or_default();
+        nbrs.sort();
+
+        for nbr in nbrs {
+            let nbr_worker = match workers.get(&nbr) {
+                Some(w) if !visited.contains(&nbr) => w,
+                _ => continue,
+            };
+
+            let time = get_time_to_process(pearl, nbr_worker, ability_map);
+            let transfer = transfer_costs.cost(holder.id, nbr);
+            let qualifies = pass_qualifies(
+                holder_time,
+                time,
+                transfer,
+                1,
+                pearl.is_partial(),
+                &config.strategy,
+            );
+
+            let total = time.saturating_add(transfer);
+            if qualifies && best.is_none_or(|(_, t)| total < t) {
+                best = Some((nbr, total));
+            }
+        }
+
+        match best {
+            Some((nbr, _)) => {
+                travel = travel.saturating_add(edge_weight(neighbor_graph, holder.id, nbr));
+                visited.push(nbr);
+                holder = workers[&nbr];
+            }
+            None => return Some((holder.id, travel)),
+        }
+    }
+}
+
+/// Estimates what it would cost to process a pearl at the gatekeeper and
+/// through each of the gatekeeper's neighbors. A pearl handed to a
+/// neighbor is expected to be processed by whoever `follow_hand_offs` says
+/// it ends up with, and costs `hop_cost` for every turn of its round trip,
+/// out and back again, on top of that worker's processing time. On a floor
+/// with free two-way edges, that is `2 * d * hop_cost` for a worker `d`
+/// passes away. Workers that can't get a pearl back home are never
+/// considered.
+///
+/// # Arguments
+///
+/// * `pearl` - A reference to the pearl being estimated
+/// * `gatekeeper` - A reference to the gatekeeper
+/// * `state` - A reference to the state of the pipeline
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `distances_home` - How far every worker is from the gatekeeper
+/// * `config` - The config for this run, including the gatekeeper policy
+///
+/// # Returns
+///
+/// * `IntakeEstimate` - The estimated costs for the pearl
+pub fn estimate_intake_cost(
+    pearl: &Pearl,
+    gatekeeper: &Worker,
+    state: &State,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    distances_home: &HashMap<u32, u32>,
+    config: &Config,
+) -> IntakeEstimate {
+    let workers: HashMap<u32, &Worker> = state.workers.iter().map(|w| (w.id, w)).collect();
+    let mut hop_costs: Vec<(u32, u32)> = Vec::new();
+
+    let mut first_hops = neighbor_graph
+        .get(&gatekeeper.id)
+        .cloned()
+        .unwrap_or_default();
+    first_hops.sort();
+
+    for hop in first_hops {
+        if hop == gatekeeper.id {
+            continue;
+        }
+
+        let (processor, onward) =
+            match follow_hand_offs(pearl, hop, &workers, ability_map, neighbor_graph, config) {
+                Some(found) => found,
+                None => continue,
+            };
+        let home = match distances_home.get(&processor) {
+            Some(h) => *h,
+            None => continue,
+        };
+
+        let travel = edge_weight(neighbor_graph, gatekeeper.id, hop)
+            .saturating_add(onward)
+            .saturating_add(home)
+            .saturating_mul(config.gatekeeper.hop_cost);
+        let cost =
+            get_time_to_process(pearl, workers[&processor], ability_map).saturating_add(travel);
+        hop_costs.push((hop, cost));
+    }
+
+    hop_costs.sort_by_key(|&(hop, cost)| (cost, hop));
+
+    return IntakeEstimate {
+        pearl_id: pearl.id,
+        self_time: get_time_to_process(pearl, gatekeeper, ability_map),
+        hop_costs: hop_costs,
+    };
+}
+
+/// Ranks the unfinished pearls on the gatekeeper's desk by their estimated
+/// total cost across the network, cheapest first. Ties go to the lower
+/// pearl ID so the ranking is stable between runs.
+///
+/// # Arguments
+///
+/// * `gatekeeper` - A reference to the gatekeeper
+/// * `state` - A reference to the state of the pipeline
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `config` - The config for this run, including the gatekeeper policy
+///
+/// # Returns
+///
+/// * `Vec<IntakeEstimate>` - The estimates for every unfinished pearl,
+///                           in ranked order
+pub fn rank_intake(
+    gatekeeper: &Worker,
+    state: &State,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    config: &Config,
+) -> Vec<IntakeEstimate> {
+    let distances_home = get_distances_home(gatekeeper.id, neighbor_graph);
+    let mut ranked: Vec<IntakeEstimate> = Vec::new();
+
+    for p in &gatekeeper.desk {
+        if p.layers.is_empty() {
+            continue;
+        }
+        ranked.push(estimate_intake_cost(
+            p,
+            gatekeeper,
+            state,
+            ability_map,
+            neighbor_graph,
+            &distances_home,
+            config,
+        ));
+    }
+
+    ranked.sort_by_key(|e| (e.total_cost(), e.pearl_id));
+
+    return ranked;
+}
+
+/// Decides what the gatekeeper should do this turn. Pearls are considered
+/// in ranked order, and the first one the network can process sufficiently
+/// faster than the gatekeeper is dispatched to an empty neighbor, as long
+/// as there is room in flight. Otherwise the gatekeeper noms a pearl itself.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state of the pipeline
+/// * `gatekeeper` - A reference to the gatekeeper
+/// * `pearl_counts` - The number of pearls each worker will have after the
+///                    actions decided so far this turn
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `config` - The config for this run, including the gatekeeper policy
+///
+/// # Returns
+///
+/// * `Option<ActionType>` - The gatekeeper's action, or None if it only has
+///                          finished pearls on its desk
+pub fn determine_gatekeeper_action(
+    state: &State,
+    gatekeeper: &Worker,
+    pearl_counts: &HashMap<u32, u32>,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    config: &Config,
+) -> Option<ActionType> {
+    let policy = &config.gatekeeper;
+    let ranked = rank_intake(gatekeeper, state, ability_map, neighbor_graph, config);
+
+    if count_pearls_in_flight(state, gatekeeper.id) < policy.max_in_flight {
+        let open_hop = |est: &IntakeEstimate| {
+            return est
+                .hop_costs
+                .iter()
+                .find(|(hop, _)| pearl_counts.get(hop) == Some(&0))
+                .copied();
+        };
+
+        for est in &ranked {
+            if let Some((hop, cost)) = open_hop(est) {
+                if cost.saturating_add(policy.self_nom_margin) < est.self_time {
+                    return Some(make_pass(gatekeeper.id, est.pearl_id, hop));
+                }
+            }
+        }
+
+        // As with passes between workers, a network that is only as fast
+        // is worth it when the gatekeeper has other pearls to get on with
+        if policy.self_nom_margin == 0 && ranked.len() >= config.strategy.equal_pass_min_desk {
+            for est in &ranked {
+                if let Some((hop, cost)) = open_hop(est) {
+                    if cost == est.self_time {
+                        return Some(make_pass(gatekeeper.id, est.pearl_id, hop));
+                    }
+                }
+            }
+        }
+    }
+
+    // Nothing worth dispatching, so nom the highest ranked pearl that the
+    // gatekeeper is itself the cheapest option for
+    for est in &ranked {
+        if est.total_cost() == est.self_time {
+            return Some(make_nom(gatekeeper.id, est.pearl_id));
+        }
+    }
+
+    return get_best_pearl_to_nom(gatekeeper, ability_map, config.strategy.nom_tie_break)
+        .map(|id| make_nom(gatekeeper.id, id));
+}
@@ -0,0 +1,371 @@
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::{self, json};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::config::load_ability_map;
+use super::explain::{explain_state, explanation_to_json};
+use super::metrics::{write_metrics, Metrics};
+use super::pearl_pipeline::Pipeline;
+use super::server::ServerOptions;
+use super::utils::get_action_string;
+use super::validate::validate_state;
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::config::Config;
+use models::state::State;
+
+/// The body of a request to `/actions` or `/explain`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DecisionRequest {
+    /// The game the state belongs to. Every state sent with the same ID is
+    /// decided by the same pipeline, so pearl paths carry over between
+    /// turns. `/explain` doesn't need one.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub state: State,
+}
+
+/// A response to an HTTP request, before it is written out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// Always JSON, with failures written as `{"error": "..."}`
+    pub body: String,
+}
+
+impl HttpResponse {
+    fn ok(body: String) -> HttpResponse {
+        return HttpResponse {
+            status: 200,
+            body: body,
+        };
+    }
+
+    fn error(status: u16, message: &str) -> HttpResponse {
+        return HttpResponse {
+            status: status,
+            body: json!({ "error": message }).to_string(),
+        };
+    }
+}
+
+/// The games being played over HTTP, each with its own `Pipeline`.
+pub struct Sessions {
+    config: Config,
+    ability_map: AbilityMap,
+    idle_timeout: Option<std::time::Duration>,
+    pipelines: HashMap<String, (Pipeline, Instant)>,
+    /// The combined metrics of every session that has ended
+    metrics: Metrics,
+}
+
+impl Sessions {
+    /// Creates an empty set of sessions.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The config every session decides with
+    /// * `ability_map` - The ability map every session decides with
+    /// * `idle_timeout` - Forget a session once it has had no requests for
+    ///                    this long
+    ///
+    /// # Returns
+    ///
+    /// * `Sessions` - The sessions, with none in progress
+    pub fn new(
+        config: Config,
+        ability_map: AbilityMap,
+        idle_timeout: Option<std::time::Duration>,
+    ) -> Sessions {
+        return Sessions {
+            config: config,
+            ability_map: ability_map,
+            idle_timeout: idle_timeout,
+            pipelines: HashMap::new(),
+            metrics: Metrics::default(),
+        };
+    }
+
+    /// Starts a session that decides with `pipeline`, replacing any session
+    /// with the same ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID requests for the session will carry
+    /// * `pipeline` - The pipeline to decide the session's turns with
+    ///
+    /// # Returns
+    ///
+    /// * None
+    pub fn open(&mut self, session_id: &str, pipeline: Pipeline) {
+        if let Some((old, _)) = self
+            .pipelines
+            .insert(session_id.to_string(), (pipeline, Instant::now()))
+        {
+            self.metrics.merge(old.metrics());
+        }
+    }
+
+    /// How many sessions are in progress
+    pub fn session_count(&self) -> usize {
+        return self.pipelines.len();
+    }
+
+    /// The combined metrics of every session that has ended so far
+    pub fn metrics(&self) -> &Metrics {
+        return &self.metrics;
+    }
+
+    /// Ends a session, keeping its metrics.
+    fn end(&mut self, session_id: &str) -> bool {
+        return match self.pipelines.remove(session_id) {
+            Some((pipeline, _)) => {
+                debug!(session = session_id, turns = pipeline.turn(); "session ended");
+                self.metrics.merge(pipeline.metrics());
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// Ends every session that is still in progress, keeping their metrics.
+    pub fn end_all(&mut self) {
+        let ids: Vec<String> = self.pipelines.keys().cloned().collect();
+        for id in ids {
+            self.end(&id);
+        }
+    }
+
+    /// Forgets every session that has been idle for too long.
+    fn evict_idle(&mut self) {
+        if let Some(limit) = self.idle_timeout {
+            let idle: Vec<String> = self
+                .pipelines
+                .iter()
+                .filter(|(_, (_, used))| used.elapsed() >= limit)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in idle {
+                self.end(&id);
+            }
+        }
+    }
+
+    /// Reads and checks the body of a decision request.
+    fn parse_request(&self, body: &str) -> Result<DecisionRequest, HttpResponse> {
+        let request: DecisionRequest = match serde_json::from_str(body) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(HttpResponse::error(
+                    400,
+                    &format!("Could not parse request: {}", e),
+                ))
+            }
+        };
+
+        let problems = validate_state(&request.state, self.config.gatekeeper_id);
+        if !problems.is_empty() {
+            return Err(HttpResponse::error(422, &problems.join("; ")));
+        }
+
+        return Ok(request);
+    }
+
+    /// Answers a single request. The routes are:
+    ///
+    /// * `POST /actions` - The actions `determine_actions` issues for the
+    ///                     state, decided by the request's session
+    /// * `POST /explain` - Why each worker takes the action it does, in the
+    ///                     same format as `atlantis explain --json`
+    /// * `DELETE /sessions/<id>` - Ends a session
+    ///
+    /// A session's metrics are kept once it ends, whether it is deleted,
+    /// goes idle or panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The request method, e.g. `POST`
+    /// * `path` - The request path, e.g. `/actions`
+    /// * `body` - The request body
+    ///
+    /// # Returns
+    ///
+    /// * `HttpResponse` - The status and JSON body to send back
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> HttpResponse {
+        self.evict_idle();
+
+        let path = path.split('?').next().unwrap_or_default();
+
+        if let Some(id) = path.strip_prefix("/sessions/") {
+            if method != "DELETE" {
+                return HttpResponse::error(405, "Use DELETE to end a session");
+            }
+            return match self.end(id) {
+                true => HttpResponse::ok(json!({ "ended": id }).to_string()),
+                false => HttpResponse::error(404, &format!("No session {}", id)),
+            };
+        }
+
+        if path != "/actions" && path != "/explain" {
+            return HttpResponse::error(404, &format!("No such endpoint {}", path));
+        }
+        if method != "POST" {
+            return HttpResponse::error(405, &format!("Use POST for {}", path));
+        }
+
+        let request = match self.parse_request(body) {
+            Ok(r) => r,
+            Err(response) => return response,
+        };
+
+        // A panic while deciding is answered with a 500, rather than taking
+        // the server and every other session down with it
+        if path == "/explain" {
+            let explained = panic::catch_unwind(AssertUnwindSafe(|| {
+                explain_state(&request.state, &self.ability_map, &self.config)
+            }));
+            return match explained {
+                Ok(explanation) => HttpResponse::ok(explanation_to_json(&explanation)),
+                Err(_) => HttpResponse::error(500, "Internal error explaining the state"),
+            };
+        }
+
+        let session_id = match &request.session_id {
+            Some(id) => id.clone(),
+            None => return HttpResponse::error(400, "/actions needs a session_id"),
+        };
+
+        let config = &self.config;
+        let ability_map = &self.ability_map;
+        let (pipeline, used) = self.pipelines.entry(session_id.clone()).or_insert_with(|| {
+            let pipeline = Pipeline::with_ability_map(config.clone(), ability_map.clone());
+            return (pipeline, Instant::now());
+        });
+        *used = Instant::now();
+
+        let decided = panic::catch_unwind(AssertUnwindSafe(|| pipeline.step(&request.state)));
+        return match decided {
+            Ok(actions) => HttpResponse::ok(get_action_string(actions)),
+            Err(_) => {
+                // The pipeline may have been left half way through a turn
+                warn!(session = session_id.as_str(); "panicked deciding a turn");
+                self.end(&session_id);
+                HttpResponse::error(500, "Internal error deciding the turn, session ended")
+            }
+        };
+    }
+}
+
+/// Serves decisions over HTTP, for clients that want request and response
+/// rather than a pipe. Only built with the `http` feature.
+pub struct HttpServer {
+    server: tiny_http::Server,
+    sessions: Sessions,
+    options: ServerOptions,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl HttpServer {
+    /// Starts listening on a TCP address. Nothing is answered until `run`
+    /// is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Where to listen, e.g. `127.0.0.1:8080`
+    /// * `config` - The config every session decides with
+    /// * `options` - How long sessions are kept, and how often to check
+    ///               whether the server is shutting down
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HttpServer, String>` - The server, or an error if the
+    ///                                  address couldn't be bound or the
+    ///                                  ability map couldn't be read
+    pub fn bind(addr: &str, config: Config, options: ServerOptions) -> Result<HttpServer, String> {
+        let ability_map = load_ability_map(&config)?;
+        let server = match tiny_http::Server::http(addr) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("Could not listen on {}: {}", addr, e)),
+        };
+
+        return Ok(HttpServer {
+            server: server,
+            sessions: Sessions::new(config, ability_map, options.idle_timeout),
+            options: options,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        });
+    }
+
+    /// The address the server is listening on, which is useful when binding
+    /// to port 0.
+    pub fn local_addr(&self) -> String {
+        return match self.server.server_addr().to_ip() {
+            Some(a) => a.to_string(),
+            None => String::new(),
+        };
+    }
+
+    /// A flag that stops the server once set, after the request it is
+    /// working on.
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        return self.shutdown.clone();
+    }
+
+    /// Answers requests one at a time until the shutdown flag is set, then
+    /// ends every session and writes out their combined metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * None
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - An error if the listener stopped working or
+    ///                          the metrics couldn't be written
+    pub fn run(&mut self) -> Result<(), String> {
+        while !self.shutdown.load(Ordering::SeqCst) {
+            let mut request = match self.server.recv_timeout(self.options.poll_interval) {
+                Ok(Some(r)) => r,
+                Ok(None) => continue,
+                Err(e) => {
+                    self.sessions.end_all();
+                    return Err(format!("Could not accept request: {}", e));
+                }
+            };
+
+            let mut body = String::new();
+            let response = match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => self
+                    .sessions
+                    .handle(&request.method().to_string(), request.url(), &body),
+                Err(e) => HttpResponse::error(400, &format!("Could not read body: {}", e)),
+            };
+            debug!(
+                method:% = request.method(),
+                path = request.url(),
+                status = response.status;
+                "answered request"
+            );
+
+            let content_type =
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]);
+            let mut reply =
+                tiny_http::Response::from_string(response.body).with_status_code(response.status);
+            if let Ok(h) = content_type {
+                reply = reply.with_header(h);
+            }
+
+            if let Err(e) = request.respond(reply) {
+                warn!(error:% = e; "could not answer request");
+            }
+        }
+
+        self.sessions.end_all();
+        return write_metrics(self.sessions.metrics(), &self.sessions.config.metrics);
+    }
+}
@@ -0,0 +1,326 @@
+use serde::Serialize;
+use serde_json;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use super::scenario::{check_scenario, generate_scenarios};
+use super::simulator::simulate;
+use crate::models;
+use models::ability_map::{AbilityMap, FLAVORS};
+use models::config::Config;
+use models::state::State;
+
+/// Floors with at most this many possible assignments are searched in
+/// full. Larger floors are searched by changing one worker at a time.
+const EXHAUSTIVE_LIMIT: usize = 729;
+
+/// How well an assignment of flavors did over the simulated games.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct LayoutScore {
+    /// The average score at the end of a game
+    pub score: f64,
+    /// The average number of turns a game lasted
+    pub turns: f64,
+}
+
+/// The flavor suggested for one worker.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WorkerFlavor {
+    pub worker_id: u32,
+    pub flavor: String,
+}
+
+/// A worker whose flavor the advisor would change.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FlavorChange {
+    pub worker_id: u32,
+    pub from: String,
+    pub to: String,
+}
+
+/// The flavor the advisor suggests for each worker, and what it expects
+/// to gain over the current ones.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LayoutAdvice {
+    pub current: LayoutScore,
+    pub suggested: LayoutScore,
+    /// How much the average score goes up with the suggested flavors
+    pub score_gain: f64,
+    /// The suggested flavor of every worker, in the order they appear in
+    /// the state
+    pub flavors: Vec<WorkerFlavor>,
+    pub changes: Vec<FlavorChange>,
+    /// How many different assignments were simulated
+    pub assignments_tried: usize,
+    /// Whether every possible assignment was tried
+    pub exhaustive: bool,
+}
+
+/// The totals over every game for one assignment, so assignments can be
+/// compared without rounding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Totals {
+    score: u64,
+    turns: u64,
+}
+
+/// Plays every game with the given flavors, remembering the result so no
+/// assignment is simulated twice.
+struct Evaluator<'a> {
+    games: Vec<State>,
+    ability_map: &'a AbilityMap,
+    config: &'a Config,
+    flavors: Vec<&'static str>,
+    seen: HashMap<Vec<usize>, Totals>,
+}
+
+impl Evaluator<'_> {
+    fn evaluate(&mut self, assignment: &[usize]) -> Totals {
+        if let Some(t) = self.seen.get(assignment) {
+            return *t;
+        }
+
+        let mut totals = Totals { score: 0, turns: 0 };
+        for game in &self.games {
+            let mut game = game.clone();
+            for (w, &f) in game.workers.iter_mut().zip(assignment) {
+                w.flavor = self.flavors[f].to_string();
+            }
+
+            let (turns, end) = simulate(
+                &game,
+                self.ability_map,
+                self.config,
+                self.config.scenario.turns,
+            );
+            totals.score += end.score as u64;
+            totals.turns += turns.len() as u64;
+        }
+
+        self.seen.insert(assignment.to_vec(), totals);
+        return totals;
+    }
+
+    fn score(&self, totals: Totals) -> LayoutScore {
+        let games = self.games.len().max(1) as f64;
+        return LayoutScore {
+            score: totals.score as f64 / games,
+            turns: totals.turns as f64 / games,
+        };
+    }
+}
+
+/// Whether one assignment beats another: a higher score first, then
+/// fewer turns, then fewer changes from the current flavors.
+fn beats(a: (Totals, usize), b: (Totals, usize)) -> bool {
+    let key = |(t, changes): (Totals, usize)| (Reverse(t.score), t.turns, changes);
+    return key(a) < key(b);
+}
+
+/// How many workers an assignment changes the flavor of.
+fn count_changes(assignment: &[usize], current: &[usize]) -> usize {
+    return assignment
+        .iter()
+        .zip(current)
+        .filter(|(a, c)| a != c)
+        .count();
+}
+
+/// Suggests a flavor for every worker on a floor, to get the most pearls
+/// through it. Each assignment is scored by playing the games described by
+/// the `scenario` section of the config with the simulator, and every
+/// assignment plays the same games so they are compared fairly.
+///
+/// Small floors have every assignment tried. Larger ones start from the
+/// current flavors and keep making whichever single change helps most,
+/// until none does.
+///
+/// # Arguments
+///
+/// * `floor` - A reference to a state with the floor and its current
+///             flavors. The pearls on it are ignored.
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config to simulate with
+///
+/// # Returns
+///
+/// * `Result<LayoutAdvice, String>` - The suggested flavors, or a message
+///                                    describing why none could be found
+pub fn advise_layout(
+    floor: &State,
+    ability_map: &AbilityMap,
+    config: &Config,
+) -> Result<LayoutAdvice, String> {
+    let problems = check_scenario(&config.scenario);
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+
+    let flavors: Vec<&'static str> = FLAVORS.to_vec();
+
+    let mut current: Vec<usize> = Vec::new();
+    for w in &floor.workers {
+        match flavors.iter().position(|f| *f == w.flavor) {
+            Some(i) => current.push(i),
+            None => return Err(format!("worker {} has unknown flavor {}", w.id, w.flavor)),
+        }
+    }
+
+    let mut evaluator = Evaluator {
+        games: generate_scenarios(floor, &config.scenario, config.gatekeeper_id),
+        ability_map: ability_map,
+        config: config,
+        flavors: flavors.clone(),
+        seen: HashMap::new(),
+    };
+
+    let current_totals = evaluator.evaluate(&current);
+    let mut best = current.clone();
+    let mut best_totals = current_totals;
+
+    let possible = (0..current.len())
+        .try_fold(1usize, |n, _| n.checked_mul(flavors.len()))
+        .filter(|&n| n <= EXHAUSTIVE_LIMIT);
+    let exhaustive = possible.is_some();
+
+    if let Some(count) = possible {
+        for index in 0..count {
+            let mut assignment: Vec<usize> = Vec::new();
+            let mut rest = index;
+            for _ in 0..current.len() {
+                assignment.push(rest % flavors.len());
+                rest /= flavors.len();
+            }
+
+            let totals = evaluator.evaluate(&assignment);
+            let changes = count_changes(&assignment, &current);
+            if beats(
+                (totals, changes),
+                (best_totals, count_changes(&best, &current)),
+            ) {
+                best = assignment;
+                best_totals = totals;
+            }
+        }
+    } else {
+        loop {
+            let mut step: Option<(Vec<usize>, Totals)> = None;
+
+            for i in 0..best.len() {
+                for f in 0..flavors.len() {
+                    if f == best[i] {
+                        continue;
+                    }
+
+                    let mut assignment = best.clone();
+                    assignment[i] = f;
+                    let totals = evaluator.evaluate(&assignment);
+                    let changes = count_changes(&assignment, &current);
+                    let to_beat = match &step {
+                        Some((a, t)) => (*t, count_changes(a, &current)),
+                        None => (best_totals, usize::MAX),
+                    };
+
+                    // Only a better score or a shorter game counts as progress
+                    let improves = beats((totals, 0), (best_totals, 0));
+                    if improves && beats((totals, changes), to_beat) {
+                        step = Some((assignment, totals));
+                    }
+                }
+            }
+
+            match step {
+                Some((a, t)) => {
+                    best = a;
+                    best_totals = t;
+                }
+                None => break,
+            }
+        }
+    }
+
+    let current_score = evaluator.score(current_totals);
+    let suggested_score = evaluator.score(best_totals);
+    let mut changes: Vec<FlavorChange> = Vec::new();
+    let mut suggested: Vec<WorkerFlavor> = Vec::new();
+
+    for (i, w) in floor.workers.iter().enumerate() {
+        suggested.push(WorkerFlavor {
+            worker_id: w.id,
+            flavor: flavors[best[i]].to_string(),
+        });
+        if best[i] != current[i] {
+            changes.push(FlavorChange {
+                worker_id: w.id,
+                from: w.flavor.clone(),
+                to: flavors[best[i]].to_string(),
+            });
+        }
+    }
+
+    return Ok(LayoutAdvice {
+        current: current_score,
+        suggested: suggested_score,
+        score_gain: suggested_score.score - current_score.score,
+        flavors: suggested,
+        changes: changes,
+        assignments_tried: evaluator.seen.len(),
+        exhaustive: exhaustive,
+    });
+}
+
+/// Writes layout advice out as readable text.
+///
+/// # Arguments
+///
+/// * `advice` - A reference to the advice to write
+///
+/// # Returns
+///
+/// * `String` - The advice, one fact per line
+pub fn layout_to_text(advice: &LayoutAdvice) -> String {
+    let mut out = format!(
+        "current:   score {:.2} in {:.1} turns\n",
+        advice.current.score, advice.current.turns
+    );
+    out += &format!(
+        "suggested: score {:.2} in {:.1} turns ({:+.2})\n",
+        advice.suggested.score, advice.suggested.turns, advice.score_gain
+    );
+
+    if advice.changes.is_empty() {
+        out += "no changes suggested\n";
+    }
+    for c in &advice.changes {
+        out += &format!("  worker {}: {} -> {}\n", c.worker_id, c.from, c.to);
+    }
+
+    let how = if advice.exhaustive {
+        "every possible assignment"
+    } else {
+        "one change at a time"
+    };
+    out += &format!(
+        "simulated {} assignments, {}\n",
+        advice.assignments_tried, how
+    );
+
+    return out;
+}
+
+/// Writes layout advice out as JSON.
+///
+/// # Arguments
+///
+/// * `advice` - A reference to the advice to write
+///
+/// # Returns
+///
+/// * `String` - The advice as a single line of JSON
+pub fn layout_to_json(advice: &LayoutAdvice) -> String {
+    return match serde_json::to_string(advice) {
+        Ok(s) => s,
+        Err(e) => panic!("Layout Error: could not write advice: {}", e),
+    };
+}
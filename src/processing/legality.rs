@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+
+use super::gatekeeper::get_next_hop_home;
+use super::utils::{get_best_pearl_to_nom, make_nom, make_pass};
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::action::{ActionType, Actions};
+use models::config::Config;
+use models::state::{NeighborGraph, State, Worker};
+
+/// An illegal action, and what was done about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegalityIssue {
+    pub worker_id: u32,
+    /// The action as the strategy proposed it
+    pub action: ActionType,
+    pub problem: String,
+    /// The legal action taken instead, or None if the action was dropped
+    pub repair: Option<ActionType>,
+}
+
+/// Finds a legal replacement for an illegal action. An unfinished pearl can
+/// always be nommed by the worker holding it; a finished pearl is sent home
+/// by the shortest route, as long as that route is legal.
+fn repair_action(
+    worker: &Worker,
+    pearl_id: u32,
+    neighbor_graph: &NeighborGraph,
+    gatekeeper_id: u32,
+) -> Option<ActionType> {
+    let pearl = worker.desk.iter().find(|p| p.id == pearl_id)?;
+
+    if !pearl.layers.is_empty() {
+        return Some(make_nom(worker.id, pearl_id));
+    }
+
+    return get_next_hop_home(worker.id, gatekeeper_id, neighbor_graph)
+        .map(|to| make_pass(worker.id, pearl_id, to));
+}
+
+/// Checks a single action on its own: whether the worker exists and holds
+/// the pearl, whether a pass goes to a neighbor, and whether a nom is for an
+/// unfinished pearl.
+fn check_action(
+    id: u32,
+    action: &ActionType,
+    workers: &HashMap<u32, &Worker>,
+    neighbor_graph: &NeighborGraph,
+) -> Option<String> {
+    let (actor, pearl_id) = match action {
+        ActionType::Pass(p) => (p.from_id, p.pearl_id),
+        ActionType::Nom(n) => (n.nautiloid_id, n.pearl_id),
+    };
+
+    if actor != id {
+        return Some(format!("the action is for worker {} instead", actor));
+    }
+
+    let worker = match workers.get(&id) {
+        Some(w) => w,
+        None => return Some(String::from("there is no such worker")),
+    };
+
+    let pearl = match worker.desk.iter().find(|p| p.id == pearl_id) {
+        Some(p) => p,
+        None => return Some(format!("pearl {} is not on the worker's desk", pearl_id)),
+    };
+
+    return match action {
+        ActionType::Pass(p) => {
+            let neighbors = neighbor_graph.get(&id).cloned().unwrap_or_default();
+            if neighbors.contains(&p.to_id) {
+                None
+            } else {
+                Some(format!("{} is not a neighbor", p.to_id))
+            }
+        }
+        ActionType::Nom(_) => {
+            if pearl.layers.is_empty() {
+                Some(format!("pearl {} is already finished", pearl_id))
+            } else {
+                None
+            }
+        }
+    };
+}
+
+/// Checks the actions a strategy proposed against the state they were
+/// decided for, before they are printed. Every action must be taken by a
+/// worker holding the pearl, a pass must go to a neighbor, a nom must be of
+/// an unfinished pearl, and no empty desk may be sent two pearls at once.
+///
+/// Illegal actions are repaired where possible: an unfinished pearl that
+/// can't be passed is nommed instead, a finished pearl that can't be nommed
+/// or passed where it was meant to go is sent home by the shortest route,
+/// and a nom of a finished pearl becomes a nom of the worker's best
+/// unfinished pearl. Anything that can't be repaired is dropped.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state the actions were decided for
+/// * `ability_map` - A reference to the ability map, for choosing a pearl
+///                   to nom instead
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `config` - The config for this run
+/// * `actions` - The actions proposed
+///
+/// # Returns
+///
+/// * `(Actions, Vec<LegalityIssue>)` - The legal actions, and every illegal
+///                                     action found along with what was done
+///                                     about it
+pub fn enforce_legality(
+    state: &State,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    config: &Config,
+    actions: Actions,
+) -> (Actions, Vec<LegalityIssue>) {
+    let workers: HashMap<u32, &Worker> = state.workers.iter().map(|w| (w.id, w)).collect();
+    let mut legal: Actions = HashMap::new();
+    let mut issues: Vec<LegalityIssue> = Vec::new();
+
+    // Decide in worker order, so the same actions are always kept when two
+    // passes compete for an empty desk
+    let mut ids: Vec<u32> = actions.keys().cloned().collect();
+    ids.sort();
+
+    for id in &ids {
+        let action = &actions[id];
+        let problem = match check_action(*id, action, &workers, neighbor_graph) {
+            Some(p) => p,
+            None => {
+                legal.insert(*id, action.clone());
+                continue;
+            }
+        };
+
+        let repair = match (workers.get(id), action) {
+            (Some(w), ActionType::Nom(n)) if n.nautiloid_id == *id => {
+                match get_best_pearl_to_nom(w, ability_map, config.strategy.nom_tie_break) {
+                    Some(p) => Some(make_nom(*id, p)),
+                    None => repair_action(w, n.pearl_id, neighbor_graph, config.gatekeeper_id),
+                }
+            }
+            (Some(w), ActionType::Pass(p)) if p.from_id == *id => {
+                repair_action(w, p.pearl_id, neighbor_graph, config.gatekeeper_id)
+            }
+            _ => None,
+        };
+
+        if let Some(r) = &repair {
+            legal.insert(*id, r.clone());
+        }
+        issues.push(LegalityIssue {
+            worker_id: *id,
+            action: action.clone(),
+            problem: problem,
+            repair: repair,
+        });
+    }
+
+    // An empty desk can only take one pearl a turn
+    let mut filled: HashSet<u32> = HashSet::new();
+    for id in &ids {
+        let pass = match legal.get(id) {
+            Some(ActionType::Pass(p)) => p.clone(),
+            _ => continue,
+        };
+
+        let receiver_empty = workers.get(&pass.to_id).is_some_and(|w| w.desk.is_empty());
+        if !receiver_empty || filled.insert(pass.to_id) {
+            continue;
+        }
+
+        let repair = workers
+            .get(id)
+            .and_then(|w| w.desk.iter().find(|p| p.id == pass.pearl_id))
+            .filter(|p| !p.layers.is_empty())
+            .map(|p| make_nom(*id, p.id));
+
+        match &repair {
+            Some(r) => legal.insert(*id, r.clone()),
+            None => legal.remove(id),
+        };
+        issues.push(LegalityIssue {
+            worker_id: *id,
+            action: ActionType::Pass(pass.clone()),
+            problem: format!("{} has already been sent a pearl this turn", pass.to_id),
+            repair: repair,
+        });
+    }
+
+    return (legal, issues);
+}
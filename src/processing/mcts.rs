@@ -0,0 +1,429 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::{HashMap, HashSet};
+
+use super::scenario::{generate_pearl, sample_arrivals};
+use super::scoring::plan_objective;
+use super::simulator::{apply_turn, InTransit};
+use super::strategy::{Anytime, Deadline, Strategy, Turn};
+use super::utils::{determine_actions, make_nom, make_pass};
+use crate::models;
+use models::action::{ActionType, Actions};
+use models::config::Rollout;
+use models::state::{PearlPaths, State};
+
+/// How much a pearl delivered a turn later is worth, relative to one
+/// delivered now.
+const DISCOUNT: f64 = 0.95;
+
+/// A set of actions tried from a position, and how well the playouts
+/// through it did.
+struct Branch {
+    actions: Actions,
+    /// The position it leads to, once it has been visited twice
+    child: Option<usize>,
+    visits: u32,
+    total: f64,
+}
+
+/// A position in the search tree. The tree is open loop: a position is
+/// reached by its sequence of actions, and the state is played out again
+/// on every visit, since new pearls may arrive differently each time.
+#[derive(Default)]
+struct TreeNode {
+    /// Empty until the position is first visited
+    branches: Vec<Branch>,
+}
+
+/// One playout of the game from the state the strategy was asked about.
+struct Playout<'a> {
+    turn: &'a Turn<'a>,
+    state: State,
+    in_transit: Vec<InTransit>,
+    pearl_paths: PearlPaths,
+    /// The ID the next pearl to arrive is given
+    next_id: u32,
+    turns: u32,
+    reward: f64,
+}
+
+/// Updates pearl paths for the passes in a set of actions, the same way the
+/// greedy strategy does: an unfinished pearl remembers who passed it, and a
+/// finished one going back the way it came forgets the last step. A
+/// finished pearl sent anywhere else forgets its path, and is sent home by
+/// the shortest route from then on.
+fn record_passes(state: &State, actions: &Actions, pearl_paths: &mut PearlPaths) {
+    for action in actions.values() {
+        let pass = match action {
+            ActionType::Pass(p) => p,
+            ActionType::Nom(_) => continue,
+        };
+        let finished = state
+            .workers
+            .iter()
+            .filter(|w| w.id == pass.from_id)
+            .flat_map(|w| &w.desk)
+            .any(|p| p.id == pass.pearl_id && p.layers.is_empty());
+
+        if !finished {
+            pearl_paths
+                .entry(pass.pearl_id)
+                .or_default()
+                .push(pass.from_id);
+        } else if pearl_paths.get(&pass.pearl_id).and_then(|p| p.last()) == Some(&pass.to_id) {
+            pearl_paths.get_mut(&pass.pearl_id).and_then(|p| p.pop());
+        } else {
+            pearl_paths.remove(&pass.pearl_id);
+        }
+    }
+}
+
+/// Picks a random legal action for every worker with pearls. A worker with
+/// a pearl left to nom always acts, and no empty desk is sent more than one
+/// pearl; a worker whose pass would break that noms or waits instead.
+fn random_actions(turn: &Turn, state: &State, rng: &mut StdRng) -> Actions {
+    let mut actions: Actions = HashMap::new();
+    let mut sent_to: HashSet<u32> = HashSet::new();
+    let empty: HashSet<u32> = state
+        .workers
+        .iter()
+        .filter(|w| w.desk.is_empty())
+        .map(|w| w.id)
+        .collect();
+
+    let mut workers: Vec<_> = state
+        .workers
+        .iter()
+        .filter(|w| !w.desk.is_empty())
+        .collect();
+    workers.sort_by_key(|w| w.id);
+
+    for w in workers {
+        let noms: Vec<ActionType> = w
+            .desk
+            .iter()
+            .filter(|p| !p.layers.is_empty())
+            .map(|p| make_nom(w.id, p.id))
+            .collect();
+
+        let mut options = noms.clone();
+        for p in &w.desk {
+            for &to in turn
+                .neighbor_graph
+                .get(&w.id)
+                .map(|n| n.as_slice())
+                .unwrap_or(&[])
+            {
+                if to != w.id {
+                    options.push(make_pass(w.id, p.id, to));
+                }
+            }
+        }
+
+        let mut choice = options.choose(rng).cloned();
+        if let Some(ActionType::Pass(p)) = &choice {
+            if empty.contains(&p.to_id) && !sent_to.insert(p.to_id) {
+                choice = noms.first().cloned();
+            }
+        }
+        if let Some(a) = choice {
+            actions.insert(w.id, a);
+        }
+    }
+
+    return actions;
+}
+
+impl Playout<'_> {
+    fn is_over(&self) -> bool {
+        let depth = self.turn.config.mcts.depth;
+        let settled = self.in_transit.is_empty()
+            && self.state.workers.iter().all(|w| w.desk.is_empty())
+            && self.turn.config.mcts.arrival_rate <= 0.0;
+
+        return self.turns >= depth || settled;
+    }
+
+    /// What greedy would do next, without changing the playout's paths
+    fn greedy_actions(&self) -> Actions {
+        let mut pearl_paths = self.pearl_paths.clone();
+        return determine_actions(
+            &self.state,
+            self.turn.ability_map,
+            self.turn.neighbor_graph,
+            &mut pearl_paths,
+            self.turn.config,
+        );
+    }
+
+    /// Plays a turn, counting any pearls delivered, then lets new pearls
+    /// arrive at the gatekeeper.
+    fn play(&mut self, actions: &Actions, rng: &mut StdRng) {
+        let config = self.turn.config;
+        let before = self.state.score;
+
+        self.state = apply_turn(
+            &self.state,
+            actions,
+            self.turn.ability_map,
+            config.gatekeeper_id,
+            self.turn.transfer_costs,
+            &mut self.in_transit,
+            &mut self.pearl_paths,
+        );
+        let delivered = self.state.score.saturating_sub(before);
+        self.reward += DISCOUNT.powi(self.turns as i32) * delivered as f64;
+        self.turns += 1;
+
+        for _ in 0..sample_arrivals(config.mcts.arrival_rate, rng) {
+            let pearl = generate_pearl(&config.scenario, self.next_id, rng);
+            self.next_id = self.next_id.saturating_add(1);
+            if let Some(gk) = self
+                .state
+                .workers
+                .iter_mut()
+                .find(|w| w.id == config.gatekeeper_id)
+            {
+                gk.desk.push(pearl);
+            }
+        }
+    }
+
+    /// Plays the game out to the search depth with the rollout policy
+    fn roll_out(&mut self, rng: &mut StdRng) {
+        while !self.is_over() {
+            let actions = match self.turn.config.mcts.rollout {
+                Rollout::Random => {
+                    let actions = random_actions(self.turn, &self.state, rng);
+                    record_passes(&self.state, &actions, &mut self.pearl_paths);
+                    actions
+                }
+                Rollout::Greedy => determine_actions(
+                    &self.state,
+                    self.turn.ability_map,
+                    self.turn.neighbor_graph,
+                    &mut self.pearl_paths,
+                    self.turn.config,
+                ),
+            };
+            self.play(&actions, rng);
+        }
+    }
+
+    /// The value of the playout: the pearls delivered, each worth less the
+    /// later it arrived, and the pearls still on the floor, worth more the
+    /// closer they are to being delivered
+    fn value(&self) -> f64 {
+        let left = plan_objective(
+            &self.state,
+            &HashMap::new(),
+            self.turn.ability_map,
+            self.turn.neighbor_graph,
+            self.turn.config.gatekeeper_id,
+        );
+        return self.reward + DISCOUNT.powi(self.turns as i32) * left;
+    }
+}
+
+/// Chooses each turn's actions with Monte Carlo tree search. Every playout
+/// follows the tree from the current state, choosing between the greedy
+/// actions and a handful of random ones at each position by how well they
+/// have done so far and how little they have been tried, then finishes
+/// the game with random or greedy rollouts. The actions taken are those
+/// tried most often from the current state.
+///
+/// The search runs for `mcts.iterations` playouts, or until the turn's
+/// deadline passes. New pearls can arrive during a playout, as configured
+/// by `mcts.arrival_rate`, and the same seed always gives the same actions.
+#[derive(Debug, Clone, Default)]
+pub struct MctsStrategy;
+
+/// Runs a single playout from the root of the tree, and adds its value to
+/// every branch it took.
+fn iterate(
+    tree: &mut Vec<TreeNode>,
+    turn: &Turn,
+    pearl_paths: &PearlPaths,
+    next_id: u32,
+    rng: &mut StdRng,
+) {
+    let mcts = &turn.config.mcts;
+    let mut playout = Playout {
+        turn: turn,
+        state: turn.state.clone(),
+        in_transit: Vec::new(),
+        pearl_paths: pearl_paths.clone(),
+        next_id: next_id,
+        turns: 0,
+        reward: 0.0,
+    };
+    let mut path: Vec<(usize, usize)> = Vec::new();
+    let mut node = 0;
+
+    while !playout.is_over() {
+        if tree[node].branches.is_empty() {
+            let mut candidates = vec![playout.greedy_actions()];
+            for _ in 0..mcts.candidates {
+                let actions = random_actions(turn, &playout.state, rng);
+                if !candidates.contains(&actions) {
+                    candidates.push(actions);
+                }
+            }
+            tree[node].branches = candidates
+                .into_iter()
+                .map(|a| Branch {
+                    actions: a,
+                    child: None,
+                    visits: 0,
+                    total: 0.0,
+                })
+                .collect();
+        }
+
+        let branches = &tree[node].branches;
+        let pick = match branches.iter().position(|b| b.visits == 0) {
+            Some(i) => i,
+            None => {
+                let visits: u32 = branches.iter().map(|b| b.visits).sum();
+                let log_visits = (visits as f64).ln();
+                let score = |b: &Branch| {
+                    b.total / b.visits as f64
+                        + mcts.exploration * (log_visits / b.visits as f64).sqrt()
+                };
+
+                let mut best = 0;
+                for (i, b) in branches.iter().enumerate() {
+                    if score(b) > score(&branches[best]) {
+                        best = i;
+                    }
+                }
+                best
+            }
+        };
+
+        let actions = tree[node].branches[pick].actions.clone();
+        let first_visit = tree[node].branches[pick].visits == 0;
+        path.push((node, pick));
+        record_passes(&playout.state, &actions, &mut playout.pearl_paths);
+        playout.play(&actions, rng);
+
+        if first_visit {
+            break;
+        }
+        node = match tree[node].branches[pick].child {
+            Some(c) => c,
+            None => {
+                tree.push(TreeNode::default());
+                let c = tree.len() - 1;
+                tree[node].branches[pick].child = Some(c);
+                c
+            }
+        };
+    }
+
+    playout.roll_out(rng);
+
+    // Values are shared out per pearl on the floor, so the exploration
+    // weight means the same however busy the floor is
+    let pearls: usize = turn.state.workers.iter().map(|w| w.desk.len()).sum();
+    let value = playout.value() / pearls.max(1) as f64;
+    for (n, b) in path {
+        let branch = &mut tree[n].branches[b];
+        branch.visits += 1;
+        branch.total += value;
+    }
+}
+
+impl Strategy for MctsStrategy {
+    fn name(&self) -> &str {
+        return "mcts";
+    }
+
+    fn decide(&mut self, turn: &Turn, pearl_paths: &mut PearlPaths) -> Actions {
+        return match self.decide_within(turn, pearl_paths, &Deadline::unlimited()) {
+            Anytime::Complete(a) | Anytime::BestSoFar(a) => a,
+            Anytime::Nothing => HashMap::new(),
+        };
+    }
+
+    fn decide_within(
+        &mut self,
+        turn: &Turn,
+        pearl_paths: &mut PearlPaths,
+        deadline: &Deadline,
+    ) -> Anytime {
+        let mcts = &turn.config.mcts;
+        let mut rng = StdRng::seed_from_u64(mcts.seed.wrapping_add(turn.number as u64));
+        let mut tree: Vec<TreeNode> = vec![TreeNode::default()];
+
+        // Pearls that arrive during a playout get IDs nobody has used yet
+        let next_id = turn
+            .state
+            .workers
+            .iter()
+            .flat_map(|w| &w.desk)
+            .map(|p| p.id.saturating_add(1))
+            .chain(pearl_paths.keys().map(|id| id.saturating_add(1)))
+            .max()
+            .unwrap_or(1);
+
+        let mut finished = true;
+        for i in 0..mcts.iterations {
+            if deadline.expired() {
+                if i == 0 {
+                    return Anytime::Nothing;
+                }
+                finished = false;
+                break;
+            }
+            iterate(&mut tree, turn, pearl_paths, next_id, &mut rng);
+        }
+
+        // Most visited first, then best on average, then the first tried,
+        // which is greedy's
+        let mut best: Option<&Branch> = None;
+        for b in &tree[0].branches {
+            let better = match best {
+                None => true,
+                Some(c) => {
+                    b.visits > c.visits
+                        || (b.visits == c.visits
+                            && b.visits > 0
+                            && b.total / b.visits as f64 > c.total / c.visits as f64)
+                }
+            };
+            if better {
+                best = Some(b);
+            }
+        }
+
+        let actions = match best {
+            Some(b) => b.actions.clone(),
+            None => {
+                // No playouts were run, e.g. with `mcts.iterations` set to 0,
+                // so there is nothing better than greedy to offer. That is
+                // only a best guess if the time ran out as well.
+                let greedy = determine_actions(
+                    turn.state,
+                    turn.ability_map,
+                    turn.neighbor_graph,
+                    pearl_paths,
+                    turn.config,
+                );
+                return if deadline.expired() {
+                    Anytime::BestSoFar(greedy)
+                } else {
+                    Anytime::Complete(greedy)
+                };
+            }
+        };
+        record_passes(turn.state, &actions, pearl_paths);
+
+        return if finished {
+            Anytime::Complete(actions)
+        } else {
+            Anytime::BestSoFar(actions)
+        };
+    }
+}
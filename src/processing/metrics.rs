@@ -0,0 +1,300 @@
+use serde_json::{self, json, Value};
+use std::fs;
+use std::time::Duration;
+
+use super::gatekeeper::count_pearls_in_flight;
+use crate::models;
+use models::action::{ActionType, Actions};
+use models::config::MetricsConfig;
+use models::state::{PearlPaths, State};
+
+/// Upper bounds of the turn latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 9] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+/// Upper bounds of the buckets for counts of workers or pearls
+const COUNT_BUCKETS: [f64; 8] = [0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0];
+/// Upper bounds of the buckets for how many hops a pearl is from home
+const HOP_BUCKETS: [f64; 8] = [0.0, 1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0];
+
+/// Counts how many observations fell at or below each of a fixed set of
+/// upper bounds, along with their sum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    /// How many observations fell in each bucket, with one extra at the end
+    /// for those above every bound
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram with the given bucket upper bounds, which
+    /// should be in increasing order.
+    pub fn new(bounds: &[f64]) -> Histogram {
+        return Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        };
+    }
+
+    /// Records a single observation
+    pub fn observe(&mut self, value: f64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|b| value <= *b)
+            .unwrap_or(self.bounds.len());
+
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Adds every observation from another histogram with the same buckets
+    pub fn merge(&mut self, other: &Histogram) {
+        for (c, o) in self.counts.iter_mut().zip(&other.counts) {
+            *c += o;
+        }
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    /// How many observations have been recorded
+    pub fn count(&self) -> u64 {
+        return self.count;
+    }
+
+    /// The sum of every observation
+    pub fn sum(&self) -> f64 {
+        return self.sum;
+    }
+
+    /// The mean of every observation, or 0 if there are none
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        return self.sum / self.count as f64;
+    }
+
+    /// The number of observations at or below each bound, ending with the
+    /// total for `+Inf`, as Prometheus expects.
+    fn cumulative(&self) -> Vec<(String, u64)> {
+        let mut total: u64 = 0;
+        let mut out: Vec<(String, u64)> = Vec::new();
+
+        for (i, c) in self.counts.iter().enumerate() {
+            total += c;
+            let le = match self.bounds.get(i) {
+                Some(b) => b.to_string(),
+                None => String::from("+Inf"),
+            };
+            out.push((le, total));
+        }
+
+        return out;
+    }
+
+    fn to_value(&self) -> Value {
+        let buckets: Vec<Value> = self
+            .cumulative()
+            .into_iter()
+            .map(|(le, c)| json!({"le": le, "count": c}))
+            .collect();
+
+        return json!({
+            "count": self.count,
+            "sum": self.sum,
+            "mean": self.mean(),
+            "buckets": buckets,
+        });
+    }
+}
+
+/// Numbers describing how the pipeline behaved over a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics {
+    pub turns: u64,
+    pub passes: u64,
+    pub noms: u64,
+    /// How long each turn took to decide, in seconds
+    pub turn_latency: Histogram,
+    /// How many workers had an empty desk each turn
+    pub idle_workers: Histogram,
+    /// How many pearls were away from the gatekeeper each turn
+    pub pearls_in_flight: Histogram,
+    /// How many hops each pearl was from the gatekeeper at the end of each
+    /// turn, according to `pearl_paths`
+    pub hops_per_pearl: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        return Metrics::new();
+    }
+}
+
+impl Metrics {
+    /// Creates a set of metrics with nothing recorded
+    pub fn new() -> Metrics {
+        return Metrics {
+            turns: 0,
+            passes: 0,
+            noms: 0,
+            turn_latency: Histogram::new(&LATENCY_BUCKETS),
+            idle_workers: Histogram::new(&COUNT_BUCKETS),
+            pearls_in_flight: Histogram::new(&COUNT_BUCKETS),
+            hops_per_pearl: Histogram::new(&HOP_BUCKETS),
+        };
+    }
+
+    /// Records everything about a single turn once its actions have been
+    /// decided.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A reference to the state the turn was decided for
+    /// * `actions` - A reference to the actions decided
+    /// * `pearl_paths` - A reference to the pearl paths after the turn
+    /// * `gatekeeper_id` - The ID of the gatekeeper
+    /// * `latency` - How long the turn took to decide
+    pub fn record_turn(
+        &mut self,
+        state: &State,
+        actions: &Actions,
+        pearl_paths: &PearlPaths,
+        gatekeeper_id: u32,
+        latency: Duration,
+    ) {
+        self.turns += 1;
+
+        for a in actions.values() {
+            match a {
+                ActionType::Pass(_) => self.passes += 1,
+                ActionType::Nom(_) => self.noms += 1,
+            }
+        }
+
+        let idle = state.workers.iter().filter(|w| w.desk.is_empty()).count();
+        self.turn_latency.observe(latency.as_secs_f64());
+        self.idle_workers.observe(idle as f64);
+        self.pearls_in_flight
+            .observe(count_pearls_in_flight(state, gatekeeper_id) as f64);
+
+        for path in pearl_paths.values() {
+            self.hops_per_pearl.observe(path.len() as f64);
+        }
+    }
+
+    /// Adds everything recorded by another set of metrics, e.g. from
+    /// another game played by the same server
+    pub fn merge(&mut self, other: &Metrics) {
+        self.turns += other.turns;
+        self.passes += other.passes;
+        self.noms += other.noms;
+        self.turn_latency.merge(&other.turn_latency);
+        self.idle_workers.merge(&other.idle_workers);
+        self.pearls_in_flight.merge(&other.pearls_in_flight);
+        self.hops_per_pearl.merge(&other.hops_per_pearl);
+    }
+
+    /// Writes the metrics out as a single line of JSON
+    pub fn to_json(&self) -> String {
+        let value = json!({
+            "turns": self.turns,
+            "actions": {"pass": self.passes, "nom": self.noms},
+            "turn_latency_seconds": self.turn_latency.to_value(),
+            "idle_workers": self.idle_workers.to_value(),
+            "pearls_in_flight": self.pearls_in_flight.to_value(),
+            "hops_per_pearl": self.hops_per_pearl.to_value(),
+        });
+
+        return value.to_string();
+    }
+
+    /// Writes the metrics out in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        out += "# HELP atlantis_turns_total Turns decided.\n";
+        out += "# TYPE atlantis_turns_total counter\n";
+        out += &format!("atlantis_turns_total {}\n", self.turns);
+
+        out += "# HELP atlantis_actions_total Actions issued, by kind.\n";
+        out += "# TYPE atlantis_actions_total counter\n";
+        out += &format!("atlantis_actions_total{{kind=\"pass\"}} {}\n", self.passes);
+        out += &format!("atlantis_actions_total{{kind=\"nom\"}} {}\n", self.noms);
+
+        let histograms = [
+            (
+                "atlantis_turn_latency_seconds",
+                "Time taken to decide each turn.",
+                &self.turn_latency,
+            ),
+            (
+                "atlantis_idle_workers",
+                "Workers with an empty desk each turn.",
+                &self.idle_workers,
+            ),
+            (
+                "atlantis_pearls_in_flight",
+                "Pearls away from the gatekeeper each turn.",
+                &self.pearls_in_flight,
+            ),
+            (
+                "atlantis_hops_per_pearl",
+                "Hops each pearl is from the gatekeeper at the end of each turn.",
+                &self.hops_per_pearl,
+            ),
+        ];
+
+        for (name, help, hist) in histograms.iter() {
+            out += &format!("# HELP {} {}\n# TYPE {} histogram\n", name, help, name);
+            for (le, c) in hist.cumulative() {
+                out += &format!("{}_bucket{{le=\"{}\"}} {}\n", name, le, c);
+            }
+            out += &format!(
+                "{}_sum {}\n{}_count {}\n",
+                name,
+                hist.sum(),
+                name,
+                hist.count()
+            );
+        }
+
+        return out;
+    }
+}
+
+/// Writes the metrics for a run once it has finished: as JSON to the file
+/// named in the config (or `stderr`), and in the Prometheus text format if
+/// a path for it is set.
+///
+/// # Arguments
+///
+/// * `metrics` - A reference to the metrics to write
+/// * `config` - Where to write them
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if a file couldn't be written
+pub fn write_metrics(metrics: &Metrics, config: &MetricsConfig) -> Result<(), String> {
+    match &config.json_path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, metrics.to_json() + "\n") {
+                return Err(format!("Could not write metrics to {}: {}", path, e));
+            }
+        }
+        None => eprintln!("metrics: {}", metrics.to_json()),
+    }
+
+    if let Some(path) = &config.prometheus_path {
+        if let Err(e) = fs::write(path, metrics.to_prometheus()) {
+            return Err(format!("Could not write metrics to {}: {}", path, e));
+        }
+    }
+
+    return Ok(());
+}
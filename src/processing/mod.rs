@@ -1,5 +1,28 @@
+pub mod ability_estimator;
+pub mod bound;
+pub mod config;
+pub mod explain;
+pub mod fuzz;
+pub mod gatekeeper;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod layout;
+pub mod legality;
+pub mod mcts;
+pub mod metrics;
 pub mod pearl_pipeline;
+pub mod progress;
+pub mod render;
+pub mod scenario;
+pub mod scoring;
+pub mod server;
+pub mod simulator;
+pub mod solver;
+pub mod strategy;
+pub mod topology;
+pub mod transfer;
 pub mod utils;
+pub mod validate;
 
 #[cfg(test)]
 mod tests;
@@ -1,28 +1,456 @@
-use core::panic;
+use log::{debug, error, info, warn};
 use serde_json;
 use std::collections::HashMap;
 use std::io::{self, BufRead};
+use std::time::Instant;
 
-use super::utils::{build_neighbor_graph, determine_actions, get_ability_map, get_action_string};
+use serde::Serialize;
+
+use super::ability_estimator::{AbilityEstimator, Disagreement};
+use super::config::load_ability_map;
+use super::legality::{enforce_legality, LegalityIssue};
+use super::metrics::{write_metrics, Metrics};
+use super::progress::LayerTracker;
+use super::scoring::{project_score, ScoreProjection};
+use super::simulator::hold_back_pass;
+use super::strategy::{
+    build_strategy, Anytime, Deadline, DeadlineStats, GreedyStrategy, Strategy, Turn,
+};
+use super::transfer::TransferCosts;
+use super::utils::{build_state_graph, get_action_string, get_worker_ids};
+use super::validate::validate_state;
 use crate::models;
 use models::ability_map::AbilityMap;
-use models::state::State;
+use models::action::{ActionType, Actions};
+use models::config::Config;
+use models::state::{Edges, NeighborGraph, NeighborMap, PearlPaths, State};
+
+/// A copy of everything a `Pipeline` has carried over between turns, for
+/// inspecting or logging a game part way through.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PipelineSnapshot {
+    /// How many turns have been decided
+    pub turn: u32,
+    pub strategy: String,
+    pub pearl_paths: PearlPaths,
+    /// The ability map learned from the states seen so far
+    pub learned_ability_map: AbilityMap,
+    /// The score projected for the last turn's actions
+    pub projection: Option<ScoreProjection>,
+    pub deadline_stats: DeadlineStats,
+}
+
+/// The pearl processing pipeline for a single game. It holds everything
+/// that has to carry over from one turn to the next, so that embedders and
+/// tests can feed it states one at a time and get back the actions to take.
+pub struct Pipeline {
+    config: Config,
+    ability_map: AbilityMap,
+    strategy: Box<dyn Strategy>,
+    pearl_paths: PearlPaths,
+    estimator: AbilityEstimator,
+    layer_tracker: LayerTracker,
+    transfer_costs: TransferCosts,
+    projection: Option<ScoreProjection>,
+    turn: u32,
+    deadline_stats: DeadlineStats,
+    metrics: Metrics,
+    /// The illegal actions found in the last turn's decision
+    legality_issues: Vec<LegalityIssue>,
+    /// Every disagreement with the ability map logged so far, so each is
+    /// only warned about once
+    disagreements: Vec<Disagreement>,
+    /// The neighbor graph, along with the neighbor map and worker IDs it
+    /// was built from, so it's only rebuilt when the floor layout changes
+    neighbor_graph: Option<(NeighborMap, Edges, Vec<u32>, NeighborGraph)>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline using the strategy named by `planner` in the
+    /// config, reading the ability map named in the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The config for this game
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Pipeline, String>` - The pipeline, or an error if the
+    ///                                ability map couldn't be read
+    pub fn new(config: Config) -> Result<Pipeline, String> {
+        let ability_map = load_ability_map(&config)?;
+        return Ok(Pipeline::with_ability_map(config, ability_map));
+    }
+
+    /// Creates a pipeline using the strategy named by `planner` in the
+    /// config and the given ability map, ignoring the `ability_map_path` in
+    /// the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The config for this game
+    /// * `ability_map` - The ability map to decide with
+    ///
+    /// # Returns
+    ///
+    /// * `Pipeline` - The pipeline
+    pub fn with_ability_map(config: Config, ability_map: AbilityMap) -> Pipeline {
+        let transfer_costs = TransferCosts::new(&config.transfer);
+
+        return Pipeline {
+            strategy: build_strategy(&config),
+            config: config,
+            ability_map: ability_map,
+            pearl_paths: HashMap::new(),
+            estimator: AbilityEstimator::new(),
+            layer_tracker: LayerTracker::new(),
+            transfer_costs: transfer_costs,
+            projection: None,
+            turn: 0,
+            deadline_stats: DeadlineStats::default(),
+            metrics: Metrics::new(),
+            legality_issues: Vec::new(),
+            disagreements: Vec::new(),
+            neighbor_graph: None,
+        };
+    }
+
+    /// Replaces the strategy used to decide each turn.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - The strategy to use from now on
+    ///
+    /// # Returns
+    ///
+    /// * `Pipeline` - The pipeline, now using `strategy`
+    pub fn with_strategy(mut self, strategy: Box<dyn Strategy>) -> Pipeline {
+        self.strategy = strategy;
+        return self;
+    }
+
+    /// Makes sure the cached neighbor graph matches a state, rebuilding it
+    /// only if the neighbor map, the edges or the set of workers has
+    /// changed. The transfer costs are rebuilt along with it, since the
+    /// state's edges can override the config.
+    fn refresh_neighbor_graph(&mut self, state: &State) {
+        let worker_ids = get_worker_ids(&state.workers);
+
+        let stale = match &self.neighbor_graph {
+            Some((map, edges, ids, _)) => {
+                map != &state.neighbor_map || edges != &state.edges || ids != &worker_ids
+            }
+            None => true,
+        };
+
+        if stale {
+            let graph = build_state_graph(state, &self.config.transfer);
+            self.transfer_costs = TransferCosts::new(&self.config.transfer).with_graph(&graph);
+            self.neighbor_graph = Some((
+                state.neighbor_map.clone(),
+                state.edges.clone(),
+                worker_ids,
+                graph,
+            ));
+        }
+    }
+
+    /// Decides the actions for one turn. If `learn_ability_map` is set, the
+    /// ability map learned from the states seen so far is used to decide.
+    ///
+    /// The strategy gets `turn_budget_ms` to decide in. If it runs out of
+    /// time without a plan, the greedy actions are used instead. Whatever
+    /// it decides is checked for legality, and illegal actions are repaired
+    /// or dropped before they are returned.
+    ///
+    /// The strategy sees every layer's original thickness, as given by the
+    /// harness or as first seen by the pipeline, so it knows how much work
+    /// has already gone into each pearl.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A reference to the state of the pipeline this turn
+    ///
+    /// # Returns
+    ///
+    /// * `Actions` - The action each worker should take
+    pub fn step(&mut self, state: &State) -> Actions {
+        let started = Instant::now();
+        let annotated = self.layer_tracker.annotate(state);
+        let state = &annotated;
+        self.refresh_neighbor_graph(state);
+        self.estimator.observe(state);
+        self.report_disagreements();
+
+        let neighbor_graph = match &self.neighbor_graph {
+            Some((_, _, _, g)) => g,
+            None => panic!("Neighbor Graph Error: the neighbor graph was not built"),
+        };
+
+        let learned_map;
+        let decision_map = if self.config.learn_ability_map {
+            learned_map = self.estimator.ability_map(&self.ability_map);
+            &learned_map
+        } else {
+            &self.ability_map
+        };
+
+        let turn = Turn {
+            number: self.turn,
+            state: state,
+            ability_map: decision_map,
+            neighbor_graph: neighbor_graph,
+            transfer_costs: &self.transfer_costs,
+            config: &self.config,
+        };
+        let deadline = Deadline::from_config(&self.config);
+        let actions = match self
+            .strategy
+            .decide_within(&turn, &mut self.pearl_paths, &deadline)
+        {
+            Anytime::Complete(a) => {
+                if deadline.expired() {
+                    self.deadline_stats.overran += 1;
+                } else {
+                    self.deadline_stats.completed += 1;
+                }
+                a
+            }
+            Anytime::BestSoFar(a) => {
+                self.deadline_stats.best_so_far += 1;
+                a
+            }
+            Anytime::Nothing => {
+                self.deadline_stats.greedy_fallback += 1;
+                GreedyStrategy.decide(&turn, &mut self.pearl_paths)
+            }
+        };
+
+        let (actions, issues) =
+            enforce_legality(state, decision_map, neighbor_graph, &self.config, actions);
+        for issue in &issues {
+            warn!(
+                turn = self.turn,
+                worker = issue.worker_id,
+                action:? = issue.action,
+                repair:? = issue.repair;
+                "illegal action: {}", issue.problem
+            );
+            undo_pass(&mut self.pearl_paths, state, &issue.action, &actions);
+        }
+        self.legality_issues = issues;
+
+        self.projection = Some(project_score(
+            state,
+            &actions,
+            decision_map,
+            neighbor_graph,
+            self.config.gatekeeper_id,
+            self.config.score_horizon,
+        ));
+        self.turn += 1;
+
+        self.metrics.record_turn(
+            state,
+            &actions,
+            &self.pearl_paths,
+            self.config.gatekeeper_id,
+            started.elapsed(),
+        );
+
+        return actions;
+    }
+
+    /// Forgets everything about the current game, so the pipeline can start
+    /// a new one. The config, ability map and strategy are kept.
+    pub fn reset(&mut self) {
+        self.pearl_paths.clear();
+        self.estimator = AbilityEstimator::new();
+        self.layer_tracker = LayerTracker::new();
+        self.projection = None;
+        self.turn = 0;
+        self.deadline_stats = DeadlineStats::default();
+        self.metrics = Metrics::new();
+        self.legality_issues.clear();
+        self.disagreements.clear();
+        self.neighbor_graph = None;
+        self.strategy.reset();
+    }
+
+    /// Takes a copy of everything carried over between turns so far.
+    pub fn snapshot(&self) -> PipelineSnapshot {
+        return PipelineSnapshot {
+            turn: self.turn,
+            strategy: self.strategy.name().to_string(),
+            pearl_paths: self.pearl_paths.clone(),
+            learned_ability_map: self.estimator.ability_map(&self.ability_map),
+            projection: self.projection.clone(),
+            deadline_stats: self.deadline_stats.clone(),
+        };
+    }
+
+    /// How many turns have been decided since the pipeline was created or
+    /// last reset
+    pub fn turn(&self) -> u32 {
+        return self.turn;
+    }
+
+    /// The config this pipeline decides with
+    pub fn config(&self) -> &Config {
+        return &self.config;
+    }
+
+    /// The static ability map this pipeline was created with
+    pub fn ability_map(&self) -> &AbilityMap {
+        return &self.ability_map;
+    }
+
+    /// The ability map learned from the states seen so far
+    pub fn estimator(&self) -> &AbilityEstimator {
+        return &self.estimator;
+    }
+
+    /// How each turn's decision was reached against the per-turn budget
+    pub fn deadline_stats(&self) -> &DeadlineStats {
+        return &self.deadline_stats;
+    }
+
+    /// Numbers describing how the pipeline has behaved so far
+    pub fn metrics(&self) -> &Metrics {
+        return &self.metrics;
+    }
+
+    /// The illegal actions the strategy proposed in the last `step`, and
+    /// what was done about them
+    pub fn legality_issues(&self) -> &[LegalityIssue] {
+        return &self.legality_issues;
+    }
+
+    /// The score projected for the actions returned by the last `step`
+    pub fn projection(&self) -> Option<&ScoreProjection> {
+        return self.projection.as_ref();
+    }
+
+    /// Every way the states seen so far have contradicted the ability map
+    pub fn disagreements(&self) -> &[Disagreement] {
+        return &self.disagreements;
+    }
+
+    /// Warns about each contradiction between the observed rates and the
+    /// ability map the first time it's seen, whichever entry point is
+    /// feeding the pipeline.
+    fn report_disagreements(&mut self) {
+        for d in self.estimator.disagreements(&self.ability_map) {
+            if !self.disagreements.contains(&d) {
+                warn!(
+                    turn = self.turn,
+                    flavor = d.flavor.as_str(),
+                    color = d.color.as_str(),
+                    expected = d.expected,
+                    observed = d.observed;
+                    "ability map disagrees with observed rate"
+                );
+                self.disagreements.push(d);
+            }
+        }
+    }
+}
+
+/// Takes back what the strategy did to a pearl's path when it decided a
+/// pass, if that pass was not kept. An unfinished pearl forgets the step it
+/// was given, and a finished one gets back the hop home it was handed.
+fn undo_pass(
+    pearl_paths: &mut PearlPaths,
+    state: &State,
+    proposed: &ActionType,
+    actions: &Actions,
+) {
+    let pass = match proposed {
+        ActionType::Pass(p) => p,
+        ActionType::Nom(_) => return,
+    };
+
+    if actions.get(&pass.from_id) == Some(proposed) {
+        return;
+    }
+
+    let finished = state
+        .workers
+        .iter()
+        .filter(|w| w.id == pass.from_id)
+        .flat_map(|w| &w.desk)
+        .any(|p| p.id == pass.pearl_id && p.layers.is_empty());
+    hold_back_pass(pearl_paths, pass, finished);
+}
+
+/// Decides the actions for a single line of input. This is the path every
+/// line read from `stdin` or a server connection takes. The state is checked
+/// with `validate_state` first, so a line the pipeline can't safely decide
+/// is rejected rather than deciding on it.
+///
+/// The score the last turn projected is logged at `debug` alongside the
+/// score the state actually reports, so the score model can be calibrated.
+///
+/// # Arguments
+///
+/// * `pipeline` - The pipeline for the game the line belongs to
+/// * `line` - A single JSON state
+///
+/// # Returns
+///
+/// * `Result<String, String>` - The actions to print, or a message saying
+///                              why the line isn't a valid state
+pub fn process_line(pipeline: &mut Pipeline, line: &str) -> Result<String, String> {
+    let data: State = match serde_json::from_str(line) {
+        Ok(d) => d,
+        Err(e) => return Err(format!("Could not parse state: {}", e)),
+    };
+
+    let problems = validate_state(&data, pipeline.config().gatekeeper_id);
+    if !problems.is_empty() {
+        return Err(format!("Invalid state: {}", problems.join("; ")));
+    }
+
+    if let Some(proj) = pipeline.projection() {
+        debug!(
+            turn = pipeline.turn(),
+            projected = proj.projected_score,
+            actual = data.score;
+            "score projection"
+        );
+    }
+
+    let actions = pipeline.step(&data);
+    return Ok(get_action_string(actions));
+}
 
 /// Runs the main loop for the pearl processing pipeline, which consists of
 /// repeatedly reading lines form `stdin`, converting that JSON-like string
-/// into our custom State type, and then passing that to the worker method
-/// `determine_action`
+/// into our custom State type, and then passing that to a `Pipeline`. Each
+/// line goes through `process_line`, so a state that fails validation ends
+/// the run with an error instead of being decided on.
+///
+/// After each turn the projected score is compared against the score the
+/// next state actually reports, and both are logged at `debug` so the score
+/// model can be calibrated without disturbing the actions on `stdout`.
+/// The ability map is also learned from the states as they arrive, and any
+/// disagreement with the static ability map is logged as a warning.
+///
+/// Once `stdin` is exhausted the metrics for the run are written out, as
+/// set in the `metrics` section of the config.
 ///
 /// # Arguments
 ///
-/// * None
+/// * `config` - The config for this run
 ///
 /// # Returns
 ///
-/// * ()
-pub fn run_pearl_processing() {
-    let ability_map: AbilityMap = get_ability_map();
-    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+/// * `Result<(), String>` - An error if the ability map named in the config
+///                          couldn't be read, `stdin` couldn't be read, a
+///                          line wasn't a valid state, or the metrics
+///                          couldn't be written
+pub fn run_pearl_processing(config: &Config) -> Result<(), String> {
+    let mut pipeline = Pipeline::new(config.clone())?;
 
     // Variables to handle looping over inputs from stdin until hitting the end
     let stdin = io::stdin();
@@ -35,23 +463,49 @@ pub fn run_pearl_processing() {
             Ok(0) => {
                 // read_line returns the number of bytes read before finding the next line end.
                 // So if it reads 0 bytes, then we know that nothing was there.
+                info!(turns = pipeline.turn(); "stdin closed");
                 eof = true;
             }
-            Ok(_) => {
-                let data: State = serde_json::from_str(&line).unwrap();
-                let neighbor_graph = build_neighbor_graph(&data.neighbor_map, &data.workers);
+            Ok(_) if line.trim().is_empty() => {
+                // Blank lines are skipped, as they are by the server
+                line.clear();
+            }
+            Ok(bytes) => {
+                debug!(turn = pipeline.turn(), bytes = bytes; "read state");
 
-                let actions =
-                    determine_actions(&data, &ability_map, &neighbor_graph, &mut pearl_paths);
+                let action_str = match process_line(&mut pipeline, &line) {
+                    Ok(a) => a,
+                    Err(e) => {
+                        error!(turn = pipeline.turn(), error = e.as_str(); "rejected state");
+                        return Err(e);
+                    }
+                };
 
-                let action_str = get_action_string(actions);
                 println!("{}", action_str);
+                debug!(turn = pipeline.turn(); "wrote actions");
 
                 line.clear();
             }
             Err(e) => {
-                panic!("Error reading from stdin: {}", e);
+                error!(error:% = e; "could not read from stdin");
+                return Err(format!("Error reading from stdin: {}", e));
             }
         }
     }
+
+    if config.turn_budget_ms.is_some() {
+        let stats = pipeline.deadline_stats();
+        info!(
+            turns = stats.turns(),
+            completed = stats.completed,
+            overran = stats.overran,
+            best_so_far = stats.best_so_far,
+            greedy_fallback = stats.greedy_fallback;
+            "turn budget"
+        );
+    }
+
+    write_metrics(pipeline.metrics(), &config.metrics)?;
+
+    return Ok(());
 }
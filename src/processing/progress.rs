@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use crate::models;
+use models::state::State;
+
+/// Remembers how thick each layer of each pearl was when it was first seen,
+/// so the work already put into a pearl is known even when the harness
+/// only reports what's left of it.
+///
+/// Layers are dissolved from the outside in, so a layer is identified by
+/// its pearl and how many layers lie beneath it, which doesn't change as
+/// the layers above it are removed.
+#[derive(Debug, Clone, Default)]
+pub struct LayerTracker {
+    originals: HashMap<(u32, usize), u32>,
+}
+
+impl LayerTracker {
+    pub fn new() -> LayerTracker {
+        return LayerTracker::default();
+    }
+
+    /// Records the layers in a state and returns a copy of it with every
+    /// layer's `original_thickness` filled in. An original thickness given
+    /// by the harness is always kept; otherwise the thickest the layer has
+    /// been seen is used. Pearls no longer in the state are forgotten.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A reference to the newest state of the pipeline
+    ///
+    /// # Returns
+    ///
+    /// * `State` - The state, with the original thickness of every layer
+    pub fn annotate(&mut self, state: &State) -> State {
+        let mut annotated = state.clone();
+        let mut seen: HashMap<(u32, usize), u32> = HashMap::new();
+
+        for w in annotated.workers.iter_mut() {
+            for p in w.desk.iter_mut() {
+                let depth = p.layers.len();
+                for (i, layer) in p.layers.iter_mut().enumerate() {
+                    let key = (p.id, depth - 1 - i);
+                    let known = self.originals.get(&key).copied().unwrap_or(0);
+                    let original = match layer.original_thickness {
+                        Some(o) => o.max(layer.thickness),
+                        None => known.max(layer.thickness),
+                    };
+
+                    layer.original_thickness = Some(original);
+                    seen.insert(key, original);
+                }
+            }
+        }
+
+        self.originals = seen;
+        return annotated;
+    }
+
+    /// The original thickness recorded for a layer, if it has been seen
+    ///
+    /// # Arguments
+    ///
+    /// * `pearl_id` - The ID of the pearl
+    /// * `depth` - How many layers lie beneath the layer
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u32>` - The layer's original thickness
+    pub fn original(&self, pearl_id: u32, depth: usize) -> Option<u32> {
+        return self.originals.get(&(pearl_id, depth)).copied();
+    }
+}
@@ -0,0 +1,167 @@
+use crate::models;
+use models::state::{Pearl, State};
+
+/// Describes a pearl's remaining layers, outermost first, e.g.
+/// `42 [Red 3/8, Blue 10]`. A partly processed layer shows its original
+/// thickness after the slash.
+fn describe_pearl(pearl: &Pearl) -> String {
+    let layers: Vec<String> = pearl
+        .layers
+        .iter()
+        .map(|l| {
+            if l.is_partial() {
+                format!("{} {}/{}", l.color, l.thickness, l.original())
+            } else {
+                format!("{} {}", l.color, l.thickness)
+            }
+        })
+        .collect();
+
+    return format!("{} [{}]", pearl.id, layers.join(", "));
+}
+
+/// Lists who a worker can pass to, going by the neighbor map and the
+/// state's edges the same way the neighbor graph does, but skipping
+/// entries that name unknown workers instead of panicking.
+fn outgoing_neighbors(state: &State, worker_id: u32) -> Vec<u32> {
+    let mut nbrs: Vec<u32> = Vec::new();
+    for pair in &state.neighbor_map {
+        if pair.len() == 2 && pair[0] == worker_id {
+            nbrs.push(pair[1]);
+        } else if pair.len() == 2 && pair[1] == worker_id {
+            nbrs.push(pair[0]);
+        }
+    }
+
+    for edge in &state.edges {
+        let [from, to] = edge.workers;
+        let other = if from == worker_id {
+            to
+        } else if to == worker_id && !edge.one_way {
+            from
+        } else if to == worker_id {
+            // A one-way edge doesn't carry pearls back
+            nbrs.retain(|&n| n != from);
+            continue;
+        } else {
+            continue;
+        };
+
+        nbrs.retain(|&n| n != other);
+        if edge.capacity != Some(0) {
+            nbrs.push(other);
+        }
+    }
+
+    nbrs.sort();
+    nbrs.dedup();
+    return nbrs;
+}
+
+/// Renders a state as plain text, one line per worker in ID order, listing
+/// each worker's flavor, who they can pass to and the pearls on their desk.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to render
+/// * `gatekeeper_id` - The ID of the gatekeeper, which is marked in the output
+///
+/// # Returns
+///
+/// * `String` - The rendered state
+pub fn render_state_text(state: &State, gatekeeper_id: u32) -> String {
+    let mut workers: Vec<_> = state.workers.iter().collect();
+    workers.sort_by_key(|w| w.id);
+
+    let mut out = format!("score {}\n", state.score);
+
+    for w in workers {
+        let nbrs = outgoing_neighbors(state, w.id);
+        let marker = if w.id == gatekeeper_id { "*" } else { " " };
+        let desk: Vec<String> = w.desk.iter().map(describe_pearl).collect();
+
+        out += &format!(
+            "{}{:>4} {:<8} -> {:?}: {}\n",
+            marker,
+            w.id,
+            w.flavor,
+            nbrs,
+            desk.join("; ")
+        );
+    }
+
+    return out;
+}
+
+/// Renders a state as a Graphviz `dot` graph, with one node per worker
+/// labelled with its flavor and how many pearls are on its desk. Edges with
+/// attributes are labelled with them, one-way edges have an arrow, and
+/// closed edges are dashed.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to render
+/// * `gatekeeper_id` - The ID of the gatekeeper, which is drawn as a box
+///
+/// # Returns
+///
+/// * `String` - The rendered graph
+pub fn render_state_dot(state: &State, gatekeeper_id: u32) -> String {
+    let mut out = String::from("graph atlantis {\n");
+
+    for w in &state.workers {
+        let shape = if w.id == gatekeeper_id {
+            "box"
+        } else {
+            "ellipse"
+        };
+        out += &format!(
+            "    {} [shape={}, label=\"{}\\n{}\\n{} pearls\"];\n",
+            w.id,
+            shape,
+            w.id,
+            w.flavor,
+            w.desk.len()
+        );
+    }
+
+    for pair in &state.neighbor_map {
+        let described = state.edges.iter().any(|e| {
+            pair.len() == 2 && (e.workers == [pair[0], pair[1]] || e.workers == [pair[1], pair[0]])
+        });
+        if pair.len() == 2 && !described {
+            out += &format!("    {} -- {};\n", pair[0], pair[1]);
+        }
+    }
+
+    for edge in &state.edges {
+        let mut attributes: Vec<String> = Vec::new();
+        let mut label: Vec<String> = Vec::new();
+
+        if let Some(c) = edge.cost {
+            label.push(format!("cost {}", c));
+        }
+        if let Some(c) = edge.capacity {
+            label.push(format!("capacity {}", c));
+        }
+        if !label.is_empty() {
+            attributes.push(format!("label=\"{}\"", label.join(", ")));
+        }
+        if edge.one_way {
+            attributes.push(String::from("dir=forward"));
+        }
+        if edge.capacity == Some(0) {
+            attributes.push(String::from("style=dashed"));
+        }
+
+        let [from, to] = edge.workers;
+        if attributes.is_empty() {
+            out += &format!("    {} -- {};\n", from, to);
+        } else {
+            out += &format!("    {} -- {} [{}];\n", from, to, attributes.join(", "));
+        }
+    }
+
+    out += "}\n";
+    return out;
+}
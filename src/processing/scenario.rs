@@ -0,0 +1,215 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+use crate::models;
+use models::ability_map::COLORS;
+use models::config::ScenarioConfig;
+use models::state::{Layer, Pearl, State};
+
+/// Checks that pearls can be generated from a scenario config.
+///
+/// # Arguments
+///
+/// * `scenario` - A reference to the scenario config to check
+///
+/// # Returns
+///
+/// * `Vec<String>` - A description of every problem found, empty if the
+///                   config is usable
+pub fn check_scenario(scenario: &ScenarioConfig) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+
+    if scenario.layers[0] == 0 || scenario.layers[0] > scenario.layers[1] {
+        problems.push(format!(
+            "scenario layers {:?} must be a range starting at 1 or more",
+            scenario.layers
+        ));
+    }
+    if scenario.thickness[0] == 0 || scenario.thickness[0] > scenario.thickness[1] {
+        problems.push(format!(
+            "scenario thickness {:?} must be a range starting at 1 or more",
+            scenario.thickness
+        ));
+    }
+    if scenario.samples == 0 {
+        problems.push(String::from("scenario samples must be at least 1"));
+    }
+
+    for (color, weight) in &scenario.colors {
+        if !COLORS.contains(&color.as_str()) {
+            problems.push(format!("scenario has unknown color {}", color));
+        }
+        if !weight.is_finite() || *weight < 0.0 {
+            problems.push(format!(
+                "scenario weight for {} must not be negative",
+                color
+            ));
+        }
+    }
+    if !scenario.colors.is_empty() && scenario.colors.values().all(|w| *w <= 0.0) {
+        problems.push(String::from("scenario colors need a weight above 0"));
+    }
+
+    return problems;
+}
+
+/// Reads a color distribution written as `Color=weight` pairs separated by
+/// commas, e.g. `Red=2,Green=1`. Colors left out never appear.
+///
+/// # Arguments
+///
+/// * `spec` - The distribution as written on the command line
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, f64>, String>` - The weight of each color, or
+///                                            a message describing what was
+///                                            wrong with `spec`
+pub fn parse_color_weights(spec: &str) -> Result<HashMap<String, f64>, String> {
+    let mut weights: HashMap<String, f64> = HashMap::new();
+
+    for part in spec.split(',').filter(|p| !p.trim().is_empty()) {
+        let (color, weight) = match part.split_once('=') {
+            Some((c, w)) => (c.trim(), w.trim()),
+            None => return Err(format!("{} should look like Color=weight", part)),
+        };
+
+        match weight.parse::<f64>() {
+            Ok(w) => weights.insert(color.to_string(), w),
+            Err(_) => return Err(format!("{} is not a valid weight for {}", weight, color)),
+        };
+    }
+
+    return Ok(weights);
+}
+
+/// Generates a single pearl.
+///
+/// # Arguments
+///
+/// * `scenario` - A reference to a scenario config that passes
+///                `check_scenario`
+/// * `id` - The ID to give the pearl
+/// * `rng` - The random number generator to draw from
+///
+/// # Returns
+///
+/// * `Pearl` - The generated pearl
+pub fn generate_pearl(scenario: &ScenarioConfig, id: u32, rng: &mut StdRng) -> Pearl {
+    // Colors are listed in a fixed order, so a seed always gives the same
+    // pearls however the weights were read in
+    let colors: Vec<&str> = COLORS
+        .iter()
+        .filter(|c| scenario.colors.is_empty() || scenario.colors.contains_key(**c))
+        .copied()
+        .collect();
+    let weights: Vec<f64> = colors
+        .iter()
+        .map(|c| scenario.colors.get(*c).copied().unwrap_or(1.0))
+        .collect();
+    let pick = match WeightedIndex::new(&weights) {
+        Ok(w) => w,
+        Err(e) => panic!("Scenario Error: bad color weights: {}", e),
+    };
+
+    let count = rng.gen_range(scenario.layers[0]..=scenario.layers[1]);
+    let layers: Vec<Layer> = (0..count)
+        .map(|_| Layer {
+            color: colors[pick.sample(rng)].to_string(),
+            thickness: rng.gen_range(scenario.thickness[0]..=scenario.thickness[1]),
+            original_thickness: None,
+        })
+        .collect();
+
+    return Pearl {
+        id: id,
+        layers: layers,
+    };
+}
+
+/// Generates the pearls for one game, numbered from 1.
+///
+/// # Arguments
+///
+/// * `scenario` - A reference to a scenario config that passes
+///                `check_scenario`
+/// * `rng` - The random number generator to draw from
+///
+/// # Returns
+///
+/// * `Vec<Pearl>` - The generated pearls
+pub fn generate_pearls(scenario: &ScenarioConfig, rng: &mut StdRng) -> Vec<Pearl> {
+    return (1..=scenario.pearls)
+        .map(|id| generate_pearl(scenario, id, rng))
+        .collect();
+}
+
+/// Draws how many pearls arrive in a turn, from a Poisson distribution.
+///
+/// # Arguments
+///
+/// * `rate` - How many pearls arrive in a turn on average
+/// * `rng` - The random number generator to draw from
+///
+/// # Returns
+///
+/// * `u32` - How many pearls arrive
+pub fn sample_arrivals(rate: f64, rng: &mut StdRng) -> u32 {
+    if !rate.is_finite() || rate <= 0.0 {
+        return 0;
+    }
+
+    // Multiplies uniform draws together until they fall below e^-rate
+    let limit = (-rate).exp();
+    let mut product: f64 = rng.gen();
+    let mut count: u32 = 0;
+    while product > limit {
+        product *= rng.gen::<f64>();
+        count += 1;
+    }
+
+    return count;
+}
+
+/// Generates the games described by a scenario config on a floor. The
+/// floor keeps its workers and edges, but every desk is cleared and the
+/// generated pearls all start on the gatekeeper's desk. The same config
+/// always generates the same games.
+///
+/// # Arguments
+///
+/// * `floor` - A reference to a state with the floor to play on
+/// * `scenario` - A reference to a scenario config that passes
+///                `check_scenario`
+/// * `gatekeeper_id` - The ID of the gatekeeper
+///
+/// # Returns
+///
+/// * `Vec<State>` - The starting state of each game
+pub fn generate_scenarios(
+    floor: &State,
+    scenario: &ScenarioConfig,
+    gatekeeper_id: u32,
+) -> Vec<State> {
+    let mut rng = StdRng::seed_from_u64(scenario.seed);
+    let mut games: Vec<State> = Vec::new();
+
+    for _ in 0..scenario.samples {
+        let mut game = floor.clone();
+        game.score = 0;
+
+        let pearls = generate_pearls(scenario, &mut rng);
+        for w in game.workers.iter_mut() {
+            w.desk.clear();
+            if w.id == gatekeeper_id {
+                w.desk = pearls.clone();
+            }
+        }
+
+        games.push(game);
+    }
+
+    return games;
+}
@@ -0,0 +1,234 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::gatekeeper::get_distances_home;
+use super::utils::get_time_to_process;
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::action::ActionType;
+use models::state::{NeighborGraph, Pearl, State, Worker};
+
+/// What the score model expects to happen over the coming turns if a set
+/// of actions is carried out.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScoreProjection {
+    /// The score reported in the state the plan was made from
+    pub current_score: u32,
+    /// Finished pearls expected to reach the gatekeeper this turn
+    pub delivered: u32,
+    /// The score expected in the next state
+    pub projected_score: u32,
+    /// Estimated finished pearls delivered per turn over the horizon
+    pub pearls_per_turn: f64,
+}
+
+/// Checks whether a single nom will dissolve the last layer of a pearl.
+///
+/// # Arguments
+///
+/// * `pearl` - A reference to the pearl being nommed
+/// * `worker` - A reference to the worker nomming it
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+///
+/// # Returns
+///
+/// * `bool` - True if the pearl will be finished after this turn
+pub fn finishes_this_turn(pearl: &Pearl, worker: &Worker, ability_map: &AbilityMap) -> bool {
+    if pearl.layers.len() != 1 {
+        return false;
+    }
+
+    let layer = &pearl.layers[0];
+    let rate = ability_map.rate(&worker.flavor, &layer.color).unwrap_or(0);
+    return layer.thickness <= rate;
+}
+
+/// Estimates how many turns it will take before a pearl is back at the
+/// gatekeeper, assuming the worker holding it processes it in full and
+/// then passes it straight home.
+///
+/// # Arguments
+///
+/// * `pearl` - A reference to the pearl in question
+/// * `holder` - A reference to the worker that will be processing it
+/// * `gatekeeper_id` - The ID of the gatekeeper
+/// * `distances_home` - How many turns it takes to get a pearl home from
+///                      every worker that can reach the gatekeeper
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+///
+/// # Returns
+///
+/// * `Option<u32>` - The estimated number of turns, or None if the holder
+///                   can't reach the gatekeeper at all
+pub fn estimate_turns_to_delivery(
+    pearl: &Pearl,
+    holder: &Worker,
+    gatekeeper_id: u32,
+    distances_home: &HashMap<u32, u32>,
+    ability_map: &AbilityMap,
+) -> Option<u32> {
+    let distance = if holder.id == gatekeeper_id {
+        0
+    } else {
+        *distances_home.get(&holder.id)?
+    };
+
+    return Some(get_time_to_process(pearl, holder, ability_map).saturating_add(distance));
+}
+
+/// Projects the score after a set of actions. A point is counted for every
+/// finished pearl passed to the gatekeeper and for every pearl the
+/// gatekeeper finishes nomming, which is how `score` increases in practice.
+///
+/// Beyond the next turn, every pearl on the floor is given an estimated
+/// delivery time based on where it will be after the actions, and the
+/// number delivered within `horizon` turns gives a throughput estimate.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state the actions were planned from
+/// * `actions` - The actions planned for this turn
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `gatekeeper_id` - The ID of the gatekeeper
+/// * `horizon` - How many turns ahead to estimate throughput over
+///
+/// # Returns
+///
+/// * `ScoreProjection` - The projected evolution of the score
+pub fn project_score(
+    state: &State,
+    actions: &HashMap<u32, ActionType>,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    gatekeeper_id: u32,
+    horizon: u32,
+) -> ScoreProjection {
+    let etas = estimate_delivery_times(state, actions, ability_map, neighbor_graph, gatekeeper_id);
+
+    let delivered = etas.iter().filter(|&&t| t == 0).count() as u32;
+    let within_horizon = etas.iter().filter(|&&t| t <= horizon).count() as u32;
+
+    let pearls_per_turn = if horizon == 0 {
+        delivered as f64
+    } else {
+        within_horizon as f64 / horizon as f64
+    };
+
+    return ScoreProjection {
+        current_score: state.score,
+        delivered: delivered,
+        projected_score: state.score.saturating_add(delivered),
+        pearls_per_turn: pearls_per_turn,
+    };
+}
+
+/// Scores a plan so that strategies can compare candidate action sets. Each
+/// pearl delivered this turn is worth one point, and every other pearl is
+/// worth `1 / (1 + eta)`, so plans that bring pearls closer to delivery
+/// score higher. Larger is better. `MctsStrategy` uses it to value the
+/// pearls a playout leaves undelivered.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state the actions were planned from
+/// * `actions` - The candidate actions for this turn
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `gatekeeper_id` - The ID of the gatekeeper
+///
+/// # Returns
+///
+/// * `f64` - The value of the plan
+pub fn plan_objective(
+    state: &State,
+    actions: &HashMap<u32, ActionType>,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    gatekeeper_id: u32,
+) -> f64 {
+    let etas = estimate_delivery_times(state, actions, ability_map, neighbor_graph, gatekeeper_id);
+    let mut value = 0.0;
+
+    for t in etas {
+        value += 1.0 / (1.0 + t as f64);
+    }
+
+    return value;
+}
+
+/// Works out, for every pearl on the floor, how many turns after this one
+/// it is expected to reach the gatekeeper. A value of 0 means the pearl is
+/// delivered by this turn's actions. Pearls that can't reach the gatekeeper
+/// are left out.
+fn estimate_delivery_times(
+    state: &State,
+    actions: &HashMap<u32, ActionType>,
+    ability_map: &AbilityMap,
+    neighbor_graph: &NeighborGraph,
+    gatekeeper_id: u32,
+) -> Vec<u32> {
+    let distances_home = get_distances_home(gatekeeper_id, neighbor_graph);
+    let mut workers: HashMap<u32, &Worker> = HashMap::new();
+    for w in &state.workers {
+        workers.insert(w.id, w);
+    }
+
+    let mut etas: Vec<u32> = Vec::new();
+
+    for w in &state.workers {
+        for p in &w.desk {
+            // Finished pearls sitting with the gatekeeper have already been
+            // counted in the score
+            if w.id == gatekeeper_id && p.layers.is_empty() {
+                continue;
+            }
+
+            let eta = match actions.get(&w.id) {
+                Some(ActionType::Pass(a)) if a.pearl_id == p.id => {
+                    if p.layers.is_empty() && a.to_id == gatekeeper_id {
+                        Some(0)
+                    } else {
+                        match workers.get(&a.to_id) {
+                            Some(to) => estimate_turns_to_delivery(
+                                p,
+                                to,
+                                gatekeeper_id,
+                                &distances_home,
+                                ability_map,
+                            ),
+                            None => None,
+                        }
+                    }
+                }
+                Some(ActionType::Nom(a)) if a.pearl_id == p.id => {
+                    if w.id == gatekeeper_id && finishes_this_turn(p, w, ability_map) {
+                        Some(0)
+                    } else {
+                        let t = estimate_turns_to_delivery(
+                            p,
+                            w,
+                            gatekeeper_id,
+                            &distances_home,
+                            ability_map,
+                        );
+                        t.map(|t| if t > 0 { t - 1 } else { 0 })
+                    }
+                }
+                // Pearls that aren't touched this turn lose a turn of progress
+                _ => estimate_turns_to_delivery(p, w, gatekeeper_id, &distances_home, ability_map)
+                    .map(|t| t.saturating_add(1)),
+            };
+
+            if let Some(t) = eta {
+                etas.push(t);
+            }
+        }
+    }
+
+    return etas;
+}
@@ -0,0 +1,368 @@
+use log::{info, warn};
+use serde_json::json;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::config::load_ability_map;
+use super::metrics::{write_metrics, Metrics};
+use super::pearl_pipeline::{process_line, Pipeline};
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::config::Config;
+
+/// Where the server listens for connections.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddr {
+    /// A TCP address such as `127.0.0.1:7000`
+    Tcp(String),
+    /// The path of a Unix domain socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Reads a listen address of the form `tcp:<host>:<port>` or
+/// `unix:<path>`. A bare `<host>:<port>` is taken to be TCP.
+///
+/// # Arguments
+///
+/// * `addr` - The address, as given on the command line
+///
+/// # Returns
+///
+/// * `Result<ListenAddr, String>` - The address, or a message saying why it
+///                                  couldn't be understood
+pub fn parse_listen_addr(addr: &str) -> Result<ListenAddr, String> {
+    if let Some(path) = addr.strip_prefix("unix:") {
+        #[cfg(unix)]
+        return Ok(ListenAddr::Unix(PathBuf::from(path)));
+
+        #[cfg(not(unix))]
+        return Err(format!("Unix sockets are not supported here: {}", path));
+    }
+
+    let tcp = addr.strip_prefix("tcp:").unwrap_or(addr);
+    if !tcp.contains(':') {
+        return Err(format!(
+            "{} is not of the form tcp:<host>:<port> or unix:<path>",
+            addr
+        ));
+    }
+
+    return Ok(ListenAddr::Tcp(tcp.to_string()));
+}
+
+/// Tunable behaviour for the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerOptions {
+    /// Close a session after it has sent nothing for this long
+    pub idle_timeout: Option<Duration>,
+    /// How often idle sessions and the accept loop check whether the server
+    /// is shutting down
+    pub poll_interval: Duration,
+}
+
+impl Default for ServerOptions {
+    fn default() -> ServerOptions {
+        return ServerOptions {
+            idle_timeout: Some(Duration::from_secs(300)),
+            poll_interval: Duration::from_millis(100),
+        };
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// Serves many games from one process. Every connection is an independent
+/// game with its own `Pipeline`, speaking the same protocol as `stdin` and
+/// `stdout`: one JSON state per line in, one line of actions out.
+pub struct Server {
+    listener: Listener,
+    config: Config,
+    ability_map: AbilityMap,
+    options: ServerOptions,
+    shutdown: Arc<AtomicBool>,
+    /// The metrics of every session that has closed
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+impl Server {
+    /// Starts listening on an address. Nothing is accepted until `run` is
+    /// called.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Where to listen
+    /// * `config` - The config every session decides with
+    /// * `options` - Tunable behaviour for the server
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Server, String>` - The server, or an error if the address
+    ///                              couldn't be bound or the ability map
+    ///                              couldn't be read
+    pub fn bind(
+        addr: &ListenAddr,
+        config: Config,
+        options: ServerOptions,
+    ) -> Result<Server, String> {
+        let ability_map = load_ability_map(&config)?;
+
+        let listener = match addr {
+            ListenAddr::Tcp(a) => match TcpListener::bind(a) {
+                Ok(l) => Listener::Tcp(l),
+                Err(e) => return Err(format!("Could not listen on {}: {}", a, e)),
+            },
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => match UnixListener::bind(path) {
+                Ok(l) => Listener::Unix(l, path.clone()),
+                Err(e) => return Err(format!("Could not listen on {}: {}", path.display(), e)),
+            },
+        };
+
+        let nonblocking = match &listener {
+            Listener::Tcp(l) => l.set_nonblocking(true),
+            #[cfg(unix)]
+            Listener::Unix(l, _) => l.set_nonblocking(true),
+        };
+        if let Err(e) = nonblocking {
+            return Err(format!("Could not configure listener: {}", e));
+        }
+
+        return Ok(Server {
+            listener: listener,
+            config: config,
+            ability_map: ability_map,
+            options: options,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            metrics: Arc::new(Mutex::new(Metrics::new())),
+        });
+    }
+
+    /// The address the server is listening on, which is useful when binding
+    /// to TCP port 0.
+    pub fn local_addr(&self) -> String {
+        return match &self.listener {
+            Listener::Tcp(l) => match l.local_addr() {
+                Ok(a) => a.to_string(),
+                Err(_) => String::new(),
+            },
+            #[cfg(unix)]
+            Listener::Unix(_, path) => path.display().to_string(),
+        };
+    }
+
+    /// A flag that shuts the server down gracefully once set. No new
+    /// connections are accepted, and every open session is closed after
+    /// the line it is working on.
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        return self.shutdown.clone();
+    }
+
+    /// The combined metrics of every session that has closed so far
+    pub fn metrics(&self) -> Metrics {
+        return match self.metrics.lock() {
+            Ok(m) => m.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+    }
+
+    /// Accepts connections until the shutdown flag is set, running each one
+    /// as a separate game on its own thread, then waits for every session
+    /// to close and writes out their combined metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * None
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - An error if the listener stopped working, or
+    ///                          the metrics couldn't be written
+    pub fn run(&self) -> Result<(), String> {
+        let mut sessions: Vec<JoinHandle<()>> = Vec::new();
+        let mut next_id: u64 = 0;
+
+        while !self.shutdown.load(Ordering::SeqCst) {
+            let accepted = match &self.listener {
+                Listener::Tcp(l) => l.accept().map(|(s, _)| self.spawn_tcp(s, next_id)),
+                #[cfg(unix)]
+                Listener::Unix(l, _) => l.accept().map(|(s, _)| self.spawn_unix(s, next_id)),
+            };
+
+            match accepted {
+                Ok(Ok(handle)) => {
+                    sessions.push(handle);
+                    next_id += 1;
+                }
+                Ok(Err(e)) => warn!(session = next_id, error:% = e; "could not start session"),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(self.options.poll_interval);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Could not accept connection: {}", e)),
+            }
+
+            sessions.retain(|s| !s.is_finished());
+        }
+
+        for s in sessions {
+            // A session that panicked has already been reported, and can't
+            // affect any of the others
+            let _ = s.join();
+        }
+
+        return write_metrics(&self.metrics(), &self.config.metrics);
+    }
+
+    fn spawn_tcp(&self, stream: TcpStream, id: u64) -> std::io::Result<JoinHandle<()>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(self.options.poll_interval))?;
+        let writer = stream.try_clone()?;
+        return Ok(self.spawn_session(stream, writer, id));
+    }
+
+    #[cfg(unix)]
+    fn spawn_unix(&self, stream: UnixStream, id: u64) -> std::io::Result<JoinHandle<()>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(self.options.poll_interval))?;
+        let writer = stream.try_clone()?;
+        return Ok(self.spawn_session(stream, writer, id));
+    }
+
+    fn spawn_session<R, W>(&self, reader: R, writer: W, id: u64) -> JoinHandle<()>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let mut pipeline =
+            Pipeline::with_ability_map(self.config.clone(), self.ability_map.clone());
+        let shutdown = self.shutdown.clone();
+        let metrics = self.metrics.clone();
+        let idle_timeout = self.options.idle_timeout;
+
+        return thread::spawn(move || {
+            info!(session = id; "session opened");
+            match run_session(reader, writer, &mut pipeline, &shutdown, idle_timeout) {
+                Ok(()) => info!(session = id, turns = pipeline.turn(); "session closed"),
+                Err(e) => {
+                    warn!(session = id, turns = pipeline.turn(), reason = e.as_str(); "session closed")
+                }
+            }
+
+            if let Ok(mut m) = metrics.lock() {
+                m.merge(pipeline.metrics());
+            }
+        });
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = &self.listener {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Plays one game over a connection, answering every line of input with a
+/// line of actions. A line that isn't a valid state is answered with
+/// `{"error": "..."}` instead, and the game carries on. If deciding a turn
+/// panics, the client is sent an error line and the session ends, so that
+/// its metrics can still be collected. The reader should
+/// have a read timeout set, so that the session can notice when it has been
+/// idle too long or the server is shutting down.
+///
+/// # Arguments
+///
+/// * `reader` - Where the states come from
+/// * `writer` - Where the actions go
+/// * `pipeline` - The pipeline for this game
+/// * `shutdown` - Set when the server is shutting down
+/// * `idle_timeout` - How long to wait for a line before giving up
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error if the connection failed, went idle or
+///                          panicked
+pub fn run_session<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    pipeline: &mut Pipeline,
+    shutdown: &AtomicBool,
+    idle_timeout: Option<Duration>,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(reader);
+    let mut buf: Vec<u8> = Vec::new();
+    let mut last_line = Instant::now();
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let eof = match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => true,
+            Ok(_) => !buf.ends_with(b"\n"),
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                // Anything read before the timeout stays in buf, and the
+                // rest of the line is appended on the next pass
+                if let Some(limit) = idle_timeout {
+                    if last_line.elapsed() >= limit {
+                        return Err(String::from("idle timeout"));
+                    }
+                }
+                continue;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("read error: {}", e)),
+        };
+
+        let line = String::from_utf8_lossy(&buf).to_string();
+        buf.clear();
+        last_line = Instant::now();
+
+        if !line.trim().is_empty() {
+            // A bad line gets an error line back, and the game carries on.
+            // A panic deciding the turn leaves the pipeline in an unknown
+            // state, so the client is told and the session ends.
+            let decided = panic::catch_unwind(AssertUnwindSafe(|| process_line(pipeline, &line)));
+            let (response, panicked) = match decided {
+                Ok(Ok(r)) => (r, false),
+                Ok(Err(e)) => {
+                    warn!(turn = pipeline.turn(), error = e.as_str(); "rejected line");
+                    (json!({ "error": e }).to_string(), false)
+                }
+                Err(_) => {
+                    let error = "Internal error deciding the turn";
+                    (json!({ "error": error }).to_string(), true)
+                }
+            };
+            let written = writer
+                .write_all(response.as_bytes())
+                .and_then(|_| writer.write_all(b"\n"))
+                .and_then(|_| writer.flush());
+
+            if let Err(e) = written {
+                return Err(format!("write error: {}", e));
+            }
+            if panicked {
+                return Err(String::from("panicked deciding a turn"));
+            }
+        }
+
+        if eof {
+            break;
+        }
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use super::transfer::TransferCosts;
+use super::utils::{build_state_graph, determine_actions};
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::action::{ActionType, Pass};
+use models::config::Config;
+use models::state::{Pearl, PearlPaths, State};
+
+/// A pearl that has been passed but hasn't reached the receiving worker's
+/// desk yet.
+#[derive(Debug, Clone)]
+pub struct InTransit {
+    pub from_id: u32,
+    pub to_id: u32,
+    pub pearl: Pearl,
+    /// How many more turns before it lands
+    pub turns_left: u32,
+}
+
+/// One turn of a simulated game.
+#[derive(Debug)]
+pub struct SimulatedTurn {
+    /// The state the actions were decided from
+    pub state: State,
+    pub actions: HashMap<u32, ActionType>,
+}
+
+/// Works out the state that follows from carrying out a set of actions,
+/// following the same rules as the real pipeline:
+///
+/// * A nom dissolves up to the worker's rate from the pearl's outermost
+///   layer, and nothing carries over into the next layer. The layer's
+///   original thickness is recorded the first time it is nommed.
+/// * A passed pearl lands on the receiving worker's desk
+/// * Finished pearls on the gatekeeper's desk are delivered, adding one to
+///   the score each
+///
+/// Actions that refer to workers or pearls that don't exist are ignored.
+/// Every pass is free, see `apply_turn` for passes that take time.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state the actions were decided from
+/// * `actions` - The actions each worker takes this turn
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `gatekeeper_id` - The ID of the gatekeeper
+///
+/// # Returns
+///
+/// * `State` - The state at the start of the next turn
+pub fn apply_actions(
+    state: &State,
+    actions: &HashMap<u32, ActionType>,
+    ability_map: &AbilityMap,
+    gatekeeper_id: u32,
+) -> State {
+    return apply_turn(
+        state,
+        actions,
+        ability_map,
+        gatekeeper_id,
+        &TransferCosts::default(),
+        &mut Vec::new(),
+        &mut HashMap::new(),
+    );
+}
+
+/// Takes back what deciding a pass did to a pearl's path, for a pass that
+/// was held back: an unfinished pearl forgets the step to the worker still
+/// holding it, and a finished one remembers the stop it didn't reach.
+pub fn hold_back_pass(pearl_paths: &mut PearlPaths, pass: &Pass, finished: bool) {
+    if !finished {
+        if let Some(path) = pearl_paths.get_mut(&pass.pearl_id) {
+            if path.last() == Some(&pass.from_id) {
+                path.pop();
+            }
+        }
+    } else {
+        pearl_paths
+            .entry(pass.pearl_id)
+            .or_default()
+            .push(pass.to_id);
+    }
+}
+
+/// Works out the state that follows from carrying out a set of actions,
+/// as `apply_actions` does, except that a pass leaves the pearl in transit
+/// for as many turns as `transfer_costs` says. Pearls in transit are on
+/// nobody's desk, so nobody can work on them, and they land on the
+/// receiving worker's desk once their time is up. A pass over an edge that
+/// already has as many pearls in transit as its capacity doesn't happen,
+/// and the pearl stays where it is. Its path in `pearl_paths` is put back
+/// the way it was before the pass was decided, so that later turns don't
+/// treat it as having moved.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state the actions were decided from
+/// * `actions` - The actions each worker takes this turn
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `gatekeeper_id` - The ID of the gatekeeper
+/// * `transfer_costs` - How long a pass over each edge takes
+/// * `in_transit` - The pearls in transit, which is updated with this
+///                  turn's passes and landings
+/// * `pearl_paths` - How each pearl has been passed around, as updated when
+///                   the actions were decided
+///
+/// # Returns
+///
+/// * `State` - The state at the start of the next turn
+pub fn apply_turn(
+    state: &State,
+    actions: &HashMap<u32, ActionType>,
+    ability_map: &AbilityMap,
+    gatekeeper_id: u32,
+    transfer_costs: &TransferCosts,
+    in_transit: &mut Vec<InTransit>,
+    pearl_paths: &mut PearlPaths,
+) -> State {
+    let mut next = state.clone();
+    let mut moved = Vec::new();
+
+    // Pearls already on their way get a turn closer
+    for t in in_transit.iter_mut() {
+        t.turns_left -= 1;
+    }
+
+    for w in next.workers.iter_mut() {
+        match actions.get(&w.id) {
+            Some(ActionType::Nom(n)) => {
+                let flavor = w.flavor.clone();
+                if let Some(p) = w.desk.iter_mut().find(|p| p.id == n.pearl_id) {
+                    if !p.layers.is_empty() {
+                        let layer = &mut p.layers[0];
+                        // A flavor or color the map doesn't know makes no progress
+                        let rate = ability_map.rate(&flavor, &layer.color).unwrap_or(0);
+                        layer.original_thickness = Some(layer.original());
+                        layer.thickness = layer.thickness.saturating_sub(rate);
+                        if layer.thickness == 0 {
+                            p.layers.remove(0);
+                        }
+                    }
+                }
+            }
+            Some(ActionType::Pass(a)) => {
+                let cost = transfer_costs.cost(w.id, a.to_id);
+                let on_edge = in_transit
+                    .iter()
+                    .filter(|t| t.from_id == w.id && t.to_id == a.to_id && t.turns_left > 0)
+                    .count() as u32;
+                let full = cost > 0
+                    && transfer_costs
+                        .capacity(w.id, a.to_id)
+                        .is_some_and(|c| on_edge >= c);
+
+                if full {
+                    let finished = w
+                        .desk
+                        .iter()
+                        .any(|p| p.id == a.pearl_id && p.layers.is_empty());
+                    hold_back_pass(pearl_paths, a, finished);
+                    continue;
+                }
+
+                if let Some(i) = w.desk.iter().position(|p| p.id == a.pearl_id) {
+                    in_transit.push(InTransit {
+                        from_id: w.id,
+                        to_id: a.to_id,
+                        pearl: w.desk.remove(i),
+                        turns_left: cost,
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Pearls whose time is up land, in the order they were passed
+    let mut still_moving = Vec::new();
+    for t in in_transit.drain(..) {
+        if t.turns_left == 0 {
+            moved.push(t);
+        } else {
+            still_moving.push(t);
+        }
+    }
+    *in_transit = still_moving;
+
+    for t in moved {
+        if let Some(w) = next.workers.iter_mut().find(|w| w.id == t.to_id) {
+            w.desk.push(t.pearl);
+        }
+    }
+
+    if let Some(gk) = next.workers.iter_mut().find(|w| w.id == gatekeeper_id) {
+        let before = gk.desk.len();
+        gk.desk.retain(|p| !p.layers.is_empty());
+        next.score += (before - gk.desk.len()) as u32;
+    }
+
+    return next;
+}
+
+/// Plays a game forward from a starting state using the greedy strategy,
+/// until every pearl has been delivered or `max_turns` is reached. Passes
+/// take as long as the state's edges or the `transfer` section of the
+/// config say, and respect the capacity of their edges.
+///
+/// # Arguments
+///
+/// * `initial` - A reference to the state to start from
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config to make decisions with
+/// * `max_turns` - The most turns to simulate
+///
+/// # Returns
+///
+/// * `(Vec<SimulatedTurn>, State)` - Every turn that was played, and the
+///                                   state the game ended in
+pub fn simulate(
+    initial: &State,
+    ability_map: &AbilityMap,
+    config: &Config,
+    max_turns: u32,
+) -> (Vec<SimulatedTurn>, State) {
+    let neighbor_graph = build_state_graph(initial, &config.transfer);
+    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+    let mut turns: Vec<SimulatedTurn> = Vec::new();
+    let mut state = initial.clone();
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(&neighbor_graph);
+    let mut in_transit: Vec<InTransit> = Vec::new();
+
+    while (turns.len() as u32) < max_turns
+        && (!in_transit.is_empty() || state.workers.iter().any(|w| !w.desk.is_empty()))
+    {
+        let actions = determine_actions(
+            &state,
+            ability_map,
+            &neighbor_graph,
+            &mut pearl_paths,
+            config,
+        );
+        let next = apply_turn(
+            &state,
+            &actions,
+            ability_map,
+            config.gatekeeper_id,
+            &transfer_costs,
+            &mut in_transit,
+            &mut pearl_paths,
+        );
+
+        turns.push(SimulatedTurn {
+            state: state,
+            actions: actions,
+        });
+        state = next;
+    }
+
+    return (turns, state);
+}
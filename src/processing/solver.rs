@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{self, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use super::bound::estimate_lower_bound_with_graph;
+use super::explain::action_to_value;
+use super::simulator::{apply_turn, simulate, InTransit};
+use super::transfer::TransferCosts;
+use super::utils::{build_state_graph, make_nom, make_pass};
+use crate::models;
+use models::ability_map::{AbilityMap, COLORS};
+use models::action::{ActionType, Actions};
+use models::config::Config;
+use models::state::{NeighborGraph, State};
+
+/// How many states the solver searches before giving up, unless told
+/// otherwise.
+pub const DEFAULT_MAX_STATES: u64 = 1_000_000;
+
+/// The quickest way to deliver every pearl in a game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub turns: u32,
+    /// The actions for each turn, in order
+    pub actions: Vec<Actions>,
+    /// How many states were searched to prove it
+    pub states_searched: u64,
+}
+
+/// How greedy did on a game against the optimal solution.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SolvedGame {
+    pub optimal_turns: u32,
+    /// How many turns greedy played
+    pub greedy_turns: u32,
+    /// Whether greedy delivered every pearl within the turn limit
+    pub greedy_finished: bool,
+    pub states_searched: u64,
+    /// The optimal actions for each turn, in the same format as `stdout`
+    pub actions: Vec<Value>,
+}
+
+impl SolvedGame {
+    /// Whether greedy took longer than it had to
+    pub fn greedy_is_suboptimal(&self) -> bool {
+        return !self.greedy_finished || self.greedy_turns > self.optimal_turns;
+    }
+}
+
+/// A game where greedy is known to be beaten, kept as a regression test.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SolverFixture {
+    pub state: State,
+    pub optimal_turns: u32,
+    pub greedy_turns: u32,
+    pub actions: Vec<Value>,
+}
+
+/// A position in the search: the floor, and the pearls still being passed.
+#[derive(Debug, Clone)]
+struct Node {
+    state: State,
+    in_transit: Vec<InTransit>,
+}
+
+/// What searching below a node found.
+enum Outcome {
+    /// A way to deliver every pearl within the limit, left in the path
+    Found,
+    /// The smallest estimate that went over the limit
+    Exceeded(u32),
+    /// The search ran out of states
+    GaveUp,
+}
+
+/// The floor being searched, and everything learned about it so far.
+struct Search<'a> {
+    neighbor_graph: NeighborGraph,
+    transfer_costs: TransferCosts,
+    ability_map: &'a AbilityMap,
+    gatekeeper_id: u32,
+    max_states: u64,
+    states: u64,
+    /// The fewest turns each position has been reached in during this pass
+    seen: HashMap<Vec<u32>, u32>,
+    path: Vec<Actions>,
+}
+
+impl Node {
+    fn is_done(&self) -> bool {
+        return self.in_transit.is_empty() && self.state.workers.iter().all(|w| w.desk.is_empty());
+    }
+
+    /// Identifies the position regardless of the order pearls are listed
+    /// in. The score isn't included, since it follows from the pearls left.
+    fn key(&self) -> Vec<u32> {
+        let mut workers: Vec<_> = self.state.workers.iter().collect();
+        workers.sort_by_key(|w| w.id);
+
+        let mut key: Vec<u32> = Vec::new();
+        for w in workers {
+            let mut desk: Vec<_> = w.desk.iter().collect();
+            desk.sort_by_key(|p| p.id);
+
+            key.push(w.id);
+            key.push(desk.len() as u32);
+            for p in desk {
+                key.push(p.id);
+                key.push(p.layers.len() as u32);
+                for l in &p.layers {
+                    let color = COLORS.iter().position(|c| *c == l.color);
+                    key.push(color.unwrap_or(COLORS.len()) as u32);
+                    key.push(l.thickness);
+                }
+            }
+        }
+
+        let mut moving: Vec<[u32; 4]> = self
+            .in_transit
+            .iter()
+            .map(|t| [t.pearl.id, t.from_id, t.to_id, t.turns_left])
+            .collect();
+        moving.sort();
+        key.extend(moving.into_iter().flatten());
+
+        return key;
+    }
+}
+
+impl Search<'_> {
+    /// A lower bound on the turns left, counting pearls in transit as if
+    /// they had already landed. None if some pearl can never be delivered.
+    fn estimate(&self, node: &Node) -> Option<u32> {
+        let mut landed = node.state.clone();
+        for t in &node.in_transit {
+            if let Some(w) = landed.workers.iter_mut().find(|w| w.id == t.to_id) {
+                w.desk.push(t.pearl.clone());
+            }
+        }
+
+        let bound = estimate_lower_bound_with_graph(
+            &landed,
+            &self.neighbor_graph,
+            self.ability_map,
+            self.gatekeeper_id,
+        );
+        if !bound.stranded.is_empty() {
+            return None;
+        }
+        if bound.lower_bound == 0 && !node.in_transit.is_empty() {
+            return Some(1);
+        }
+        return Some(bound.lower_bound);
+    }
+
+    /// Every set of actions worth trying from a node. A worker with a pearl
+    /// left to nom always acts, since nomming never makes things worse, and
+    /// no empty desk is sent more than one pearl.
+    fn joint_actions(&self, node: &Node) -> Vec<Actions> {
+        let empty: HashSet<u32> = node
+            .state
+            .workers
+            .iter()
+            .filter(|w| w.desk.is_empty())
+            .map(|w| w.id)
+            .collect();
+        let mut workers: Vec<_> = node.state.workers.iter().collect();
+        workers.sort_by_key(|w| w.id);
+
+        let mut joint: Vec<Actions> = vec![HashMap::new()];
+        for w in workers {
+            let mut options: Vec<Option<ActionType>> = Vec::new();
+            let mut nbrs = self.neighbor_graph.get(&w.id).cloned().unwrap_or_default();
+            nbrs.sort();
+
+            let mut desk: Vec<_> = w.desk.iter().collect();
+            desk.sort_by_key(|p| p.id);
+            for p in &desk {
+                if !p.layers.is_empty() {
+                    options.push(Some(make_nom(w.id, p.id)));
+                }
+            }
+            if options.is_empty() {
+                options.push(None);
+            }
+            for p in &desk {
+                for &to in &nbrs {
+                    if to != w.id {
+                        options.push(Some(make_pass(w.id, p.id, to)));
+                    }
+                }
+            }
+
+            let mut next: Vec<Actions> = Vec::new();
+            for actions in &joint {
+                for option in &options {
+                    let mut actions = actions.clone();
+                    if let Some(a) = option {
+                        if let ActionType::Pass(p) = a {
+                            let taken = actions
+                                .values()
+                                .any(|b| matches!(b, ActionType::Pass(q) if q.to_id == p.to_id));
+                            if taken && empty.contains(&p.to_id) {
+                                continue;
+                            }
+                        }
+                        actions.insert(w.id, a.clone());
+                    }
+                    next.push(actions);
+                }
+            }
+            joint = next;
+        }
+
+        return joint;
+    }
+
+    fn visit(&mut self, node: &Node, estimate: u32, turns: u32, limit: u32) -> Outcome {
+        self.states += 1;
+        if self.states > self.max_states {
+            return Outcome::GaveUp;
+        }
+        if node.is_done() {
+            return Outcome::Found;
+        }
+
+        let total = turns.saturating_add(estimate);
+        if total > limit {
+            return Outcome::Exceeded(total);
+        }
+
+        // Getting here no sooner than before can't find anything new
+        let key = node.key();
+        if self.seen.get(&key).is_some_and(|&t| t <= turns) {
+            return Outcome::Exceeded(u32::MAX);
+        }
+        self.seen.insert(key, turns);
+
+        let mut children: Vec<(u32, Actions, Node)> = Vec::new();
+        let mut keys: HashSet<Vec<u32>> = HashSet::new();
+        for actions in self.joint_actions(node) {
+            let mut in_transit = node.in_transit.clone();
+            // Nodes don't carry pearl paths, every action is tried anyway
+            let state = apply_turn(
+                &node.state,
+                &actions,
+                self.ability_map,
+                self.gatekeeper_id,
+                &self.transfer_costs,
+                &mut in_transit,
+                &mut HashMap::new(),
+            );
+            let child = Node {
+                state: state,
+                in_transit: in_transit,
+            };
+
+            if !keys.insert(child.key()) {
+                continue;
+            }
+            if let Some(e) = self.estimate(&child) {
+                children.push((e, actions, child));
+            }
+        }
+        // Most promising first, keeping the order they were found in on ties
+        children.sort_by_key(|(e, _, _)| *e);
+
+        let mut smallest = u32::MAX;
+        for (e, actions, child) in children {
+            self.path.push(actions);
+            match self.visit(&child, e, turns + 1, limit) {
+                Outcome::Found => return Outcome::Found,
+                Outcome::GaveUp => return Outcome::GaveUp,
+                Outcome::Exceeded(t) => smallest = smallest.min(t),
+            }
+            self.path.pop();
+        }
+
+        return Outcome::Exceeded(smallest);
+    }
+}
+
+/// Finds the fewest turns it takes to deliver every pearl in a game, and
+/// the actions that do it, for games small enough to search exhaustively.
+/// The search is iterative deepening branch and bound: it looks for a
+/// solution within a number of turns, cutting off any line of play whose
+/// turns so far plus the lower bound from `estimate_lower_bound` goes over,
+/// and raises the limit until one is found. Since the bound never
+/// overestimates, the first solution found is optimal.
+///
+/// The game follows the simulator's rules, including transfer costs and
+/// edge capacities.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to start from
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config, for the gatekeeper and transfer costs
+/// * `max_states` - How many states to search before giving up
+///
+/// # Returns
+///
+/// * `Result<Solution, String>` - The optimal actions, or a message saying
+///                                why none were found
+pub fn solve_exact(
+    state: &State,
+    ability_map: &AbilityMap,
+    config: &Config,
+    max_states: u64,
+) -> Result<Solution, String> {
+    let neighbor_graph = build_state_graph(state, &config.transfer);
+    let mut search = Search {
+        transfer_costs: TransferCosts::new(&config.transfer).with_graph(&neighbor_graph),
+        neighbor_graph: neighbor_graph,
+        ability_map: ability_map,
+        gatekeeper_id: config.gatekeeper_id,
+        max_states: max_states,
+        states: 0,
+        seen: HashMap::new(),
+        path: Vec::new(),
+    };
+
+    let root = Node {
+        state: state.clone(),
+        in_transit: Vec::new(),
+    };
+    let mut limit = match search.estimate(&root) {
+        Some(e) => e,
+        None => return Err(String::from("some pearls can never be delivered")),
+    };
+
+    loop {
+        search.seen.clear();
+        search.path.clear();
+
+        match search.visit(&root, limit, 0, limit) {
+            Outcome::Found => {
+                return Ok(Solution {
+                    turns: search.path.len() as u32,
+                    actions: search.path,
+                    states_searched: search.states,
+                });
+            }
+            Outcome::GaveUp => {
+                return Err(format!(
+                    "gave up after searching {} states, the game is too big to solve exactly",
+                    max_states
+                ));
+            }
+            Outcome::Exceeded(u32::MAX) => {
+                return Err(String::from("some pearls can never be delivered"));
+            }
+            Outcome::Exceeded(next) => limit = next,
+        }
+    }
+}
+
+/// Writes one turn's actions as JSON, keyed by worker ID like `stdout`.
+fn actions_to_value(actions: &Actions) -> Value {
+    let mut map = Map::new();
+    for (id, action) in actions {
+        map.insert(id.to_string(), action_to_value(action));
+    }
+    return Value::Object(map);
+}
+
+/// Solves a game exactly and plays it with greedy, to see how far from
+/// optimal greedy is.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to start from
+/// * `ability_map` - A reference to the ability map describing how different
+///                   flavors of worker can process different colors of pearls
+/// * `config` - The config to make decisions with
+/// * `max_states` - How many states the solver searches before giving up
+/// * `max_turns` - The most turns to let greedy play
+///
+/// # Returns
+///
+/// * `Result<SolvedGame, String>` - How greedy did against the optimal
+///                                  solution, or why there isn't one
+pub fn compare_to_optimal(
+    state: &State,
+    ability_map: &AbilityMap,
+    config: &Config,
+    max_states: u64,
+    max_turns: u32,
+) -> Result<SolvedGame, String> {
+    let solution = solve_exact(state, ability_map, config, max_states)?;
+
+    let pearl_count: usize = state.workers.iter().map(|w| w.desk.len()).sum();
+    let (turns, end) = simulate(state, ability_map, config, max_turns);
+
+    return Ok(SolvedGame {
+        optimal_turns: solution.turns,
+        greedy_turns: turns.len() as u32,
+        greedy_finished: end.score.saturating_sub(state.score) as usize >= pearl_count,
+        states_searched: solution.states_searched,
+        actions: solution.actions.iter().map(actions_to_value).collect(),
+    });
+}
+
+/// Writes a game greedy gets wrong out as a regression fixture.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state the game starts from
+/// * `solved` - A reference to the game's solution
+///
+/// # Returns
+///
+/// * `String` - The fixture as a single line of JSON
+pub fn solved_to_fixture(state: &State, solved: &SolvedGame) -> String {
+    let fixture = SolverFixture {
+        state: state.clone(),
+        optimal_turns: solved.optimal_turns,
+        greedy_turns: solved.greedy_turns,
+        actions: solved.actions.clone(),
+    };
+
+    return match serde_json::to_string(&fixture) {
+        Ok(s) => s,
+        Err(e) => panic!("Solver Error: could not write fixture: {}", e),
+    };
+}
+
+/// Writes solved games out as readable text.
+///
+/// # Arguments
+///
+/// * `games` - The solved games
+///
+/// # Returns
+///
+/// * `String` - Each game's result and optimal actions, one per line
+pub fn solved_to_text(games: &[SolvedGame]) -> String {
+    let mut out = String::new();
+
+    for (i, g) in games.iter().enumerate() {
+        let greedy = if g.greedy_finished {
+            format!("greedy {} turns", g.greedy_turns)
+        } else {
+            format!("greedy unfinished after {} turns", g.greedy_turns)
+        };
+        out += &format!(
+            "game {}: optimal {} turns, {}, searched {} states\n",
+            i, g.optimal_turns, greedy, g.states_searched
+        );
+        for (turn, actions) in g.actions.iter().enumerate() {
+            out += &format!("  turn {}: {}\n", turn, actions);
+        }
+    }
+
+    let suboptimal = games.iter().filter(|g| g.greedy_is_suboptimal()).count();
+    out += &format!(
+        "greedy is suboptimal in {} of {} games\n",
+        suboptimal,
+        games.len()
+    );
+
+    return out;
+}
+
+/// Writes solved games out as JSON.
+///
+/// # Arguments
+///
+/// * `games` - The solved games
+///
+/// # Returns
+///
+/// * `String` - The games as a single line of JSON
+pub fn solved_to_json(games: &[SolvedGame]) -> String {
+    return match serde_json::to_string(games) {
+        Ok(s) => s,
+        Err(e) => panic!("Solver Error: could not write games: {}", e),
+    };
+}
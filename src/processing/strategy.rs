@@ -0,0 +1,190 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use super::mcts::MctsStrategy;
+use super::transfer::TransferCosts;
+use super::utils::determine_actions;
+use crate::models;
+use models::ability_map::AbilityMap;
+use models::action::Actions;
+use models::config::{Config, Planner};
+use models::state::{NeighborGraph, PearlPaths, State};
+
+/// Everything a strategy gets to see when deciding a turn.
+pub struct Turn<'a> {
+    /// How many turns have been decided before this one
+    pub number: u32,
+    pub state: &'a State,
+    pub ability_map: &'a AbilityMap,
+    pub neighbor_graph: &'a NeighborGraph,
+    /// How long a pass over each edge leaves a pearl in transit
+    pub transfer_costs: &'a TransferCosts,
+    pub config: &'a Config,
+}
+
+/// When a turn's decision has to be made by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deadline {
+    at: Option<Instant>,
+}
+
+impl Deadline {
+    /// A deadline that never expires
+    pub fn unlimited() -> Deadline {
+        return Deadline { at: None };
+    }
+
+    /// A deadline `budget` from now
+    pub fn after(budget: Duration) -> Deadline {
+        return Deadline {
+            at: Some(Instant::now() + budget),
+        };
+    }
+
+    /// Reads the per-turn budget from the config
+    pub fn from_config(config: &Config) -> Deadline {
+        return match config.turn_budget_ms {
+            Some(ms) => Deadline::after(Duration::from_millis(ms)),
+            None => Deadline::unlimited(),
+        };
+    }
+
+    /// Whether the deadline has passed
+    pub fn expired(&self) -> bool {
+        return match self.at {
+            Some(at) => Instant::now() >= at,
+            None => false,
+        };
+    }
+
+    /// How long is left before the deadline, or `None` if it never expires
+    pub fn remaining(&self) -> Option<Duration> {
+        return self
+            .at
+            .map(|at| at.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// What an anytime strategy came up with before its deadline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anytime {
+    /// The search finished
+    Complete(Actions),
+    /// The deadline expired part way through, and these are the best actions
+    /// found so far
+    BestSoFar(Actions),
+    /// The deadline expired before any plan was found
+    Nothing,
+}
+
+/// How each turn's decision was reached, for judging whether the per-turn
+/// budget is big enough.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct DeadlineStats {
+    /// Turns where the strategy finished in time
+    pub completed: u32,
+    /// Turns where the strategy finished, but only after the deadline
+    pub overran: u32,
+    /// Turns where the best plan found before the deadline was used
+    pub best_so_far: u32,
+    /// Turns where the greedy actions were used because the strategy found
+    /// nothing in time
+    pub greedy_fallback: u32,
+}
+
+impl DeadlineStats {
+    /// How many turns have been recorded
+    pub fn turns(&self) -> u32 {
+        return self.completed + self.overran + self.best_so_far + self.greedy_fallback;
+    }
+}
+
+/// A way of deciding what every worker does in a turn. Strategies may keep
+/// their own state between turns, which `reset` should clear.
+pub trait Strategy: Send {
+    /// A short name for the strategy, used in snapshots and diagnostics
+    fn name(&self) -> &str;
+
+    /// Decides the actions for a turn. Any pearl passed away from its
+    /// holder should have the holder pushed onto its path in `pearl_paths`,
+    /// so that it can find its way back once finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `turn` - A reference to everything known about this turn
+    /// * `pearl_paths` - How each pearl has been passed around so far
+    ///
+    /// # Returns
+    ///
+    /// * `Actions` - The action each worker should take
+    fn decide(&mut self, turn: &Turn, pearl_paths: &mut PearlPaths) -> Actions;
+
+    /// Decides the actions for a turn within a deadline. Expensive
+    /// strategies should check `deadline` as they search and stop once it
+    /// expires, returning the best plan found so far. `pearl_paths` should
+    /// only be updated for the actions actually returned.
+    ///
+    /// By default this runs `decide` to completion, however long it takes,
+    /// so the deadline only applies to strategies that override this.
+    /// `GreedyStrategy` keeps the default, since it is what the pipeline
+    /// falls back on when a deadline expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `turn` - A reference to everything known about this turn
+    /// * `pearl_paths` - How each pearl has been passed around so far
+    /// * `deadline` - When the decision has to be made by
+    ///
+    /// # Returns
+    ///
+    /// * `Anytime` - The actions, and whether the search finished
+    #[allow(unused_variables)]
+    fn decide_within(
+        &mut self,
+        turn: &Turn,
+        pearl_paths: &mut PearlPaths,
+        deadline: &Deadline,
+    ) -> Anytime {
+        return Anytime::Complete(self.decide(turn, pearl_paths));
+    }
+
+    /// Forgets anything learned during the current game
+    fn reset(&mut self) {}
+}
+
+/// The rule based strategy described in the README, implemented by
+/// `determine_actions`.
+#[derive(Debug, Clone, Default)]
+pub struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn name(&self) -> &str {
+        return "greedy";
+    }
+
+    fn decide(&mut self, turn: &Turn, pearl_paths: &mut PearlPaths) -> Actions {
+        return determine_actions(
+            turn.state,
+            turn.ability_map,
+            turn.neighbor_graph,
+            pearl_paths,
+            turn.config,
+        );
+    }
+}
+
+/// Creates the strategy named by `planner` in the config.
+///
+/// # Arguments
+///
+/// * `config` - The config for this game
+///
+/// # Returns
+///
+/// * `Box<dyn Strategy>` - The strategy to decide each turn with
+pub fn build_strategy(config: &Config) -> Box<dyn Strategy> {
+    return match config.planner {
+        Planner::Greedy => Box::new(GreedyStrategy),
+        Planner::Mcts => Box::new(MctsStrategy),
+    };
+}
@@ -1,2 +1,69 @@
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+mod test_ability_estimator;
+mod test_bound;
+mod test_command;
+mod test_config;
+mod test_explain;
+mod test_fuzz;
+mod test_gatekeeper;
+#[cfg(feature = "http")]
+mod test_http;
+mod test_invariants;
+mod test_layout;
+mod test_legality;
+mod test_mcts;
+mod test_metrics;
+mod test_neighbor_graph;
 mod test_pearl_processing;
+mod test_progress;
+mod test_scoring;
+mod test_server;
+mod test_simulator;
+mod test_solver;
+mod test_topology;
+mod test_transfer;
 mod test_utils;
+mod test_validate;
+
+/// A pearl with a single layer of the given color and thickness
+pub fn pearl(id: u32, color: &str, thickness: u32) -> Pearl {
+    return Pearl {
+        id: id,
+        layers: vec![Layer {
+            color: String::from(color),
+            thickness: thickness,
+            original_thickness: None,
+        }],
+    };
+}
+
+/// A pearl with every layer nommed away
+pub fn finished_pearl(id: u32) -> Pearl {
+    return Pearl {
+        id: id,
+        layers: vec![],
+    };
+}
+
+pub fn worker(id: u32, flavor: &str, desk: Vec<Pearl>) -> Worker {
+    return Worker {
+        id: id,
+        desk: desk,
+        flavor: String::from(flavor),
+    };
+}
+
+/// A General gatekeeper with a Matrix next to it, and a Blue pearl on the
+/// gatekeeper's desk
+pub fn matrix_state() -> State {
+    return State {
+        workers: vec![
+            worker(0, "General", vec![pearl(1, "Blue", 10)]),
+            worker(1, "Matrix", vec![]),
+        ],
+        neighbor_map: vec![vec![0, 1]],
+        edges: vec![],
+        score: 0,
+    };
+}
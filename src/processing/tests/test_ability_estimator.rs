@@ -0,0 +1,100 @@
+use super::super::ability_estimator::AbilityEstimator;
+use super::super::utils::get_ability_map;
+use crate::models::config::Config;
+use crate::models::state::{Layer, Pearl, State, Worker};
+use crate::Pipeline;
+
+/// Returns a state with a single Vector worker holding one pearl made of
+/// the given layers
+fn state_with_layers(layers: Vec<(&str, u32)>) -> State {
+    return State {
+        workers: vec![Worker {
+            id: 0,
+            desk: vec![Pearl {
+                id: 1,
+                layers: layers
+                    .iter()
+                    .map(|(c, t)| Layer {
+                        color: String::from(*c),
+                        thickness: *t,
+                        original_thickness: None,
+                    })
+                    .collect(),
+            }],
+            flavor: String::from("Vector"),
+        }],
+        neighbor_map: vec![],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+#[test]
+fn test_exact_rate_observed() {
+    let mut est = AbilityEstimator::new();
+    est.observe(&state_with_layers(vec![("Green", 12)]));
+    est.observe(&state_with_layers(vec![("Green", 7)]));
+
+    assert_eq!(
+        est.observations("Vector", "Green").unwrap().estimate(),
+        Some(5)
+    );
+    assert!(est.disagreements(&get_ability_map()).is_empty());
+}
+
+#[test]
+fn test_lower_bound_from_dissolved_layer() {
+    let mut est = AbilityEstimator::new();
+    est.observe(&state_with_layers(vec![("Red", 3), ("Blue", 4)]));
+    est.observe(&state_with_layers(vec![("Blue", 4)]));
+
+    let obs = est.observations("Vector", "Red").unwrap();
+    assert_eq!(obs.estimate(), None);
+    assert_eq!(obs.lower_bound, 3);
+
+    // The static map says Vector dissolves 1 red per turn
+    let learned = est.ability_map(&get_ability_map());
+    assert_eq!(learned["Vector"]["Red"], 3);
+    assert_eq!(est.disagreements(&get_ability_map()).len(), 1);
+}
+
+#[test]
+fn test_learned_map_reports_disagreement() {
+    let mut est = AbilityEstimator::new();
+    est.observe(&state_with_layers(vec![("Blue", 10)]));
+    est.observe(&state_with_layers(vec![("Blue", 7)]));
+
+    let static_map = get_ability_map();
+    let learned = est.ability_map(&static_map);
+    assert_eq!(learned["Vector"]["Blue"], 3);
+    assert_eq!(learned["Matrix"]["Blue"], static_map["Matrix"]["Blue"]);
+
+    let diffs = est.disagreements(&static_map);
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].expected, 2);
+    assert_eq!(diffs[0].observed, 3);
+}
+
+#[test]
+fn test_untouched_pearl_not_observed() {
+    let mut est = AbilityEstimator::new();
+    est.observe(&state_with_layers(vec![("Green", 12)]));
+    est.observe(&state_with_layers(vec![("Green", 12)]));
+
+    assert!(est.observations("Vector", "Green").is_none());
+}
+
+#[test]
+fn test_pipeline_reports_disagreements_once() {
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+    pipeline.step(&state_with_layers(vec![("Blue", 10)]));
+    pipeline.step(&state_with_layers(vec![("Blue", 7)]));
+    pipeline.step(&state_with_layers(vec![("Blue", 4)]));
+
+    // Every entry point steps the pipeline, so they all get the warning
+    assert_eq!(pipeline.disagreements().len(), 1);
+    assert_eq!(pipeline.disagreements()[0].observed, 3);
+
+    pipeline.reset();
+    assert!(pipeline.disagreements().is_empty());
+}
@@ -0,0 +1,103 @@
+use super::super::bound::{
+    bound_report, bound_to_json, bound_to_text, compare_to_greedy, estimate_lower_bound,
+};
+use super::super::scenario::generate_scenarios;
+use super::super::utils::get_ability_map;
+use super::{matrix_state, pearl, worker};
+use crate::models::config::{Config, TransferConfig};
+use crate::models::state::State;
+
+#[test]
+fn test_fastest_reachable_flavor() {
+    let bound = estimate_lower_bound(&matrix_state(), &get_ability_map(), &Config::default());
+
+    // Out to the Matrix, one nom, and back again beats 10 noms at home
+    assert_eq!(bound.pearls[0].turns, 3);
+    assert_eq!(bound.pearls[0].work, 1);
+    assert_eq!(bound.critical_path, 3);
+    assert_eq!(bound.parallel, 1);
+    assert_eq!(bound.lower_bound, 3);
+    assert!(bound.stranded.is_empty());
+}
+
+#[test]
+fn test_transfer_costs_lengthen_the_path() {
+    let config = Config {
+        transfer: TransferConfig {
+            cost: 4,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let bound = estimate_lower_bound(&matrix_state(), &get_ability_map(), &config);
+
+    // Each pass now takes 5 turns, so the General at home is quicker
+    assert_eq!(bound.lower_bound, 10);
+}
+
+#[test]
+fn test_workers_share_the_work() {
+    let state = State {
+        workers: vec![worker(
+            0,
+            "General",
+            (1..=4).map(|id| pearl(id, "Red", 3)).collect(),
+        )],
+        neighbor_map: vec![],
+        edges: vec![],
+        score: 0,
+    };
+    let game = compare_to_greedy(&state, &get_ability_map(), &Config::default(), 100);
+
+    assert_eq!(game.bound.critical_path, 3);
+    assert_eq!(game.bound.parallel, 12);
+    assert_eq!(game.bound.lower_bound, 12);
+    assert!(game.finished);
+    assert_eq!(game.greedy_turns, 12);
+    assert_eq!(game.gap, Some(0));
+}
+
+#[test]
+fn test_stranded_pearls() {
+    let mut state = matrix_state();
+    state
+        .workers
+        .push(worker(2, "Vector", vec![pearl(2, "Green", 5)]));
+    let game = compare_to_greedy(&state, &get_ability_map(), &Config::default(), 50);
+
+    assert_eq!(game.bound.stranded, vec![2]);
+    assert!(!game.finished);
+    assert_eq!(game.gap, None);
+
+    let report = bound_report(&[state], &get_ability_map(), &Config::default(), 50);
+    assert_eq!(report.unfinished, 1);
+    assert!(bound_to_text(&report).contains("WARNING pearls that can't be delivered: [2]"));
+}
+
+#[test]
+fn test_greedy_never_beats_the_bound() {
+    let floor = State {
+        workers: vec![
+            worker(0, "General", vec![]),
+            worker(1, "Vector", vec![]),
+            worker(2, "Matrix", vec![]),
+            worker(3, "General", vec![]),
+        ],
+        neighbor_map: vec![vec![0, 1], vec![1, 2], vec![0, 3], vec![3, 2]],
+        edges: vec![],
+        score: 0,
+    };
+    let config = Config::default();
+    let games = generate_scenarios(&floor, &config.scenario, config.gatekeeper_id);
+    let report = bound_report(&games, &get_ability_map(), &config, config.scenario.turns);
+
+    assert_eq!(report.games.len(), games.len());
+    for g in &report.games {
+        assert!(g.finished);
+        assert!(g.greedy_turns >= g.bound.lower_bound);
+    }
+    assert!(report.mean_gap.is_some());
+
+    let json: serde_json::Value = serde_json::from_str(&bound_to_json(&report)).unwrap();
+    assert_eq!(json["unfinished"], 0);
+}
@@ -0,0 +1,209 @@
+use super::super::solver::DEFAULT_MAX_STATES;
+use crate::command::{parse_args, parse_command, Command};
+
+fn args(list: &[&str]) -> Vec<String> {
+    return list.iter().map(|a| a.to_string()).collect();
+}
+
+fn parse(list: &[&str]) -> Result<Command, String> {
+    return parse_command(&args(list));
+}
+
+#[test]
+fn test_run_is_the_default() {
+    assert_eq!(parse(&[]), Ok(Command::Run));
+    assert_eq!(parse(&["run"]), Ok(Command::Run));
+}
+
+#[test]
+fn test_help_and_version() {
+    assert_eq!(parse(&["-h"]), Ok(Command::Help));
+    assert_eq!(parse(&["solve", "--help"]), Ok(Command::Help));
+    assert_eq!(parse(&["-V"]), Ok(Command::Version));
+    assert_eq!(parse(&["run", "--version"]), Ok(Command::Version));
+}
+
+#[test]
+fn test_file_commands() {
+    assert_eq!(
+        parse(&["simulate"]),
+        Ok(Command::Simulate {
+            file: None,
+            turns: 1000
+        })
+    );
+    assert_eq!(
+        parse(&["simulate", "game.txt", "--turns", "5"]),
+        Ok(Command::Simulate {
+            file: Some(String::from("game.txt")),
+            turns: 5
+        })
+    );
+    assert_eq!(
+        parse(&["validate", "game.txt"]),
+        Ok(Command::Validate {
+            file: String::from("game.txt")
+        })
+    );
+    assert_eq!(
+        parse(&["render", "--dot"]),
+        Ok(Command::Render {
+            file: None,
+            dot: true
+        })
+    );
+}
+
+#[test]
+fn test_state_commands() {
+    assert_eq!(
+        parse(&["explain", "-", "--json"]),
+        Ok(Command::Explain {
+            state: String::from("-"),
+            json: true
+        })
+    );
+    assert_eq!(
+        parse(&["topology", "-"]),
+        Ok(Command::Topology {
+            state: String::from("-"),
+            hops: 2,
+            json: false
+        })
+    );
+    assert_eq!(
+        parse(&["topology", "-", "--hops", "3", "--json"]),
+        Ok(Command::Topology {
+            state: String::from("-"),
+            hops: 3,
+            json: true
+        })
+    );
+    assert_eq!(
+        parse(&["advise-layout", "-", "--colors", "Red=2,Blue=1"]),
+        Ok(Command::AdviseLayout {
+            state: String::from("-"),
+            colors: Some(String::from("Red=2,Blue=1")),
+            json: false
+        })
+    );
+    assert_eq!(
+        parse(&["bound", "-", "--scenario", "--turns", "50"]),
+        Ok(Command::Bound {
+            state: String::from("-"),
+            scenario: true,
+            turns: Some(50),
+            json: false
+        })
+    );
+}
+
+#[test]
+fn test_solve_options() {
+    assert_eq!(
+        parse(&["solve", "-"]),
+        Ok(Command::Solve {
+            state: String::from("-"),
+            scenario: false,
+            max_states: DEFAULT_MAX_STATES,
+            turns: None,
+            fixtures: None,
+            json: false
+        })
+    );
+    assert_eq!(
+        parse(&[
+            "solve",
+            "-",
+            "--scenario",
+            "--max-states",
+            "10",
+            "--turns",
+            "20",
+            "--fixtures",
+            "out.jsonl",
+            "--json"
+        ]),
+        Ok(Command::Solve {
+            state: String::from("-"),
+            scenario: true,
+            max_states: 10,
+            turns: Some(20),
+            fixtures: Some(String::from("out.jsonl")),
+            json: true
+        })
+    );
+}
+
+#[test]
+fn test_server_commands() {
+    assert_eq!(
+        parse(&["serve", "--listen", "tcp:127.0.0.1:7000"]),
+        Ok(Command::Serve {
+            listen: String::from("tcp:127.0.0.1:7000"),
+            idle_timeout: 300
+        })
+    );
+    assert_eq!(
+        parse(&["http", "--listen", "127.0.0.1:8080", "--idle-timeout", "0"]),
+        Ok(Command::Http {
+            listen: String::from("127.0.0.1:8080"),
+            idle_timeout: 0
+        })
+    );
+}
+
+#[test]
+fn test_bad_command_lines() {
+    let cases: [(&[&str], &str); 11] = [
+        (&["dance"], "Unknown command dance"),
+        (&["simulate", "--dot"], "Unknown option --dot for simulate"),
+        (&["run", "--json"], "Unknown option --json for run"),
+        (
+            &["simulate", "--turns", "many"],
+            "--turns needs a whole number",
+        ),
+        (&["topology", "-", "--hops"], "--hops needs a whole number"),
+        (&["solve", "-", "--fixtures"], "--fixtures needs a file"),
+        (
+            &["validate", "a.txt", "b.txt"],
+            "Too many arguments for validate",
+        ),
+        (&["run", "extra"], "Too many arguments for run"),
+        (&["validate"], "validate needs a file to check"),
+        (&["explain"], "explain needs a state to explain"),
+        (&["serve"], "serve needs an address to --listen on"),
+    ];
+
+    for (line, message) in cases {
+        assert_eq!(parse(line), Err(String::from(message)), "{:?}", line);
+    }
+}
+
+#[test]
+fn test_config_flags_around_the_command() {
+    let (config_args, command) = parse_args(&args(&[
+        "--set",
+        "strategy.min_pass_gain=2",
+        "explain",
+        "-",
+        "--gatekeeper-id",
+        "3",
+        "--json",
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        config_args.overrides,
+        vec!["strategy.min_pass_gain=2", "gatekeeper_id=3"]
+    );
+    assert_eq!(
+        command,
+        Command::Explain {
+            state: String::from("-"),
+            json: true
+        }
+    );
+
+    assert!(parse_args(&args(&["explain", "-", "--set"])).is_err());
+}
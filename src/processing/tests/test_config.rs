@@ -0,0 +1,111 @@
+use std::fs;
+
+use super::super::config::{apply_override, load_config, parse_config_args};
+use crate::models::config::{Config, TieBreak};
+
+fn args(list: &[&str]) -> Vec<String> {
+    return list.iter().map(|s| s.to_string()).collect();
+}
+
+#[test]
+fn test_defaults_match_greedy_rules() {
+    let config = Config::default();
+
+    assert_eq!(config.gatekeeper_id, 0);
+    assert_eq!(config.strategy.min_pass_gain, 1);
+    assert_eq!(config.strategy.equal_pass_min_desk, 2);
+    assert_eq!(config.strategy.nom_tie_break, TieBreak::First);
+}
+
+#[test]
+fn test_shipped_config_is_default() {
+    let config = load_config(Some("atlantis.json"), &[]).unwrap();
+
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn test_parse_config_args() {
+    let parsed = parse_config_args(&args(&[
+        "--config",
+        "a.json",
+        "--gatekeeper-id",
+        "3",
+        "extra",
+        "--set",
+        "strategy.min_pass_gain=2",
+    ]))
+    .unwrap();
+
+    assert_eq!(parsed.path, Some(String::from("a.json")));
+    assert_eq!(
+        parsed.overrides,
+        args(&["gatekeeper_id=3", "strategy.min_pass_gain=2"])
+    );
+    assert_eq!(parsed.rest, args(&["extra"]));
+}
+
+#[test]
+fn test_parse_config_args_missing_value() {
+    assert!(parse_config_args(&args(&["--set"])).is_err());
+}
+
+#[test]
+fn test_overrides_replace_single_keys() {
+    let overrides = args(&[
+        "gatekeeper_id=4",
+        "strategy.nom_tie_break=HighestId",
+        "gatekeeper.hop_cost=3",
+    ]);
+    let config = load_config(Some("atlantis.json"), &overrides).unwrap();
+
+    assert_eq!(config.gatekeeper_id, 4);
+    assert_eq!(config.strategy.nom_tie_break, TieBreak::HighestId);
+    assert_eq!(config.gatekeeper.hop_cost, 3);
+    assert_eq!(
+        config.gatekeeper.max_in_flight,
+        Config::default().gatekeeper.max_in_flight
+    );
+}
+
+#[test]
+fn test_unknown_override_key() {
+    let mut value = serde_json::to_value(Config::default()).unwrap();
+
+    assert!(apply_override(&mut value, "strategy.nope=1").is_err());
+    assert!(apply_override(&mut value, "no_equals_sign").is_err());
+}
+
+#[test]
+fn test_partial_config_file() {
+    let path = std::env::temp_dir().join("atlantis_partial_config.json");
+    fs::write(&path, "{\"strategy\": {\"min_pass_gain\": 3}}").unwrap();
+
+    let config = load_config(path.to_str(), &[]).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.strategy.min_pass_gain, 3);
+    assert_eq!(config.strategy.equal_pass_min_desk, 2);
+    assert_eq!(config.gatekeeper, Config::default().gatekeeper);
+}
+
+#[test]
+fn test_unknown_config_file_key() {
+    let path = std::env::temp_dir().join("atlantis_typo_config.json");
+    fs::write(&path, "{\"strategy\": {\"min_pas_gain\": 3}}").unwrap();
+
+    let loaded = load_config(path.to_str(), &[]);
+    fs::remove_file(&path).unwrap();
+
+    let error = loaded.unwrap_err();
+    assert!(error.contains("min_pas_gain"), "{}", error);
+}
+
+#[test]
+fn test_ability_map_path_is_kept_verbatim() {
+    let path = "C:\\maps\\\"odd\".json";
+    let parsed = parse_config_args(&args(&["--ability-map", path])).unwrap();
+    let config = load_config(Some("atlantis.json"), &parsed.overrides).unwrap();
+
+    assert_eq!(config.ability_map_path, Some(String::from(path)));
+}
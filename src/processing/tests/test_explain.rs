@@ -0,0 +1,102 @@
+use super::super::explain::{explain_state, explanation_to_json, explanation_to_text};
+use super::super::utils::get_ability_map;
+use crate::models::config::Config;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+fn green_pearl(id: u32) -> Pearl {
+    return Pearl {
+        id: id,
+        layers: vec![Layer {
+            color: String::from("Green"),
+            thickness: 10,
+            original_thickness: None,
+        }],
+    };
+}
+
+/// Returns a line 0 - 1 - 2, where worker 1 is a slow General holding a
+/// green pearl, next to a busy Vector gatekeeper and an idle Vector
+fn basic_state() -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![green_pearl(1)],
+                flavor: String::from("Vector"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![green_pearl(2)],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 2,
+                desk: vec![],
+                flavor: String::from("Vector"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1], vec![1, 2]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+#[test]
+fn test_explain_pass() {
+    let exp = explain_state(&basic_state(), &get_ability_map(), &Config::default());
+    let w1 = &exp.workers[1];
+
+    assert_eq!(w1.worker_id, 1);
+    assert_eq!(w1.pearls[0].own_time, 10);
+    assert_eq!(w1.pearls[0].neighbor_times[1].time, 2);
+
+    assert_eq!(w1.candidates.len(), 2);
+    assert!(!w1.candidates[0].chosen);
+    assert_eq!(w1.candidates[0].reason, "the neighbor's desk is not empty");
+    assert!(w1.candidates[1].chosen);
+
+    assert_eq!(
+        w1.action.as_ref().unwrap().to_string(),
+        "{\"Pass\":{\"pearl_id\":2,\"to_worker\":2}}"
+    );
+}
+
+#[test]
+fn test_explain_gatekeeper_and_idle() {
+    let exp = explain_state(&basic_state(), &get_ability_map(), &Config::default());
+
+    assert!(exp.workers[0].intake.is_some());
+    assert_eq!(exp.workers[0].in_flight, Some(1));
+    assert!(exp.workers[2].action.is_none());
+}
+
+#[test]
+fn test_explain_output_formats() {
+    let exp = explain_state(&basic_state(), &get_ability_map(), &Config::default());
+
+    let text = explanation_to_text(&exp);
+    assert!(text.contains("pass 2 -> 2: chosen"));
+
+    let json: serde_json::Value = serde_json::from_str(&explanation_to_json(&exp)).unwrap();
+    assert_eq!(json["workers"][1]["candidates"][1]["chosen"], true);
+}
+
+#[test]
+fn test_explain_held_finished_pearl() {
+    // Worker 1 can't get its finished pearl home, but still passes the other
+    let mut state = basic_state();
+    state.workers[1].desk.push(Pearl {
+        id: 3,
+        layers: Vec::new(),
+    });
+    state.neighbor_map = vec![vec![1, 2]];
+
+    let exp = explain_state(&state, &get_ability_map(), &Config::default());
+    let w1 = &exp.workers[1];
+
+    assert!(w1.summary.starts_with("passes pearl 2 to 2"));
+    assert!(w1
+        .summary
+        .ends_with("holds finished pearl 3, there is no route home"));
+    assert!(w1.candidates.iter().all(|c| c.pearl_id != 3));
+}
@@ -0,0 +1,112 @@
+use proptest::prelude::*;
+
+use super::super::fuzz::{fuzz_bytes, fuzz_state};
+use crate::models::ability_map::{COLORS, FLAVORS};
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+/// A worker as its flavor index and the layers of each pearl on its desk,
+/// as color index, thickness and original thickness
+type FuzzWorker = (usize, Vec<Vec<(usize, u32, Option<u32>)>>);
+
+fn worker_strategy() -> impl Strategy<Value = FuzzWorker> {
+    let layer = (
+        0..COLORS.len(),
+        1u32..=u32::MAX,
+        prop::option::of(any::<u32>()),
+    );
+    return (
+        0..FLAVORS.len(),
+        prop::collection::vec(prop::collection::vec(layer, 0..4), 0..4),
+    );
+}
+
+/// Builds a state that passes validation but may have any floor layout at
+/// all: islands cut off from the gatekeeper, self loops, repeated edges.
+fn build_state(workers: Vec<FuzzWorker>, edges: Vec<(usize, usize)>) -> State {
+    let n = workers.len();
+    let mut next_pearl_id: u32 = 0;
+    let mut built: Vec<Worker> = Vec::new();
+
+    for (id, (flavor, desk)) in workers.into_iter().enumerate() {
+        let mut pearls: Vec<Pearl> = Vec::new();
+        for layers in desk {
+            pearls.push(Pearl {
+                id: next_pearl_id,
+                layers: layers
+                    .into_iter()
+                    .map(|(c, t, o)| Layer {
+                        color: COLORS[c].to_string(),
+                        thickness: t,
+                        original_thickness: o,
+                    })
+                    .collect(),
+            });
+            next_pearl_id += 1;
+        }
+
+        built.push(Worker {
+            id: id as u32,
+            flavor: FLAVORS[flavor].to_string(),
+            desk: pearls,
+        });
+    }
+
+    return State {
+        workers: built,
+        neighbor_map: edges
+            .into_iter()
+            .map(|(a, b)| vec![(a % n) as u32, (b % n) as u32])
+            .collect(),
+        edges: vec![],
+        score: 0,
+    };
+}
+
+fn state_strategy() -> impl Strategy<Value = State> {
+    return (
+        prop::collection::vec(worker_strategy(), 1..8),
+        prop::collection::vec((0usize..8, 0usize..8), 0..12),
+    )
+        .prop_map(|(w, e)| build_state(w, e));
+}
+
+#[test]
+fn test_fuzz_known_crashes() {
+    let inputs = [
+        "",
+        "{",
+        "null",
+        r#"{"workers":[{"id":0,"flavor":"General","desk":[]}],"neighbor_map":[[0]],"score":0}"#,
+        r#"{"workers":[{"id":0,"flavor":"General","desk":[]}],"neighbor_map":[[]],"score":0}"#,
+        r#"{"workers":[{"id":0,"flavor":"General","desk":[]},{"id":1,"flavor":"General","desk":[{"id":5,"layers":[]}]}],"neighbor_map":[],"score":0}"#,
+    ];
+
+    for input in inputs {
+        fuzz_bytes(input.as_bytes());
+    }
+
+    // These used to panic when read from stdin, and are now rejected
+    let invalid = [
+        r#"{"workers":[{"id":0,"flavor":"General","desk":[{"id":1,"layers":[{"color":"Red","thickness":3}]}]}],"neighbor_map":[[0,7]],"score":0}"#,
+        r#"{"workers":[{"id":0,"flavor":"Purple","desk":[{"id":1,"layers":[{"color":"Red","thickness":3}]}]}],"neighbor_map":[],"score":0}"#,
+    ];
+
+    for input in invalid {
+        assert!(fuzz_bytes(input.as_bytes()).is_none(), "{}", input);
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_fuzz_bytes(data in prop::collection::vec(any::<u8>(), 0..256)) {
+        fuzz_bytes(&data);
+    }
+
+    #[test]
+    fn test_fuzz_states(state in state_strategy()) {
+        prop_assert!(fuzz_state(&state).is_some());
+
+        let line = serde_json::to_vec(&state).unwrap();
+        prop_assert!(fuzz_bytes(&line).is_some());
+    }
+}
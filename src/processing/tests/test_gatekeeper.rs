@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use super::super::gatekeeper::{
+    count_pearls_in_flight, determine_gatekeeper_action, follow_hand_offs, get_distances_home,
+    get_gatekeeper_policy, get_next_hop_home, get_next_hops_home, rank_intake,
+};
+use super::super::utils::{build_neighbor_graph, get_ability_map, get_worker_pearl_counts};
+use crate::models::action::ActionType;
+use crate::models::config::{Config, StrategyConfig};
+use crate::models::gatekeeper_policy::GatekeeperPolicy;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+/// Returns a pearl made of a single layer of the given color
+fn single_layer_pearl(id: u32, color: &str, thickness: u32) -> Pearl {
+    return Pearl {
+        id: id,
+        layers: vec![Layer {
+            color: String::from(color),
+            thickness: thickness,
+            original_thickness: None,
+        }],
+    };
+}
+
+/// Returns a line of three workers, 0 - 1 - 2, where the gatekeeper is a
+/// Vector holding a blue pearl and a green pearl, and worker 2 is a Matrix
+/// that is very good at blue pearls.
+fn basic_state() -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![
+                    single_layer_pearl(10, "Blue", 20),
+                    single_layer_pearl(11, "Green", 10),
+                ],
+                flavor: String::from("Vector"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 2,
+                desk: vec![],
+                flavor: String::from("Matrix"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1], vec![1, 2]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+fn basic_config() -> Config {
+    return Config {
+        gatekeeper: GatekeeperPolicy {
+            max_in_flight: 4,
+            hop_cost: 1,
+            self_nom_margin: 0,
+        },
+        ..Config::default()
+    };
+}
+
+#[test]
+fn test_default_policy_loads() {
+    let policy = get_gatekeeper_policy();
+
+    assert!(policy.max_in_flight > 0);
+}
+
+#[test]
+fn test_distances_home() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let home = get_distances_home(0, &ng);
+
+    assert_eq!(home.len(), 3);
+    assert_eq!(home[&0], 0);
+    assert_eq!(home[&1], 1);
+    assert_eq!(home[&2], 2);
+
+    let hops = get_next_hops_home(0, &ng);
+    assert_eq!(hops[&2], 1);
+    assert_eq!(hops[&1], 0);
+}
+
+#[test]
+fn test_next_hop_home() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+
+    assert_eq!(get_next_hop_home(2, 0, &ng), Some(1));
+    assert_eq!(get_next_hop_home(1, 0, &ng), Some(0));
+}
+
+#[test]
+fn test_pearls_in_flight() {
+    let mut state = basic_state();
+    assert_eq!(count_pearls_in_flight(&state, 0), 0);
+
+    state.workers[2].desk.push(single_layer_pearl(12, "Red", 1));
+    assert_eq!(count_pearls_in_flight(&state, 0), 1);
+}
+
+#[test]
+fn test_rank_intake() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let ranked = rank_intake(
+        &state.workers[0],
+        &state,
+        &get_ability_map(),
+        &ng,
+        &basic_config(),
+    );
+
+    // The green pearl takes the gatekeeper 2 turns, while the blue pearl
+    // takes 2 turns at worker 2 plus 4 turns of passing
+    assert_eq!(ranked[0].pearl_id, 11);
+    assert_eq!(ranked[0].total_cost(), 2);
+    assert_eq!(ranked[1].pearl_id, 10);
+    assert_eq!(ranked[1].self_time, 10);
+    assert_eq!(ranked[1].total_cost(), 6);
+}
+
+#[test]
+fn test_hand_offs_follow_the_pass_rule() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let workers: HashMap<u32, &Worker> = state.workers.iter().map(|w| (w.id, w)).collect();
+    let blue = &state.workers[0].desk[0];
+
+    // Worker 1 takes 20 turns over the blue pearl, so passes it on to 2
+    let found = follow_hand_offs(blue, 1, &workers, &get_ability_map(), &ng, &basic_config());
+    assert_eq!(found, Some((2, 1)));
+
+    // Unless passing has to gain more than that
+    let config = Config {
+        strategy: StrategyConfig {
+            min_pass_gain: 20,
+            ..Default::default()
+        },
+        ..basic_config()
+    };
+    let found = follow_hand_offs(blue, 1, &workers, &get_ability_map(), &ng, &config);
+    assert_eq!(found, Some((1, 0)));
+}
+
+#[test]
+fn test_rank_intake_only_counts_on_hand_offs() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let config = Config {
+        strategy: StrategyConfig {
+            min_pass_gain: 20,
+            ..Default::default()
+        },
+        ..basic_config()
+    };
+    let ranked = rank_intake(&state.workers[0], &state, &get_ability_map(), &ng, &config);
+
+    // Worker 1 would keep the blue pearl rather than pass it to the Matrix,
+    // so the gatekeeper is better off nomming it itself
+    assert_eq!(ranked[1].pearl_id, 10);
+    assert_eq!(ranked[1].hop_costs, vec![(1, 22)]);
+    assert_eq!(ranked[1].total_cost(), 10);
+}
+
+#[test]
+fn test_gatekeeper_dispatches() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let pc = get_worker_pearl_counts(&state.workers);
+
+    let action = determine_gatekeeper_action(
+        &state,
+        &state.workers[0],
+        &pc,
+        &get_ability_map(),
+        &ng,
+        &basic_config(),
+    );
+
+    match action {
+        Some(ActionType::Pass(p)) => {
+            assert_eq!(p.pearl_id, 10);
+            assert_eq!(p.to_id, 1);
+        }
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_gatekeeper_dispatches_when_only_as_fast() {
+    let mut state = basic_state();
+    state.workers[0].flavor = String::from("General");
+    state.workers[0].desk = vec![single_layer_pearl(10, "Green", 6)];
+    state.workers[1].flavor = String::from("Matrix");
+    state.workers[2].flavor = String::from("Vector");
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let pc = get_worker_pearl_counts(&state.workers);
+    let decide = |state: &State| {
+        return determine_gatekeeper_action(
+            state,
+            &state.workers[0],
+            &pc,
+            &get_ability_map(),
+            &ng,
+            &basic_config(),
+        );
+    };
+
+    // The Vector takes 2 turns over the pearl and 4 more to get it there
+    // and back, the same 6 turns the gatekeeper would take
+    assert!(matches!(decide(&state), Some(ActionType::Nom(_))));
+
+    // With another pearl to get on with, handing it off is worth it
+    state.workers[0]
+        .desk
+        .push(single_layer_pearl(11, "Green", 3));
+    match decide(&state) {
+        Some(ActionType::Pass(p)) => assert_eq!(p.pearl_id, 10),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_gatekeeper_noms_when_full() {
+    let mut state = basic_state();
+    state.workers[2].desk.push(single_layer_pearl(12, "Red", 1));
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let pc = get_worker_pearl_counts(&state.workers);
+
+    let mut config = basic_config();
+    config.gatekeeper.max_in_flight = 1;
+
+    let action = determine_gatekeeper_action(
+        &state,
+        &state.workers[0],
+        &pc,
+        &get_ability_map(),
+        &ng,
+        &config,
+    );
+
+    match action {
+        Some(ActionType::Nom(n)) => assert_eq!(n.pearl_id, 11),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_gatekeeper_idle_with_finished_pearls() {
+    let mut state = basic_state();
+    state.workers[0].desk = vec![Pearl {
+        id: 5,
+        layers: vec![],
+    }];
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let pc: HashMap<u32, u32> = get_worker_pearl_counts(&state.workers);
+
+    let action = determine_gatekeeper_action(
+        &state,
+        &state.workers[0],
+        &pc,
+        &get_ability_map(),
+        &ng,
+        &basic_config(),
+    );
+
+    assert!(action.is_none());
+}
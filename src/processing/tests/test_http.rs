@@ -0,0 +1,162 @@
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use super::super::http::{HttpServer, Sessions};
+use super::super::server::ServerOptions;
+use super::super::strategy::{Strategy, Turn};
+use super::super::utils::get_ability_map;
+use crate::models::action::Actions;
+use crate::models::config::Config;
+use crate::models::state::PearlPaths;
+use crate::Pipeline;
+
+/// A strategy that fails on every turn, standing in for a bug in a planner
+struct PanickingStrategy;
+
+impl Strategy for PanickingStrategy {
+    fn name(&self) -> &str {
+        return "panicking";
+    }
+
+    fn decide(&mut self, _turn: &Turn, _pearl_paths: &mut PearlPaths) -> Actions {
+        panic!("Test Error: no plan");
+    }
+}
+
+fn actions_state() -> Value {
+    let line = include_str!("../../static_files/test_data/test_actions.txt");
+    return serde_json::from_str(line).unwrap();
+}
+
+fn sessions() -> Sessions {
+    return Sessions::new(Config::default(), get_ability_map(), None);
+}
+
+#[test]
+fn test_actions_endpoint() {
+    let mut sessions = sessions();
+    let body = json!({"session_id": "a", "state": actions_state()}).to_string();
+
+    let response = sessions.handle("POST", "/actions", &body);
+    assert_eq!(response.status, 200);
+
+    let actions: Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(actions["8"]["Pass"]["to_worker"], 9);
+    assert_eq!(sessions.session_count(), 1);
+
+    // A second game gets its own pipeline
+    sessions.handle("POST", "/actions", &body.replace("\"a\"", "\"b\""));
+    assert_eq!(sessions.session_count(), 2);
+
+    assert_eq!(sessions.handle("DELETE", "/sessions/a", "").status, 200);
+    assert_eq!(sessions.handle("DELETE", "/sessions/a", "").status, 404);
+    assert_eq!(sessions.session_count(), 1);
+}
+
+#[test]
+fn test_explain_endpoint() {
+    let mut sessions = sessions();
+    let body = json!({ "state": actions_state() }).to_string();
+
+    let response = sessions.handle("POST", "/explain", &body);
+    assert_eq!(response.status, 200);
+
+    let explanation: Value = serde_json::from_str(&response.body).unwrap();
+    assert!(explanation["workers"].as_array().unwrap().len() > 0);
+    assert_eq!(sessions.session_count(), 0);
+}
+
+#[test]
+fn test_bad_requests() {
+    let mut sessions = sessions();
+    let no_session = json!({ "state": actions_state() }).to_string();
+
+    assert_eq!(sessions.handle("POST", "/nowhere", "").status, 404);
+    assert_eq!(sessions.handle("GET", "/actions", "").status, 405);
+    assert_eq!(sessions.handle("POST", "/actions", "not json").status, 400);
+    assert_eq!(sessions.handle("POST", "/actions", &no_session).status, 400);
+
+    let mut state = actions_state();
+    state["neighbor_map"] = json!([[0, 999]]);
+    let invalid = json!({"session_id": "a", "state": state}).to_string();
+    let response = sessions.handle("POST", "/actions", &invalid);
+    assert_eq!(response.status, 422);
+    assert!(response.body.contains("999"));
+}
+
+#[test]
+fn test_ended_sessions_keep_their_metrics() {
+    let mut sessions = sessions();
+    let body = json!({"session_id": "a", "state": actions_state()}).to_string();
+
+    sessions.handle("POST", "/actions", &body);
+    sessions.handle("POST", "/actions", &body);
+    sessions.handle("POST", "/actions", &body.replace("\"a\"", "\"b\""));
+    assert_eq!(sessions.metrics().turns, 0);
+
+    sessions.handle("DELETE", "/sessions/a", "");
+    assert_eq!(sessions.metrics().turns, 2);
+
+    sessions.end_all();
+    assert_eq!(sessions.metrics().turns, 3);
+    assert_eq!(sessions.session_count(), 0);
+
+    // Idle sessions are ended the same way
+    let mut idle = Sessions::new(Config::default(), get_ability_map(), Some(Duration::ZERO));
+    idle.handle("POST", "/actions", &body);
+    idle.handle("POST", "/nowhere", "");
+    assert_eq!(idle.session_count(), 0);
+    assert_eq!(idle.metrics().turns, 1);
+}
+
+#[test]
+fn test_panicking_session() {
+    let mut sessions = sessions();
+    let pipeline = Pipeline::with_ability_map(Config::default(), get_ability_map())
+        .with_strategy(Box::new(PanickingStrategy));
+    sessions.open("bad", pipeline);
+
+    let bad = json!({"session_id": "bad", "state": actions_state()}).to_string();
+    let response = sessions.handle("POST", "/actions", &bad);
+    assert_eq!(response.status, 500);
+    assert_eq!(sessions.session_count(), 0);
+
+    // Other sessions carry on
+    let good = json!({"session_id": "good", "state": actions_state()}).to_string();
+    assert_eq!(sessions.handle("POST", "/actions", &good).status, 200);
+}
+
+#[test]
+fn test_http_server() {
+    let options = ServerOptions {
+        idle_timeout: None,
+        poll_interval: Duration::from_millis(10),
+    };
+    let mut server = HttpServer::bind("127.0.0.1:0", Config::default(), options).unwrap();
+    let addr = server.local_addr();
+    let shutdown = server.shutdown_flag();
+    let running = thread::spawn(move || server.run());
+
+    let body = json!({"session_id": "a", "state": actions_state()}).to_string();
+    let mut stream = TcpStream::connect(&addr).unwrap();
+    write!(
+        stream,
+        "POST /actions HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        addr,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("\"to_worker\":9"));
+
+    shutdown.store(true, Ordering::SeqCst);
+    running.join().unwrap().unwrap();
+}
@@ -0,0 +1,157 @@
+use proptest::prelude::*;
+use proptest::sample::Index;
+use std::collections::{HashMap, HashSet};
+
+use super::super::utils::{build_neighbor_graph, determine_actions, get_ability_map};
+use crate::models::ability_map::{COLORS, FLAVORS};
+use crate::models::action::{ActionType, Actions};
+use crate::models::config::Config;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+/// A pearl's layers as (color index, thickness), with no layers for a
+/// finished pearl
+type GenPearl = Vec<(usize, u32)>;
+/// A worker's flavor index and the pearls on their desk
+type GenWorker = (usize, Vec<GenPearl>);
+
+fn layers_strategy() -> impl Strategy<Value = GenPearl> {
+    return prop::collection::vec((0..COLORS.len(), 1u32..30), 0..4);
+}
+
+fn worker_strategy() -> impl Strategy<Value = GenWorker> {
+    return (
+        0..FLAVORS.len(),
+        prop::collection::vec(layers_strategy(), 0..4),
+    );
+}
+
+/// Builds a state with workers `0..n` and unique pearl IDs. Every worker
+/// after the first is joined to an earlier one, so the floor is connected
+/// and every pearl can find its way back to the gatekeeper.
+fn build_state(
+    workers: Vec<GenWorker>,
+    parents: Vec<Index>,
+    extra_edges: Vec<(Index, Index)>,
+) -> State {
+    let n = workers.len();
+    let mut next_pearl_id: u32 = 100;
+    let mut built: Vec<Worker> = Vec::new();
+
+    for (id, (flavor, desk)) in workers.into_iter().enumerate() {
+        let mut pearls: Vec<Pearl> = Vec::new();
+        for layers in desk {
+            pearls.push(Pearl {
+                id: next_pearl_id,
+                layers: layers
+                    .into_iter()
+                    .map(|(c, t)| Layer {
+                        color: COLORS[c].to_string(),
+                        thickness: t,
+                        original_thickness: None,
+                    })
+                    .collect(),
+            });
+            next_pearl_id += 1;
+        }
+
+        built.push(Worker {
+            id: id as u32,
+            flavor: FLAVORS[flavor].to_string(),
+            desk: pearls,
+        });
+    }
+
+    let mut neighbor_map: Vec<Vec<u32>> = Vec::new();
+    for (i, parent) in parents.iter().enumerate().skip(1) {
+        neighbor_map.push(vec![parent.index(i) as u32, i as u32]);
+    }
+    for (a, b) in extra_edges {
+        let (a, b) = (a.index(n), b.index(n));
+        if a != b {
+            neighbor_map.push(vec![a as u32, b as u32]);
+        }
+    }
+
+    return State {
+        workers: built,
+        neighbor_map: neighbor_map,
+        edges: vec![],
+        score: 0,
+    };
+}
+
+fn state_strategy() -> impl Strategy<Value = State> {
+    return (1usize..10).prop_flat_map(|n| {
+        return (
+            prop::collection::vec(worker_strategy(), n),
+            prop::collection::vec(any::<Index>(), n),
+            prop::collection::vec(any::<(Index, Index)>(), 0..n),
+        )
+            .prop_map(|(w, p, e)| build_state(w, p, e));
+    });
+}
+
+/// Checks a turn's actions against the rules the harness enforces,
+/// describing the first one broken.
+fn check_invariants(state: &State, actions: &Actions) -> Result<(), String> {
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let workers: HashMap<u32, &Worker> = state.workers.iter().map(|w| (w.id, w)).collect();
+    let mut filled: HashSet<u32> = HashSet::new();
+
+    for (id, action) in actions {
+        let (actor, pearl_id) = match action {
+            ActionType::Pass(p) => (p.from_id, p.pearl_id),
+            ActionType::Nom(n) => (n.nautiloid_id, n.pearl_id),
+        };
+
+        // Actions are keyed by worker, so each worker can only have one as
+        // long as the key is the worker acting
+        if actor != *id {
+            return Err(format!("action for {} is taken by {}", id, actor));
+        }
+
+        let worker = match workers.get(id) {
+            Some(w) => w,
+            None => return Err(format!("action for unknown worker {}", id)),
+        };
+        if worker.desk.is_empty() {
+            return Err(format!("worker {} acts with an empty desk", id));
+        }
+        if !worker.desk.iter().any(|p| p.id == pearl_id) {
+            return Err(format!(
+                "worker {} acts on pearl {} it doesn't hold",
+                id, pearl_id
+            ));
+        }
+
+        if let ActionType::Pass(p) = action {
+            if !graph[id].contains(&p.to_id) {
+                return Err(format!("worker {} passes to non-neighbor {}", id, p.to_id));
+            }
+
+            let receiver_empty = workers.get(&p.to_id).is_some_and(|w| w.desk.is_empty());
+            if receiver_empty && !filled.insert(p.to_id) {
+                return Err(format!("empty desk {} is sent two pearls", p.to_id));
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+proptest! {
+    #[test]
+    fn test_determine_actions_invariants(state in state_strategy()) {
+        let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+        let mut pearl_paths = HashMap::new();
+        let actions = determine_actions(
+            &state,
+            &get_ability_map(),
+            &graph,
+            &mut pearl_paths,
+            &Config::default(),
+        );
+
+        prop_assert_eq!(check_invariants(&state, &actions), Ok(()));
+    }
+}
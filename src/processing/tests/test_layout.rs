@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use super::super::layout::{advise_layout, layout_to_json, layout_to_text, FlavorChange};
+use super::super::scenario::{check_scenario, generate_scenarios, parse_color_weights};
+use super::super::utils::get_ability_map;
+use crate::models::config::{Config, ScenarioConfig};
+use crate::models::state::{State, Worker};
+
+/// `count` General workers in a line, with the gatekeeper at one end
+fn line_floor(count: u32) -> State {
+    return State {
+        workers: (0..count)
+            .map(|id| Worker {
+                id: id,
+                desk: vec![],
+                flavor: String::from("General"),
+            })
+            .collect(),
+        neighbor_map: (1..count).map(|id| vec![id - 1, id]).collect(),
+        edges: vec![],
+        score: 0,
+    };
+}
+
+/// A config that plays a single short game of single layer pearls
+fn scenario_config(color: &str, pearls: u32, turns: u32) -> Config {
+    let mut colors: HashMap<String, f64> = HashMap::new();
+    colors.insert(String::from(color), 1.0);
+
+    return Config {
+        scenario: ScenarioConfig {
+            pearls: pearls,
+            layers: [1, 1],
+            thickness: [10, 10],
+            colors: colors,
+            samples: 1,
+            turns: turns,
+            seed: 7,
+        },
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_generate_scenarios() {
+    let config = scenario_config("Red", 4, 10);
+    let floor = line_floor(3);
+    let games = generate_scenarios(&floor, &config.scenario, 0);
+
+    assert_eq!(games.len(), 1);
+    let pearls = &games[0].workers[0].desk;
+    assert_eq!(pearls.len(), 4);
+    assert!(pearls
+        .iter()
+        .all(|p| p.layers.len() == 1 && p.layers[0].color == "Red"));
+    assert!(games[0].workers[1..].iter().all(|w| w.desk.is_empty()));
+
+    // The same seed always gives the same pearls
+    let again = generate_scenarios(&floor, &config.scenario, 0);
+    assert_eq!(
+        serde_json::to_string(&games).unwrap(),
+        serde_json::to_string(&again).unwrap()
+    );
+}
+
+#[test]
+fn test_color_weights() {
+    let weights = parse_color_weights("Red=2, Blue=0.5").unwrap();
+    assert_eq!(weights["Red"], 2.0);
+    assert_eq!(weights["Blue"], 0.5);
+    assert!(parse_color_weights("Red").is_err());
+    assert!(parse_color_weights("Red=lots").is_err());
+
+    let mut scenario = ScenarioConfig::default();
+    assert!(check_scenario(&scenario).is_empty());
+
+    scenario.colors = parse_color_weights("Purple=1,Red=0").unwrap();
+    scenario.layers = [2, 1];
+    let problems = check_scenario(&scenario);
+    assert_eq!(problems.len(), 2);
+    assert!(problems[1].contains("Purple"));
+}
+
+#[test]
+fn test_advice_finishes_sooner() {
+    // A lone General gatekeeper takes 10 turns over each Blue pearl, where
+    // a Matrix takes 1
+    let advice = advise_layout(
+        &line_floor(1),
+        &get_ability_map(),
+        &scenario_config("Blue", 2, 50),
+    )
+    .unwrap();
+
+    assert!(advice.exhaustive);
+    assert_eq!(advice.assignments_tried, 3);
+    assert_eq!(advice.score_gain, 0.0);
+    assert!(advice.suggested.turns < advice.current.turns);
+    assert_eq!(
+        advice.changes,
+        vec![FlavorChange {
+            worker_id: 0,
+            from: String::from("General"),
+            to: String::from("Matrix"),
+        }]
+    );
+}
+
+#[test]
+fn test_advice_scores_more() {
+    // Only a Vector gets through all four Green pearls in time
+    let advice = advise_layout(
+        &line_floor(1),
+        &get_ability_map(),
+        &scenario_config("Green", 4, 12),
+    )
+    .unwrap();
+
+    assert!(advice.score_gain > 0.0);
+    assert_eq!(advice.flavors[0].flavor, "Vector");
+
+    let text = layout_to_text(&advice);
+    assert!(text.contains("worker 0: General -> Vector"));
+    let json: serde_json::Value = serde_json::from_str(&layout_to_json(&advice)).unwrap();
+    assert_eq!(json["changes"][0]["to"], "Vector");
+}
+
+#[test]
+fn test_large_floors_change_one_worker_at_a_time() {
+    let advice = advise_layout(
+        &line_floor(7),
+        &get_ability_map(),
+        &scenario_config("Blue", 2, 40),
+    )
+    .unwrap();
+
+    assert!(!advice.exhaustive);
+    assert!(advice.suggested.score >= advice.current.score);
+    assert!(advice.suggested.turns < advice.current.turns);
+}
+
+#[test]
+fn test_advice_rejects_bad_scenarios() {
+    let mut config = scenario_config("Blue", 2, 40);
+    config.scenario.samples = 0;
+
+    assert!(advise_layout(&line_floor(2), &get_ability_map(), &config).is_err());
+}
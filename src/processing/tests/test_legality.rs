@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use super::super::legality::enforce_legality;
+use super::super::strategy::{Strategy, Turn};
+use super::super::utils::{build_neighbor_graph, get_ability_map, make_nom, make_pass};
+use super::{finished_pearl, pearl, worker};
+use crate::models::action::{ActionType, Actions};
+use crate::models::config::Config;
+use crate::models::state::{NeighborGraph, PearlPaths, State};
+use crate::Pipeline;
+
+/// The gatekeeper 0 with an empty desk, joined to 1 and 3, with 2 beyond 1.
+/// Worker 2 holds a finished pearl, the others unfinished ones.
+fn legality_state() -> State {
+    return State {
+        workers: vec![
+            worker(0, "General", vec![]),
+            worker(1, "General", vec![pearl(10, "Green", 12)]),
+            worker(2, "General", vec![finished_pearl(20)]),
+            worker(3, "General", vec![pearl(30, "Green", 12)]),
+        ],
+        neighbor_map: vec![vec![0, 1], vec![1, 2], vec![0, 3]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+fn check(state: &State, actions: Actions) -> (Actions, usize) {
+    let graph: NeighborGraph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let (legal, issues) = enforce_legality(
+        state,
+        &get_ability_map(),
+        &graph,
+        &Config::default(),
+        actions,
+    );
+    return (legal, issues.len());
+}
+
+#[test]
+fn test_legal_actions_are_kept() {
+    let actions: Actions = HashMap::from([
+        (1, make_nom(1, 10)),
+        (2, make_pass(2, 20, 1)),
+        (3, make_pass(3, 30, 0)),
+    ]);
+
+    let (legal, issues) = check(&legality_state(), actions.clone());
+    assert_eq!(legal, actions);
+    assert_eq!(issues, 0);
+}
+
+#[test]
+fn test_pearl_not_on_desk() {
+    let actions: Actions = HashMap::from([(1, make_nom(1, 99)), (3, make_pass(3, 10, 0))]);
+
+    let (legal, issues) = check(&legality_state(), actions);
+    assert_eq!(issues, 2);
+    assert_eq!(legal, HashMap::from([(1, make_nom(1, 10))]));
+}
+
+#[test]
+fn test_pass_to_non_neighbor() {
+    let actions: Actions = HashMap::from([(1, make_pass(1, 10, 3)), (2, make_pass(2, 20, 0))]);
+
+    let (legal, issues) = check(&legality_state(), actions);
+    assert_eq!(issues, 2);
+    assert_eq!(legal[&1], make_nom(1, 10));
+    assert_eq!(legal[&2], make_pass(2, 20, 1));
+}
+
+#[test]
+fn test_nom_of_finished_pearl() {
+    let actions: Actions = HashMap::from([(2, make_nom(2, 20))]);
+
+    let (legal, issues) = check(&legality_state(), actions);
+    assert_eq!(issues, 1);
+    assert_eq!(legal, HashMap::from([(2, make_pass(2, 20, 1))]));
+}
+
+#[test]
+fn test_two_passes_to_an_empty_desk() {
+    let actions: Actions = HashMap::from([(1, make_pass(1, 10, 0)), (3, make_pass(3, 30, 0))]);
+
+    let (legal, issues) = check(&legality_state(), actions);
+    assert_eq!(issues, 1);
+    assert_eq!(legal[&1], make_pass(1, 10, 0));
+    assert_eq!(legal[&3], make_nom(3, 30));
+}
+
+/// Passes every worker's first pearl to worker 3, whether or not that's a
+/// neighbor, recording the step for the unfinished ones
+struct Reckless;
+
+impl Strategy for Reckless {
+    fn name(&self) -> &str {
+        return "reckless";
+    }
+
+    fn decide(&mut self, turn: &Turn, pearl_paths: &mut PearlPaths) -> Actions {
+        let mut actions = Actions::new();
+        for w in &turn.state.workers {
+            if let Some(p) = w.desk.first() {
+                actions.insert(w.id, make_pass(w.id, p.id, 3));
+                if !p.layers.is_empty() {
+                    pearl_paths.entry(p.id).or_default().push(w.id);
+                }
+            }
+        }
+        return actions;
+    }
+}
+
+#[test]
+fn test_pipeline_repairs_actions() {
+    let mut pipeline = Pipeline::new(Config::default())
+        .unwrap()
+        .with_strategy(Box::new(Reckless));
+
+    let actions = pipeline.step(&legality_state());
+    assert_eq!(actions[&1], make_nom(1, 10));
+    assert_eq!(actions[&2], make_pass(2, 20, 1));
+    assert_eq!(actions[&3], make_nom(3, 30));
+    assert_eq!(pipeline.legality_issues().len(), 3);
+
+    // None of the passes were kept, so the unfinished pearls forget their
+    // steps and the finished one keeps the hop it was sent to
+    let paths = &pipeline.snapshot().pearl_paths;
+    assert!(paths[&10].is_empty());
+    assert!(paths[&30].is_empty());
+    assert_eq!(paths[&20], vec![3]);
+    assert!(matches!(
+        pipeline.legality_issues()[0].action,
+        ActionType::Pass(_)
+    ));
+
+    pipeline.reset();
+    assert!(pipeline.legality_issues().is_empty());
+}
+
+/// Sends unfinished pearls to the gatekeeper, and finished ones back along
+/// their paths, as the greedy strategy does
+struct Retrace;
+
+impl Strategy for Retrace {
+    fn name(&self) -> &str {
+        return "retrace";
+    }
+
+    fn decide(&mut self, turn: &Turn, pearl_paths: &mut PearlPaths) -> Actions {
+        let mut actions = Actions::new();
+        for w in &turn.state.workers {
+            if let Some(p) = w.desk.first() {
+                let to_id = if p.layers.is_empty() {
+                    pearl_paths.get_mut(&p.id).and_then(|path| path.pop())
+                } else {
+                    pearl_paths.entry(p.id).or_default().push(w.id);
+                    Some(0)
+                };
+                if let Some(to_id) = to_id {
+                    actions.insert(w.id, make_pass(w.id, p.id, to_id));
+                }
+            }
+        }
+        return actions;
+    }
+}
+
+#[test]
+fn test_dropped_pass_keeps_the_way_home() {
+    let mut pipeline = Pipeline::new(Config::default())
+        .unwrap()
+        .with_strategy(Box::new(Retrace));
+
+    let mut state = legality_state();
+    state.workers[1].desk.clear();
+    state.workers[2].desk.clear();
+    pipeline.step(&state);
+    assert_eq!(pipeline.snapshot().pearl_paths[&30], vec![3]);
+
+    // Pearl 30 turns up finished on worker 1, which is not next to worker 3,
+    // so retracing its path is illegal and the hop has to stay on it
+    state.workers[3].desk.clear();
+    state.workers[1].desk.push(finished_pearl(30));
+    let actions = pipeline.step(&state);
+    assert_eq!(pipeline.legality_issues().len(), 1);
+    assert_ne!(actions[&1], make_pass(1, 30, 3));
+    assert_eq!(pipeline.snapshot().pearl_paths[&30], vec![3]);
+}
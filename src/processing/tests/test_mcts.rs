@@ -0,0 +1,190 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::super::mcts::MctsStrategy;
+use super::super::scenario::sample_arrivals;
+use super::super::simulator::{apply_turn, simulate, InTransit};
+use super::super::solver::SolverFixture;
+use super::super::strategy::{Anytime, Deadline, Strategy, Turn};
+use super::super::transfer::TransferCosts;
+use super::super::utils::{build_state_graph, get_ability_map};
+use super::test_solver::FIXTURES;
+use crate::models::action::Actions;
+use crate::models::config::{Config, MctsConfig, Planner, Rollout};
+use crate::models::state::State;
+use crate::Pipeline;
+
+/// A game the exact solver beats greedy on, by 8 turns to 10
+fn fixture_state() -> State {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES);
+    let contents = fs::read_to_string(path).unwrap();
+    let fixture: SolverFixture = serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+    return fixture.state;
+}
+
+fn mcts_config() -> Config {
+    return Config {
+        planner: Planner::Mcts,
+        mcts: MctsConfig {
+            iterations: 60,
+            depth: 20,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+}
+
+/// Plays a game through a pipeline until every pearl is delivered or
+/// `max_turns` pass, returning how many turns it took and the final state
+fn play(state: &State, config: &Config, max_turns: u32) -> (u32, State) {
+    let mut pipeline = Pipeline::new(config.clone()).unwrap();
+    let neighbor_graph = build_state_graph(state, &config.transfer);
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(&neighbor_graph);
+    let mut in_transit: Vec<InTransit> = Vec::new();
+    let mut current = state.clone();
+    let mut turns = 0;
+
+    while turns < max_turns
+        && (!in_transit.is_empty() || current.workers.iter().any(|w| !w.desk.is_empty()))
+    {
+        let actions = pipeline.step(&current);
+        assert!(pipeline.legality_issues().is_empty());
+        current = apply_turn(
+            &current,
+            &actions,
+            &get_ability_map(),
+            config.gatekeeper_id,
+            &transfer_costs,
+            &mut in_transit,
+            &mut HashMap::new(),
+        );
+        turns += 1;
+    }
+
+    return (turns, current);
+}
+
+/// Asks the strategy for the first turn of a game directly
+fn decide_first_turn(config: &Config, deadline: &Deadline) -> Anytime {
+    let state = fixture_state();
+    let neighbor_graph = build_state_graph(&state, &config.transfer);
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(&neighbor_graph);
+    let turn = Turn {
+        number: 0,
+        state: &state,
+        ability_map: &get_ability_map(),
+        neighbor_graph: &neighbor_graph,
+        transfer_costs: &transfer_costs,
+        config: config,
+    };
+
+    return MctsStrategy.decide_within(&turn, &mut HashMap::new(), deadline);
+}
+
+#[test]
+fn test_mcts_beats_greedy() {
+    let config = mcts_config();
+    let (turns, end) = play(&fixture_state(), &config, 100);
+    let (greedy, _) = simulate(&fixture_state(), &get_ability_map(), &config, 100);
+
+    assert_eq!(end.score, 3);
+    assert_eq!(greedy.len(), 10);
+    assert!(turns < 10);
+}
+
+#[test]
+fn test_mcts_is_deterministic() {
+    let config = mcts_config();
+    let first = decide_first_turn(&config, &Deadline::unlimited());
+    let again = decide_first_turn(&config, &Deadline::unlimited());
+
+    assert!(matches!(first, Anytime::Complete(_)));
+    assert_eq!(first, again);
+}
+
+#[test]
+fn test_random_rollouts() {
+    let mut config = mcts_config();
+    config.mcts.rollout = Rollout::Random;
+    let (turns, end) = play(&fixture_state(), &config, 100);
+
+    assert_eq!(end.score, 3);
+    assert!(turns < 100);
+}
+
+#[test]
+fn test_arrivals_stay_legal() {
+    let mut config = mcts_config();
+    config.mcts.arrival_rate = 0.5;
+    config.mcts.iterations = 30;
+
+    // The pearls imagined during the search never make it into the actions
+    let (_, end) = play(&fixture_state(), &config, 100);
+    assert_eq!(end.score, 3);
+}
+
+#[test]
+fn test_mcts_deadline() {
+    let config = mcts_config();
+    let expired = Deadline::after(Duration::ZERO);
+
+    assert_eq!(decide_first_turn(&config, &expired), Anytime::Nothing);
+}
+
+#[test]
+fn test_mcts_without_playouts() {
+    let mut config = mcts_config();
+    config.mcts.iterations = 0;
+
+    // With no playouts there is nothing better than greedy to offer
+    let decided = decide_first_turn(&config, &Deadline::unlimited());
+    let (greedy, _) = simulate(&fixture_state(), &get_ability_map(), &config, 1);
+    assert_eq!(decided, Anytime::Complete(greedy[0].actions.clone()));
+
+    let mut pipeline = Pipeline::new(config).unwrap();
+    assert!(!pipeline.step(&fixture_state()).is_empty());
+    assert_eq!(pipeline.deadline_stats().best_so_far, 0);
+}
+
+#[test]
+fn test_mcts_with_the_largest_pearl_id() {
+    let mut state = fixture_state();
+    let pearl = state
+        .workers
+        .iter_mut()
+        .flat_map(|w| w.desk.iter_mut())
+        .next()
+        .unwrap();
+    pearl.id = u32::MAX;
+
+    // Pearls imagined arriving during the search are numbered past it
+    let mut config = mcts_config();
+    config.mcts.arrival_rate = 2.0;
+    let mut pipeline = Pipeline::new(config).unwrap();
+    assert!(!pipeline.step(&state).is_empty());
+}
+
+#[test]
+fn test_sample_arrivals() {
+    let mut rng = StdRng::seed_from_u64(3);
+    assert_eq!(sample_arrivals(0.0, &mut rng), 0);
+
+    let total: u32 = (0..2000).map(|_| sample_arrivals(2.0, &mut rng)).sum();
+    let mean = total as f64 / 2000.0;
+    assert!(mean > 1.8 && mean < 2.2, "mean {}", mean);
+}
+
+#[test]
+fn test_planner_picks_the_strategy() {
+    let pipeline = Pipeline::new(mcts_config()).unwrap();
+    assert_eq!(pipeline.snapshot().strategy, "mcts");
+
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+    assert_eq!(pipeline.snapshot().strategy, "greedy");
+    let actions: Actions = pipeline.step(&fixture_state());
+    assert_eq!(actions.len(), 1);
+}
@@ -0,0 +1,121 @@
+use serde_json::Value;
+use std::fs;
+use std::time::Duration;
+
+use super::super::metrics::{write_metrics, Histogram, Metrics};
+use crate::models::config::{Config, MetricsConfig};
+use crate::models::state::State;
+use crate::Pipeline;
+
+fn actions_state() -> State {
+    let line = include_str!("../../static_files/test_data/test_actions.txt");
+    return serde_json::from_str(line).unwrap();
+}
+
+#[test]
+fn test_histogram() {
+    let mut hist = Histogram::new(&[1.0, 5.0]);
+    hist.observe(0.5);
+    hist.observe(1.0);
+    hist.observe(3.0);
+    hist.observe(10.0);
+
+    assert_eq!(hist.count(), 4);
+    assert_eq!(hist.sum(), 14.5);
+    assert_eq!(hist.mean(), 3.625);
+
+    let mut other = Histogram::new(&[1.0, 5.0]);
+    other.observe(2.0);
+    hist.merge(&other);
+    assert_eq!(hist.count(), 5);
+
+    assert_eq!(Histogram::new(&[1.0]).mean(), 0.0);
+}
+
+#[test]
+fn test_pipeline_records_metrics() {
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+    let actions = pipeline.step(&actions_state());
+
+    let metrics = pipeline.metrics();
+    assert_eq!(metrics.turns, 1);
+    assert_eq!(metrics.passes + metrics.noms, actions.len() as u64);
+    assert_eq!(metrics.passes, 1);
+    assert_eq!(metrics.turn_latency.count(), 1);
+
+    // The one pearl passed is a single hop from the worker holding it
+    assert_eq!(metrics.hops_per_pearl.count(), 1);
+    assert_eq!(metrics.hops_per_pearl.mean(), 1.0);
+
+    let idle = actions_state()
+        .workers
+        .iter()
+        .filter(|w| w.desk.is_empty())
+        .count();
+    assert_eq!(metrics.idle_workers.sum(), idle as f64);
+}
+
+#[test]
+fn test_metrics_json() {
+    let mut metrics = Metrics::new();
+    let state = actions_state();
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+    let actions = pipeline.step(&state);
+    metrics.record_turn(
+        &state,
+        &actions,
+        &Default::default(),
+        0,
+        Duration::from_millis(2),
+    );
+    metrics.merge(&metrics.clone());
+
+    let value: Value = serde_json::from_str(&metrics.to_json()).unwrap();
+    assert_eq!(value["turns"], 2);
+    assert_eq!(value["actions"]["pass"], 2);
+    assert_eq!(value["turn_latency_seconds"]["count"], 2);
+
+    let buckets = value["turn_latency_seconds"]["buckets"].as_array().unwrap();
+    assert_eq!(buckets.last().unwrap()["le"], "+Inf");
+    assert_eq!(buckets.last().unwrap()["count"], 2);
+}
+
+#[test]
+fn test_metrics_prometheus() {
+    let mut metrics = Metrics::new();
+    metrics.record_turn(
+        &actions_state(),
+        &Default::default(),
+        &Default::default(),
+        0,
+        Duration::from_millis(2),
+    );
+    let text = metrics.to_prometheus();
+
+    assert!(text.contains("# TYPE atlantis_turns_total counter\natlantis_turns_total 1\n"));
+    assert!(text.contains("atlantis_actions_total{kind=\"nom\"} 0\n"));
+    assert!(text.contains("atlantis_turn_latency_seconds_bucket{le=\"0.001\"} 0\n"));
+    assert!(text.contains("atlantis_turn_latency_seconds_bucket{le=\"0.005\"} 1\n"));
+    assert!(text.contains("atlantis_turn_latency_seconds_bucket{le=\"+Inf\"} 1\n"));
+    assert!(text.contains("atlantis_turn_latency_seconds_count 1\n"));
+}
+
+#[test]
+fn test_write_metrics() {
+    let dir = std::env::temp_dir();
+    let json_path = dir.join("atlantis_metrics.json");
+    let prom_path = dir.join("atlantis_metrics.prom");
+    let config = MetricsConfig {
+        json_path: Some(json_path.to_str().unwrap().to_string()),
+        prometheus_path: Some(prom_path.to_str().unwrap().to_string()),
+    };
+
+    write_metrics(&Metrics::new(), &config).unwrap();
+    let json = fs::read_to_string(&json_path).unwrap();
+    let prom = fs::read_to_string(&prom_path).unwrap();
+    fs::remove_file(&json_path).unwrap();
+    fs::remove_file(&prom_path).unwrap();
+
+    assert!(json.starts_with("{"));
+    assert!(prom.contains("atlantis_turns_total 0"));
+}
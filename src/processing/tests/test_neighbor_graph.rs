@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use super::super::gatekeeper::{get_distances_home, get_next_hop_home, get_next_hops_home};
+use super::super::render::render_state_dot;
+use super::super::simulator::{apply_turn, simulate, InTransit};
+use super::super::transfer::TransferCosts;
+use super::super::utils::{
+    build_neighbor_graph, build_state_graph, determine_actions, get_ability_map, make_nom,
+    make_pass,
+};
+use super::super::validate::validate_state;
+use super::pearl;
+use crate::models::action::ActionType;
+use crate::models::config::{Config, TransferConfig};
+use crate::models::state::{Edge, Pearl, PearlPaths, State, Worker};
+
+fn edge(from: u32, to: u32, cost: Option<u32>, capacity: Option<u32>, one_way: bool) -> Edge {
+    return Edge {
+        workers: [from, to],
+        cost: cost,
+        capacity: capacity,
+        one_way: one_way,
+    };
+}
+
+/// Three General workers with empty desks, joined however the test says
+fn triangle_state(neighbor_map: Vec<Vec<u32>>, edges: Vec<Edge>) -> State {
+    return State {
+        workers: (0..3)
+            .map(|id| Worker {
+                id: id,
+                desk: vec![],
+                flavor: String::from("General"),
+            })
+            .collect(),
+        neighbor_map: neighbor_map,
+        edges: edges,
+        score: 0,
+    };
+}
+
+#[test]
+fn test_edges_from_json() {
+    let state: State = serde_json::from_str(
+        r#"{
+            "workers": [
+                {"id": 0, "flavor": "General", "desk": []},
+                {"id": 1, "flavor": "General", "desk": []}
+            ],
+            "neighbor_map": [],
+            "edges": [{"workers": [0, 1], "cost": 2, "one_way": true}],
+            "score": 0
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(state.edges, vec![edge(0, 1, Some(2), None, true)]);
+
+    let graph = build_state_graph(&state, &TransferConfig::default());
+    assert!(graph.has_edge(0, 1));
+    assert!(!graph.has_edge(1, 0));
+    assert_eq!(graph.cost(0, 1), 2);
+
+    // States without edges read and write just as they always have
+    let plain = triangle_state(vec![vec![0, 1]], vec![]);
+    let json = serde_json::to_string(&plain).unwrap();
+    assert!(!json.contains("edges"));
+}
+
+#[test]
+fn test_undirected_graph_is_unchanged() {
+    let state = triangle_state(vec![vec![0, 1], vec![1, 2]], vec![]);
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+
+    assert_eq!(graph[&1], vec![0, 2]);
+    assert!(graph.has_edge(2, 1));
+    assert_eq!(graph.cost(1, 2), 0);
+    assert_eq!(graph.incoming(1), vec![0, 2]);
+}
+
+#[test]
+fn test_edges_shape_the_graph() {
+    let state = triangle_state(
+        vec![vec![0, 1], vec![1, 2]],
+        vec![
+            edge(1, 2, None, None, true),
+            edge(0, 1, None, Some(0), false),
+            edge(2, 0, Some(3), Some(1), false),
+        ],
+    );
+    let graph = build_state_graph(&state, &TransferConfig::default());
+
+    // 1 -> 2 only goes one way, 0 - 1 is closed and 2 - 0 is new
+    assert_eq!(graph[&0], vec![2]);
+    assert_eq!(graph[&1], vec![2]);
+    assert_eq!(graph[&2], vec![0]);
+    assert_eq!(graph.capacity(0, 2), Some(1));
+    assert_eq!(graph.cost(2, 0), 3);
+}
+
+#[test]
+fn test_config_costs_fill_in_for_edges() {
+    let state = triangle_state(
+        vec![vec![0, 1], vec![1, 2]],
+        vec![edge(1, 2, Some(4), None, false)],
+    );
+    let graph = build_state_graph(
+        &state,
+        &TransferConfig {
+            cost: 1,
+            edges: vec![],
+        },
+    );
+
+    assert_eq!(graph.cost(0, 1), 1);
+    assert_eq!(graph.cost(2, 1), 4);
+}
+
+#[test]
+fn test_routes_follow_weights() {
+    // Going straight to 1 costs 5 turns in transit, going round by 2 doesn't
+    let state = triangle_state(
+        vec![vec![0, 1], vec![0, 2], vec![2, 1]],
+        vec![edge(0, 1, Some(5), None, false)],
+    );
+    let graph = build_state_graph(&state, &TransferConfig::default());
+    let home = get_distances_home(0, &graph);
+
+    assert_eq!(home[&1], 2);
+    assert_eq!(home[&2], 1);
+    assert_eq!(get_next_hops_home(0, &graph)[&1], 2);
+    assert_eq!(get_next_hop_home(1, 0, &graph), Some(2));
+}
+
+#[test]
+fn test_routes_follow_direction() {
+    // A one-way ring, 0 -> 1 -> 2 -> 0
+    let state = triangle_state(
+        vec![],
+        vec![
+            edge(0, 1, None, None, true),
+            edge(1, 2, None, None, true),
+            edge(2, 0, None, None, true),
+        ],
+    );
+    let graph = build_state_graph(&state, &TransferConfig::default());
+    let home = get_distances_home(0, &graph);
+
+    assert_eq!(home[&1], 2);
+    assert_eq!(home[&2], 1);
+    assert_eq!(get_next_hop_home(1, 0, &graph), Some(2));
+
+    let hops = get_next_hops_home(0, &graph);
+    assert_eq!(hops[&1], 2);
+    assert_eq!(hops[&2], 0);
+    assert_eq!(graph.incoming(0), [2]);
+}
+
+#[test]
+fn test_incoming_follows_removed_edges() {
+    let state = triangle_state(
+        vec![vec![0, 1], vec![1, 2], vec![0, 2]],
+        vec![edge(2, 1, None, None, true)],
+    );
+    let graph = build_state_graph(&state, &TransferConfig::default());
+
+    // 1 can no longer pass to 2, but 2 can still pass to 1
+    assert_eq!(graph.incoming(1), [0, 2]);
+    assert_eq!(graph.incoming(2), [0]);
+}
+
+#[test]
+fn test_finished_pearl_goes_on_round_one_way_ring() {
+    let mut state = triangle_state(
+        vec![],
+        vec![
+            edge(0, 1, None, None, true),
+            edge(1, 2, None, None, true),
+            edge(2, 0, None, None, true),
+        ],
+    );
+    state.workers[1].desk.push(Pearl {
+        id: 5,
+        layers: vec![],
+    });
+    let graph = build_state_graph(&state, &TransferConfig::default());
+
+    // The pearl came from the gatekeeper, but can't go back that way
+    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+    pearl_paths.insert(5, vec![0]);
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut pearl_paths,
+        &Config::default(),
+    );
+
+    assert_eq!(actions[&1], make_pass(1, 5, 2));
+    assert!(!pearl_paths.contains_key(&5));
+}
+
+#[test]
+fn test_edge_cost_overrides_config() {
+    // A General worker holding a Blue pearl, next to a Matrix gatekeeper
+    let mut state = triangle_state(vec![vec![0, 1]], vec![]);
+    state.workers[0].desk.push(pearl(7, "Blue", 20));
+    state.workers[1].flavor = String::from("Matrix");
+    let config = Config {
+        gatekeeper_id: 1,
+        ..Default::default()
+    };
+
+    let graph = build_state_graph(&state, &config.transfer);
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut HashMap::new(),
+        &config,
+    );
+    assert_eq!(actions[&0], make_pass(0, 7, 1));
+
+    state.edges.push(edge(0, 1, Some(18), None, false));
+    let graph = build_state_graph(&state, &config.transfer);
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut HashMap::new(),
+        &config,
+    );
+    assert_eq!(actions[&0], make_nom(0, 7));
+}
+
+#[test]
+fn test_simulator_respects_capacity() {
+    let mut state = triangle_state(vec![], vec![edge(0, 1, Some(2), Some(1), false)]);
+    state.workers[0].desk = vec![pearl(1, "Red", 5), pearl(2, "Red", 5)];
+    let graph = build_state_graph(&state, &TransferConfig::default());
+    let costs = TransferCosts::default().with_graph(&graph);
+    let mut in_transit: Vec<InTransit> = Vec::new();
+    let mut pearl_paths: PearlPaths = HashMap::new();
+
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(0, make_pass(0, 1, 1));
+    pearl_paths.insert(1, vec![0]);
+    let next = apply_turn(
+        &state,
+        &acts,
+        &get_ability_map(),
+        2,
+        &costs,
+        &mut in_transit,
+        &mut pearl_paths,
+    );
+    assert_eq!(in_transit.len(), 1);
+    assert_eq!(pearl_paths[&1], vec![0]);
+
+    // The edge is full, so the second pearl stays put, and forgets the step
+    // deciding the pass added to its path
+    acts.insert(0, make_pass(0, 2, 1));
+    pearl_paths.insert(2, vec![0]);
+    let next = apply_turn(
+        &next,
+        &acts,
+        &get_ability_map(),
+        2,
+        &costs,
+        &mut in_transit,
+        &mut pearl_paths,
+    );
+    assert_eq!(in_transit.len(), 1);
+    assert_eq!(next.workers[0].desk[0].id, 2);
+    assert!(pearl_paths[&2].is_empty());
+}
+
+#[test]
+fn test_held_back_finished_pearl_keeps_its_path() {
+    let mut state = triangle_state(vec![], vec![edge(1, 0, Some(2), Some(1), false)]);
+    state.workers[1].desk = vec![pearl(1, "Red", 5), pearl(2, "Red", 5)];
+    state.workers[1].desk[1].layers.clear();
+    let graph = build_state_graph(&state, &TransferConfig::default());
+    let costs = TransferCosts::default().with_graph(&graph);
+    let mut in_transit: Vec<InTransit> = Vec::new();
+    let mut pearl_paths: PearlPaths = HashMap::new();
+
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(1, make_pass(1, 1, 0));
+    let next = apply_turn(
+        &state,
+        &acts,
+        &get_ability_map(),
+        2,
+        &costs,
+        &mut in_transit,
+        &mut pearl_paths,
+    );
+
+    // Returning finished pearl 2 popped worker 0 off its path, but the edge
+    // back is full, so it still has worker 0 to go back through
+    acts.insert(1, make_pass(1, 2, 0));
+    pearl_paths.insert(2, vec![]);
+    let next = apply_turn(
+        &next,
+        &acts,
+        &get_ability_map(),
+        2,
+        &costs,
+        &mut in_transit,
+        &mut pearl_paths,
+    );
+    assert_eq!(next.workers[1].desk[0].id, 2);
+    assert_eq!(pearl_paths[&2], vec![0]);
+}
+
+#[test]
+fn test_validate_edge_workers() {
+    let state = triangle_state(vec![], vec![edge(0, 9, None, None, false)]);
+    let problems = validate_state(&state, 0);
+
+    assert_eq!(problems, vec!["edge [0, 9] names unknown worker 9"]);
+}
+
+#[test]
+fn test_render_edges() {
+    let state = triangle_state(
+        vec![vec![0, 1], vec![1, 2]],
+        vec![edge(1, 2, Some(3), None, true)],
+    );
+    let dot = render_state_dot(&state, 0);
+
+    assert!(dot.contains("0 -- 1;"));
+    assert!(dot.contains("1 -- 2 [label=\"cost 3\", dir=forward];"));
+    assert_eq!(dot.matches("1 -- 2").count(), 1);
+}
+
+#[test]
+fn test_simulate_with_a_full_edge() {
+    // The gatekeeper hands its pearls to a faster Vector down a slow edge
+    // that only carries one at a time, so some hand-offs are held back
+    let mut state = triangle_state(vec![vec![0, 2]], vec![edge(0, 1, Some(3), Some(1), false)]);
+    state.workers[1].flavor = String::from("Vector");
+    state.workers[0].desk = (1..5).map(|id| pearl(id, "Green", 40)).collect();
+
+    let (turns, end) = simulate(&state, &get_ability_map(), &Config::default(), 200);
+
+    let held = turns.windows(2).any(|pair| {
+        pair[0].actions.values().any(|a| match a {
+            ActionType::Pass(p) => pair[1].state.workers[p.from_id as usize]
+                .desk
+                .iter()
+                .any(|d| d.id == p.pearl_id),
+            ActionType::Nom(_) => false,
+        })
+    });
+    assert!(held);
+    assert_eq!(end.score, 4);
+}
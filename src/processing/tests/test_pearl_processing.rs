@@ -1,15 +1,287 @@
-/// Tests in this module will test the full range of the pipeline.
-/// This will read sample versions of inputs (taken from actual
-/// `single-run` outputs), deserialize them, then run them through
-/// the pipeline.
-///
-/// We can then check that each of the expected instructions is in
-/// the output. We can't just compare an entire raw string, since
-/// sometimes the action strings get constructed (and hence
-/// printed) in different orders.
+//! Tests in this module will test the full range of the pipeline.
+//! This will read sample versions of inputs (taken from actual
+//! `single-run` outputs), deserialize them, then run them through
+//! the pipeline.
+//!
+//! We can then check that each of the expected instructions is in
+//! the output. We can't just compare an entire raw string, since
+//! sometimes the action strings get constructed (and hence
+//! printed) in different orders.
+
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::models::action::{ActionType, Actions};
+use crate::models::config::Config;
+use crate::models::state::{PearlPaths, State};
+use crate::processing::strategy::{Anytime, Deadline, Strategy, Turn};
+use crate::processing::utils::get_action_string;
+use crate::Pipeline;
+
+/// The directory holding the golden fixtures. Each `<name>.txt` holds a
+/// recorded game, one JSON state per line, and `<name>.expected.txt` holds
+/// the actions expected for each of those states, one line each.
+fn fixture_dir() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("src/static_files/test_data");
+}
+
+/// Set this to regenerate every fixture's expected actions, e.g.
+/// `ATLANTIS_BLESS=1 cargo test golden`, then review the diff.
+const BLESS_ENV: &str = "ATLANTIS_BLESS";
+
+/// Plays a recorded game through a fresh pipeline, returning the actions
+/// for each state as JSON so they can be compared regardless of the order
+/// the actions are written in.
+fn play_fixture(input: &Path) -> Vec<Value> {
+    let contents = fs::read_to_string(input).unwrap();
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+
+    return contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let state: State = serde_json::from_str(l).unwrap();
+            let actions = get_action_string(pipeline.step(&state));
+            return serde_json::from_str(&actions).unwrap();
+        })
+        .collect();
+}
+
+/// Checks a recorded game against its expected actions, or rewrites the
+/// expected actions in bless mode. Each line of actions is compared as
+/// JSON, so the order the actions are written in doesn't matter.
+fn check_fixture(name: &str) {
+    let input = fixture_dir().join(format!("{}.txt", name));
+    let expected_path = fixture_dir().join(format!("{}.expected.txt", name));
+    let actual = play_fixture(&input);
+
+    if env::var(BLESS_ENV).is_ok() {
+        let lines: Vec<String> = actual.iter().map(|a| a.to_string()).collect();
+        fs::write(&expected_path, lines.join("\n") + "\n").unwrap();
+        return;
+    }
+
+    let expected: Vec<Value> = match fs::read_to_string(&expected_path) {
+        Ok(c) => c
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect(),
+        Err(_) => panic!(
+            "{} has no expected actions, run with {}=1 to create them",
+            name, BLESS_ENV
+        ),
+    };
+
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "{}: played {} turns but expected {}",
+        name,
+        actual.len(),
+        expected.len()
+    );
+    for (turn, (a, e)) in actual.iter().zip(&expected).enumerate() {
+        assert_eq!(a, e, "{}: actions differ on turn {}", name, turn);
+    }
+}
+
+/// Every fixture in the test data directory, so new ones are picked up
+/// just by adding them
+fn fixture_names() -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for entry in fs::read_dir(fixture_dir()).unwrap() {
+        let file = entry.unwrap().file_name().to_string_lossy().to_string();
+        if let Some(name) = file.strip_suffix(".txt") {
+            if !name.ends_with(".expected") {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+    return names;
+}
+
+#[test]
+fn test_empty_output() {
+    check_fixture("test_empty");
+}
+
+#[test]
+fn test_output_with_actions() {
+    check_fixture("test_actions");
+}
+
+#[test]
+fn test_golden_fixtures() {
+    let names = fixture_names();
+    assert!(names.contains(&String::from("test_game")));
+
+    for name in names {
+        check_fixture(&name);
+    }
+}
+
+fn actions_state() -> State {
+    let line = include_str!("../../static_files/test_data/test_actions.txt");
+    return serde_json::from_str(line).unwrap();
+}
+
+#[test]
+fn test_pipeline_step() {
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+    let actions = pipeline.step(&actions_state());
+
+    assert_eq!(actions.len(), 4);
+    match &actions[&8] {
+        ActionType::Pass(p) => assert_eq!(p.to_id, 9),
+        ActionType::Nom(_) => panic!(),
+    }
+    assert!(pipeline.projection().is_some());
+    assert_eq!(pipeline.turn(), 1);
+}
 
 #[test]
-fn test_empty_output() {}
+fn test_pipeline_snapshot_and_reset() {
+    let mut pipeline = Pipeline::new(Config::default()).unwrap();
+    pipeline.step(&actions_state());
+
+    let snap = pipeline.snapshot();
+    assert_eq!(snap.turn, 1);
+    assert_eq!(snap.strategy, "greedy");
+    assert_eq!(snap.pearl_paths[&3048038777], vec![8]);
+
+    pipeline.reset();
+    let snap = pipeline.snapshot();
+    assert_eq!(snap.turn, 0);
+    assert!(snap.pearl_paths.is_empty());
+    assert!(snap.projection.is_none());
+}
+
+/// A strategy that never does anything, to check that the pipeline
+/// really hands decisions over to its strategy
+struct IdleStrategy;
+
+impl Strategy for IdleStrategy {
+    fn name(&self) -> &str {
+        return "idle";
+    }
+
+    fn decide(&mut self, _turn: &Turn, _pearl_paths: &mut PearlPaths) -> Actions {
+        return Actions::new();
+    }
+}
+
+#[test]
+fn test_pipeline_custom_strategy() {
+    let mut pipeline = Pipeline::new(Config::default())
+        .unwrap()
+        .with_strategy(Box::new(IdleStrategy));
+
+    assert!(pipeline.step(&actions_state()).is_empty());
+    assert_eq!(pipeline.snapshot().strategy, "idle");
+}
+
+/// An anytime strategy that always runs out of time, returning whatever it
+/// was given as its best plan so far
+struct SlowStrategy {
+    best_so_far: Option<Actions>,
+}
+
+impl Strategy for SlowStrategy {
+    fn name(&self) -> &str {
+        return "slow";
+    }
+
+    fn decide(&mut self, _turn: &Turn, _pearl_paths: &mut PearlPaths) -> Actions {
+        return Actions::new();
+    }
+
+    fn decide_within(
+        &mut self,
+        _turn: &Turn,
+        _pearl_paths: &mut PearlPaths,
+        deadline: &Deadline,
+    ) -> Anytime {
+        while !deadline.expired() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        return match &self.best_so_far {
+            Some(a) => Anytime::BestSoFar(a.clone()),
+            None => Anytime::Nothing,
+        };
+    }
+}
+
+#[test]
+fn test_deadline() {
+    assert!(!Deadline::unlimited().expired());
+    assert_eq!(Deadline::unlimited().remaining(), None);
+    assert!(Deadline::after(Duration::from_millis(0)).expired());
+
+    let config = Config {
+        turn_budget_ms: Some(60_000),
+        ..Config::default()
+    };
+    assert!(!Deadline::from_config(&config).expired());
+}
+
+#[test]
+fn test_deadline_falls_back_to_greedy() {
+    let config = Config {
+        turn_budget_ms: Some(5),
+        ..Config::default()
+    };
+    let mut pipeline = Pipeline::new(config)
+        .unwrap()
+        .with_strategy(Box::new(SlowStrategy { best_so_far: None }));
+
+    let actions = pipeline.step(&actions_state());
+    assert_eq!(actions.len(), 4);
+    assert_eq!(pipeline.deadline_stats().greedy_fallback, 1);
+    assert_eq!(pipeline.deadline_stats().turns(), 1);
+}
+
+#[test]
+fn test_deadline_uses_best_so_far() {
+    let config = Config {
+        turn_budget_ms: Some(5),
+        ..Config::default()
+    };
+    let strategy = SlowStrategy {
+        best_so_far: Some(Actions::new()),
+    };
+    let mut pipeline = Pipeline::new(config)
+        .unwrap()
+        .with_strategy(Box::new(strategy));
+
+    assert!(pipeline.step(&actions_state()).is_empty());
+    assert_eq!(pipeline.deadline_stats().best_so_far, 1);
+    assert_eq!(pipeline.deadline_stats().greedy_fallback, 0);
+
+    // Without a budget, the default greedy strategy always finishes
+    pipeline = Pipeline::new(Config::default()).unwrap();
+    pipeline.step(&actions_state());
+    assert_eq!(pipeline.snapshot().deadline_stats.completed, 1);
+}
 
 #[test]
-fn test_output_with_actions() {}
+fn test_greedy_ignores_the_budget() {
+    let config = Config {
+        turn_budget_ms: Some(0),
+        ..Config::default()
+    };
+    let mut pipeline = Pipeline::new(config).unwrap();
+
+    // Greedy is the fallback, so it always runs to the end
+    assert_eq!(pipeline.step(&actions_state()).len(), 4);
+    let stats = pipeline.deadline_stats();
+    assert_eq!(stats.completed + stats.overran, 1);
+    assert_eq!(stats.best_so_far + stats.greedy_fallback, 0);
+}
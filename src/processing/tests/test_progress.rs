@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use super::super::progress::LayerTracker;
+use super::super::simulator::apply_actions;
+use super::super::utils::{build_neighbor_graph, determine_actions, get_ability_map, make_nom};
+use crate::models::action::ActionType;
+use crate::models::config::Config;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+fn layer(color: &str, thickness: u32, original: Option<u32>) -> Layer {
+    return Layer {
+        color: String::from(color),
+        thickness: thickness,
+        original_thickness: original,
+    };
+}
+
+/// A General worker holding a single pearl, next to an empty Matrix worker
+fn progress_state(layers: Vec<Layer>) -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![Pearl {
+                    id: 7,
+                    layers: layers,
+                }],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![],
+                flavor: String::from("Matrix"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+#[test]
+fn test_layer_progress() {
+    let fresh = layer("Red", 10, None);
+    assert_eq!(fresh.original(), 10);
+    assert!(!fresh.is_partial());
+
+    let partial = layer("Red", 4, Some(10));
+    assert_eq!(partial.processed(), 6);
+    assert!(partial.is_partial());
+
+    let pearl = Pearl {
+        id: 1,
+        layers: vec![partial, layer("Blue", 10, None)],
+    };
+    assert!(pearl.is_partial());
+    assert_eq!(pearl.progress(), 0.3);
+
+    let finished = Pearl {
+        id: 2,
+        layers: vec![],
+    };
+    assert_eq!(finished.progress(), 1.0);
+}
+
+#[test]
+fn test_tracker_remembers_originals() {
+    let mut tracker = LayerTracker::new();
+
+    let first = tracker.annotate(&progress_state(vec![
+        layer("Red", 3, None),
+        layer("Blue", 20, None),
+    ]));
+    assert_eq!(
+        first.workers[0].desk[0].layers[0].original_thickness,
+        Some(3)
+    );
+
+    // The outer layer is gone and the next one has been worked on, but it
+    // is still the same layer with nothing beneath it
+    let second = tracker.annotate(&progress_state(vec![layer("Blue", 15, None)]));
+    let blue = &second.workers[0].desk[0].layers[0];
+    assert_eq!(blue.original_thickness, Some(20));
+    assert_eq!(blue.processed(), 5);
+    assert_eq!(tracker.original(7, 0), Some(20));
+    assert_eq!(tracker.original(7, 1), None);
+}
+
+#[test]
+fn test_tracker_keeps_harness_originals() {
+    let mut tracker = LayerTracker::new();
+    let state = progress_state(vec![layer("Blue", 15, Some(40))]);
+
+    let annotated = tracker.annotate(&state);
+    assert_eq!(annotated.workers[0].desk[0].layers[0].original(), 40);
+
+    // Pearls that leave the state are forgotten
+    let mut empty = state.clone();
+    empty.workers[0].desk.clear();
+    tracker.annotate(&empty);
+    assert_eq!(tracker.original(7, 0), None);
+}
+
+#[test]
+fn test_simulator_records_originals() {
+    let state = progress_state(vec![layer("Blue", 20, None)]);
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(0, make_nom(0, 7));
+
+    let next = apply_actions(&state, &acts, &get_ability_map(), 0);
+    let blue = &next.workers[0].desk[0].layers[0];
+    assert_eq!(blue.thickness, 19);
+    assert_eq!(blue.original_thickness, Some(20));
+}
+
+#[test]
+fn test_partial_pass_gain() {
+    let state = progress_state(vec![layer("Blue", 20, Some(30))]);
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    // Worker 0 isn't the gatekeeper, so it follows the pass rules
+    let mut config = Config {
+        gatekeeper_id: 1,
+        ..Default::default()
+    };
+
+    // The Matrix neighbor is 18 turns faster, enough for a fresh pearl
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut HashMap::new(),
+        &config,
+    );
+    assert!(matches!(actions[&0], ActionType::Pass(_)));
+
+    // But not once a partly processed pearl needs 20 more
+    config.strategy.partial_pass_gain = 20;
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut HashMap::new(),
+        &config,
+    );
+    assert_eq!(actions[&0], make_nom(0, 7));
+}
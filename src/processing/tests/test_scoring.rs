@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use super::super::scoring::{finishes_this_turn, plan_objective, project_score};
+use super::super::utils::{build_neighbor_graph, get_ability_map, make_nom, make_pass};
+use crate::models::action::ActionType;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+/// Returns a pair of workers where worker 1 holds a finished pearl and the
+/// gatekeeper holds a thin red pearl it can finish in one nom.
+fn basic_state() -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![Pearl {
+                    id: 7,
+                    layers: vec![Layer {
+                        color: String::from("Red"),
+                        thickness: 1,
+                        original_thickness: None,
+                    }],
+                }],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![Pearl {
+                    id: 8,
+                    layers: vec![],
+                }],
+                flavor: String::from("General"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1]],
+        edges: vec![],
+        score: 3,
+    };
+}
+
+#[test]
+fn test_finishes_this_turn() {
+    let state = basic_state();
+    let map = get_ability_map();
+
+    assert!(finishes_this_turn(
+        &state.workers[0].desk[0],
+        &state.workers[0],
+        &map
+    ));
+    assert!(!finishes_this_turn(
+        &state.workers[1].desk[0],
+        &state.workers[1],
+        &map
+    ));
+}
+
+#[test]
+fn test_project_score_counts_deliveries() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(0, make_nom(0, 7));
+    acts.insert(1, make_pass(1, 8, 0));
+
+    let proj = project_score(&state, &acts, &get_ability_map(), &ng, 0, 1);
+    assert_eq!(proj.current_score, 3);
+    assert_eq!(proj.delivered, 2);
+    assert_eq!(proj.projected_score, 5);
+}
+
+#[test]
+fn test_project_score_no_actions() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let acts: HashMap<u32, ActionType> = HashMap::new();
+
+    let proj = project_score(&state, &acts, &get_ability_map(), &ng, 0, 10);
+    assert_eq!(proj.delivered, 0);
+    assert_eq!(proj.projected_score, 3);
+    assert!(proj.pearls_per_turn > 0.0);
+}
+
+#[test]
+fn test_plan_objective_prefers_delivery() {
+    let state = basic_state();
+    let ng = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let map = get_ability_map();
+
+    let mut deliver: HashMap<u32, ActionType> = HashMap::new();
+    deliver.insert(1, make_pass(1, 8, 0));
+    let idle: HashMap<u32, ActionType> = HashMap::new();
+
+    assert!(
+        plan_objective(&state, &deliver, &map, &ng, 0)
+            > plan_objective(&state, &idle, &map, &ng, 0)
+    );
+}
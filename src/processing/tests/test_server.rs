@@ -0,0 +1,220 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use super::super::server::{parse_listen_addr, run_session, ListenAddr, Server, ServerOptions};
+use super::super::strategy::{Strategy, Turn};
+use super::super::utils::get_ability_map;
+use crate::models::action::Actions;
+use crate::models::config::Config;
+use crate::models::state::PearlPaths;
+use crate::Pipeline;
+
+/// A strategy that fails on every turn, standing in for a bug in a planner
+struct PanickingStrategy;
+
+impl Strategy for PanickingStrategy {
+    fn name(&self) -> &str {
+        return "panicking";
+    }
+
+    fn decide(&mut self, _turn: &Turn, _pearl_paths: &mut PearlPaths) -> Actions {
+        panic!("Test Error: no plan");
+    }
+}
+
+fn actions_line() -> String {
+    let line = include_str!("../../static_files/test_data/test_actions.txt");
+    return line.trim().to_string();
+}
+
+fn test_options() -> ServerOptions {
+    return ServerOptions {
+        idle_timeout: Some(Duration::from_secs(5)),
+        poll_interval: Duration::from_millis(10),
+    };
+}
+
+/// Sends one state down a connection and reads back the line of actions
+fn send_state<S: std::io::Read>(writer: &mut impl Write, reader: &mut BufReader<S>) -> String {
+    writer.write_all(actions_line().as_bytes()).unwrap();
+    writer.write_all(b"\n").unwrap();
+    writer.flush().unwrap();
+
+    let mut response = String::new();
+    reader.read_line(&mut response).unwrap();
+    return response;
+}
+
+#[test]
+fn test_parse_listen_addr() {
+    assert_eq!(
+        parse_listen_addr("tcp:127.0.0.1:7000"),
+        Ok(ListenAddr::Tcp(String::from("127.0.0.1:7000")))
+    );
+    assert_eq!(
+        parse_listen_addr("localhost:7000"),
+        Ok(ListenAddr::Tcp(String::from("localhost:7000")))
+    );
+    assert!(parse_listen_addr("7000").is_err());
+
+    #[cfg(unix)]
+    assert_eq!(
+        parse_listen_addr("unix:/tmp/atlantis.sock"),
+        Ok(ListenAddr::Unix("/tmp/atlantis.sock".into()))
+    );
+}
+
+#[test]
+fn test_run_session() {
+    let input = format!("{}\n\n{}\n", actions_line(), actions_line());
+    let mut output: Vec<u8> = Vec::new();
+    let mut pipeline = Pipeline::with_ability_map(Config::default(), get_ability_map());
+    let shutdown = AtomicBool::new(false);
+
+    run_session(
+        input.as_bytes(),
+        &mut output,
+        &mut pipeline,
+        &shutdown,
+        None,
+    )
+    .unwrap();
+
+    let out = String::from_utf8(output).unwrap();
+    assert_eq!(out.lines().count(), 2);
+    assert!(out.contains("\"Pass\""));
+}
+
+#[test]
+fn test_run_session_bad_line() {
+    let mut bad_worker = actions_line();
+    bad_worker = bad_worker.replacen("\"neighbor_map\":[", "\"neighbor_map\":[[0,999],", 1);
+    let input = format!("not a state\n{}\n{}\n", bad_worker, actions_line());
+    let mut output: Vec<u8> = Vec::new();
+    let mut pipeline = Pipeline::with_ability_map(Config::default(), get_ability_map());
+    let shutdown = AtomicBool::new(false);
+
+    run_session(
+        input.as_bytes(),
+        &mut output,
+        &mut pipeline,
+        &shutdown,
+        None,
+    )
+    .unwrap();
+
+    // Both bad lines are answered with an error, and the game carries on
+    let out = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].contains("Could not parse state"));
+    assert!(lines[1].contains("unknown worker 999"));
+    assert!(lines[2].contains("\"Pass\""));
+    assert_eq!(pipeline.turn(), 1);
+}
+
+#[test]
+fn test_run_session_panic() {
+    let input = format!("{}\n{}\n", actions_line(), actions_line());
+    let mut output: Vec<u8> = Vec::new();
+    let mut pipeline = Pipeline::with_ability_map(Config::default(), get_ability_map())
+        .with_strategy(Box::new(PanickingStrategy));
+    let shutdown = AtomicBool::new(false);
+
+    let result = run_session(
+        input.as_bytes(),
+        &mut output,
+        &mut pipeline,
+        &shutdown,
+        None,
+    );
+
+    // The client hears about it, and the session ends after the first line
+    assert!(result.is_err());
+    let out = String::from_utf8(output).unwrap();
+    assert_eq!(out.lines().count(), 1);
+    assert!(out.contains("Internal error"));
+}
+
+#[test]
+fn test_tcp_sessions_are_independent() {
+    let addr = ListenAddr::Tcp(String::from("127.0.0.1:0"));
+    let server = Server::bind(&addr, Config::default(), test_options()).unwrap();
+    let local = server.local_addr();
+    let shutdown = server.shutdown_flag();
+    let running = thread::spawn(move || server.run());
+
+    let first = TcpStream::connect(&local).unwrap();
+    let second = TcpStream::connect(&local).unwrap();
+    let mut first_reader = BufReader::new(first.try_clone().unwrap());
+    let mut second_reader = BufReader::new(second.try_clone().unwrap());
+    let (mut first, mut second) = (first, second);
+
+    // Both games see the same first state, so they both decide the same
+    // way, though the actions may be written in a different order
+    let a = send_state(&mut first, &mut first_reader);
+    let b = send_state(&mut second, &mut second_reader);
+    assert!(a.contains("\"Pass\""));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&a).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&b).unwrap()
+    );
+
+    // Closing one game leaves the other playing
+    drop(first);
+    drop(first_reader);
+    assert!(send_state(&mut second, &mut second_reader).ends_with('\n'));
+
+    shutdown.store(true, Ordering::SeqCst);
+    running.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_idle_timeout() {
+    let addr = ListenAddr::Tcp(String::from("127.0.0.1:0"));
+    let options = ServerOptions {
+        idle_timeout: Some(Duration::from_millis(50)),
+        poll_interval: Duration::from_millis(10),
+    };
+    let server = Server::bind(&addr, Config::default(), options).unwrap();
+    let local = server.local_addr();
+    let shutdown = server.shutdown_flag();
+    let running = thread::spawn(move || server.run());
+
+    let stream = TcpStream::connect(&local).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    // The server hangs up, which reads as the end of the stream
+    let mut response = String::new();
+    let read = BufReader::new(stream).read_line(&mut response).unwrap();
+    assert_eq!(read, 0);
+
+    shutdown.store(true, Ordering::SeqCst);
+    running.join().unwrap().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unix_socket() {
+    use std::os::unix::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("atlantis-test-{}.sock", std::process::id()));
+    let addr = ListenAddr::Unix(path.clone());
+    let server = Server::bind(&addr, Config::default(), test_options()).unwrap();
+    let shutdown = server.shutdown_flag();
+    let running = thread::spawn(move || server.run());
+
+    let mut stream = UnixStream::connect(&path).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    assert!(send_state(&mut stream, &mut reader).contains("\"Pass\""));
+
+    // Shutting down closes the open session and removes the socket
+    shutdown.store(true, Ordering::SeqCst);
+    running.join().unwrap().unwrap();
+    assert!(!path.exists());
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use super::super::simulator::{apply_actions, simulate};
+use super::super::utils::{get_ability_map, make_nom, make_pass};
+use crate::models::action::ActionType;
+use crate::models::config::Config;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+/// Returns a General gatekeeper with a two layer pearl, next to an empty
+/// Matrix worker
+fn basic_state() -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![Pearl {
+                    id: 1,
+                    layers: vec![
+                        Layer {
+                            color: String::from("Red"),
+                            thickness: 1,
+                            original_thickness: None,
+                        },
+                        Layer {
+                            color: String::from("Blue"),
+                            thickness: 20,
+                            original_thickness: None,
+                        },
+                    ],
+                }],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![],
+                flavor: String::from("Matrix"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+#[test]
+fn test_apply_nom_removes_dissolved_layer() {
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(0, make_nom(0, 1));
+
+    let next = apply_actions(&basic_state(), &acts, &get_ability_map(), 0);
+
+    assert_eq!(next.workers[0].desk[0].layers.len(), 1);
+    assert_eq!(next.workers[0].desk[0].layers[0].color, "Blue");
+}
+
+#[test]
+fn test_apply_pass_moves_pearl() {
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(0, make_pass(0, 1, 1));
+
+    let next = apply_actions(&basic_state(), &acts, &get_ability_map(), 0);
+
+    assert!(next.workers[0].desk.is_empty());
+    assert_eq!(next.workers[1].desk[0].id, 1);
+}
+
+#[test]
+fn test_finished_pearls_are_scored_at_gatekeeper() {
+    let mut state = basic_state();
+    state.workers[1].desk.push(Pearl {
+        id: 2,
+        layers: vec![],
+    });
+
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(1, make_pass(1, 2, 0));
+
+    let next = apply_actions(&state, &acts, &get_ability_map(), 0);
+
+    assert_eq!(next.score, 1);
+    assert_eq!(next.workers[0].desk.len(), 1);
+}
+
+#[test]
+fn test_simulate_delivers_everything() {
+    let (turns, end) = simulate(&basic_state(), &get_ability_map(), &Config::default(), 100);
+
+    assert!(!turns.is_empty());
+    assert_eq!(end.score, 1);
+    assert!(end.workers.iter().all(|w| w.desk.is_empty()));
+}
+
+#[test]
+fn test_simulate_stops_at_max_turns() {
+    let (turns, end) = simulate(&basic_state(), &get_ability_map(), &Config::default(), 1);
+
+    assert_eq!(turns.len(), 1);
+    assert_eq!(end.score, 0);
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::super::bound::estimate_lower_bound;
+use super::super::simulator::{apply_turn, simulate, InTransit};
+use super::super::solver::{
+    compare_to_optimal, solve_exact, solved_to_fixture, solved_to_text, Solution, SolverFixture,
+    DEFAULT_MAX_STATES,
+};
+use super::super::transfer::TransferCosts;
+use super::super::utils::{build_state_graph, get_ability_map};
+use super::{matrix_state, pearl, worker};
+use crate::models::config::{Config, TransferConfig};
+use crate::models::state::State;
+
+/// Games the solver found greedy to be beaten on, one fixture per line.
+/// Add more with `atlantis solve --fixtures`.
+pub const FIXTURES: &str = "src/static_files/solver_data/greedy_suboptimal.jsonl";
+
+/// Plays a solution through the simulator's rules, returning the final
+/// state and whether anything was left in transit.
+fn replay(state: &State, solution: &Solution, config: &Config) -> (State, bool) {
+    let neighbor_graph = build_state_graph(state, &config.transfer);
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(&neighbor_graph);
+    let mut in_transit: Vec<InTransit> = Vec::new();
+    let mut current = state.clone();
+
+    for actions in &solution.actions {
+        current = apply_turn(
+            &current,
+            actions,
+            &get_ability_map(),
+            config.gatekeeper_id,
+            &transfer_costs,
+            &mut in_transit,
+            &mut HashMap::new(),
+        );
+    }
+
+    return (current, !in_transit.is_empty());
+}
+
+#[test]
+fn test_solution_delivers_everything() {
+    let config = Config::default();
+    let solution = solve_exact(&matrix_state(), &get_ability_map(), &config, 1000).unwrap();
+
+    // Out to the Matrix, one nom, and back again
+    assert_eq!(solution.turns, 3);
+    assert_eq!(solution.actions.len(), 3);
+
+    let (end, moving) = replay(&matrix_state(), &solution, &config);
+    assert_eq!(end.score, 1);
+    assert!(!moving);
+}
+
+#[test]
+fn test_solver_counts_transfer_costs() {
+    let config = Config {
+        transfer: TransferConfig {
+            cost: 4,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let solution = solve_exact(&matrix_state(), &get_ability_map(), &config, 10_000).unwrap();
+
+    // Passing now takes too long, so the gatekeeper does it all itself
+    assert_eq!(solution.turns, 10);
+}
+
+#[test]
+fn test_solver_never_loses_to_greedy() {
+    let state = State {
+        workers: vec![
+            worker(
+                0,
+                "General",
+                vec![pearl(1, "Blue", 6), pearl(2, "Green", 6)],
+            ),
+            worker(1, "Matrix", vec![]),
+            worker(2, "Vector", vec![]),
+        ],
+        neighbor_map: vec![vec![0, 1], vec![1, 2]],
+        edges: vec![],
+        score: 0,
+    };
+    let config = Config::default();
+    let solved = compare_to_optimal(&state, &get_ability_map(), &config, 100_000, 100).unwrap();
+
+    let bound = estimate_lower_bound(&state, &get_ability_map(), &config);
+    assert!(solved.optimal_turns >= bound.lower_bound);
+    assert!(solved.greedy_finished);
+    assert!(solved.optimal_turns <= solved.greedy_turns);
+    assert_eq!(solved.actions.len() as u32, solved.optimal_turns);
+    assert!(solved_to_text(&[solved]).contains("game 0: optimal"));
+}
+
+#[test]
+fn test_unsolvable_games() {
+    let mut state = matrix_state();
+    state
+        .workers
+        .push(worker(2, "Vector", vec![pearl(2, "Green", 5)]));
+    let stranded = solve_exact(&state, &get_ability_map(), &Config::default(), 1000);
+    assert!(stranded.unwrap_err().contains("never be delivered"));
+
+    let too_big = solve_exact(&matrix_state(), &get_ability_map(), &Config::default(), 2);
+    assert!(too_big.unwrap_err().contains("gave up"));
+}
+
+#[test]
+fn test_greedy_suboptimal_fixtures() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES);
+    let contents = fs::read_to_string(path).unwrap();
+    let config = Config::default();
+    let ability_map = get_ability_map();
+    let mut count = 0;
+
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        let fixture: SolverFixture = serde_json::from_str(line).unwrap();
+        let solution =
+            solve_exact(&fixture.state, &ability_map, &config, DEFAULT_MAX_STATES).unwrap();
+        assert_eq!(solution.turns, fixture.optimal_turns);
+
+        let pearls: usize = fixture.state.workers.iter().map(|w| w.desk.len()).sum();
+        let (end, moving) = replay(&fixture.state, &solution, &config);
+        assert_eq!(end.score as usize, pearls);
+        assert!(!moving);
+
+        // Greedy may get better on these, but never worse
+        let (turns, _) = simulate(&fixture.state, &ability_map, &config, 1000);
+        assert!(turns.len() as u32 <= fixture.greedy_turns);
+        count += 1;
+    }
+
+    assert!(count > 0);
+}
+
+#[test]
+fn test_fixture_round_trip() {
+    let config = Config::default();
+    let solved =
+        compare_to_optimal(&matrix_state(), &get_ability_map(), &config, 1000, 100).unwrap();
+    let fixture: SolverFixture =
+        serde_json::from_str(&solved_to_fixture(&matrix_state(), &solved)).unwrap();
+
+    assert_eq!(fixture.optimal_turns, 3);
+    assert_eq!(fixture.greedy_turns, solved.greedy_turns);
+    assert_eq!(fixture.actions[0]["0"]["Pass"]["to_worker"], 1);
+}
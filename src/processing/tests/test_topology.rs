@@ -0,0 +1,130 @@
+use super::super::topology::{
+    analyse_topology, find_articulation_points, topology_to_json, topology_to_text, Adjacency,
+};
+use super::worker;
+use crate::models::config::Config;
+use crate::models::state::{Edge, State};
+
+/// A gatekeeper in the middle of a line 1 - 0 - 2 - 3, with a Matrix at
+/// the far end and a Vector worker 4 off on its own
+fn line_state() -> State {
+    return State {
+        workers: vec![
+            worker(0, "General", vec![]),
+            worker(1, "General", vec![]),
+            worker(2, "Vector", vec![]),
+            worker(3, "Matrix", vec![]),
+            worker(4, "Vector", vec![]),
+        ],
+        neighbor_map: vec![vec![1, 0], vec![0, 2], vec![2, 3]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+#[test]
+fn test_components_and_reach() {
+    let report = analyse_topology(&line_state(), &Config::default(), 2);
+
+    assert_eq!(report.components, vec![vec![0, 1, 2, 3], vec![4]]);
+    assert_eq!(report.cannot_reach_gatekeeper, vec![4]);
+    assert_eq!(report.unreachable_from_gatekeeper, vec![4]);
+    assert_eq!(report.diameter, 3);
+    assert_eq!(report.articulation_points, vec![0, 2]);
+
+    let distances: Vec<Option<u32>> = report.distances.iter().map(|d| d.distance).collect();
+    assert_eq!(distances, vec![Some(0), Some(1), Some(1), Some(2), None]);
+}
+
+#[test]
+fn test_flavors_within_hops() {
+    let report = analyse_topology(&line_state(), &Config::default(), 1);
+
+    let matrix = report
+        .flavors
+        .iter()
+        .find(|f| f.flavor == "Matrix")
+        .unwrap();
+    assert!(matrix.within_hops.is_empty());
+    assert_eq!(matrix.nearest, Some(2));
+
+    // Worker 4 is a Vector too, but out of reach
+    let vector = report
+        .flavors
+        .iter()
+        .find(|f| f.flavor == "Vector")
+        .unwrap();
+    assert_eq!(vector.within_hops, vec![2]);
+    assert_eq!(vector.nearest, Some(1));
+}
+
+#[test]
+fn test_one_way_edges() {
+    let mut state = line_state();
+    state.edges.push(Edge {
+        workers: [3, 4],
+        cost: None,
+        capacity: None,
+        one_way: true,
+    });
+    let report = analyse_topology(&state, &Config::default(), 3);
+
+    // Pearls can get to 4, but never back
+    assert_eq!(report.components.len(), 1);
+    assert_eq!(report.cannot_reach_gatekeeper, vec![4]);
+    assert!(report.unreachable_from_gatekeeper.is_empty());
+    assert_eq!(report.articulation_points, vec![0, 2, 3]);
+    assert_eq!(report.diameter, 4);
+}
+
+#[test]
+fn test_cycle_removes_articulation_points() {
+    let mut state = line_state();
+    state.neighbor_map.push(vec![3, 1]);
+    state.neighbor_map.push(vec![4, 3]);
+    let report = analyse_topology(&state, &Config::default(), 2);
+
+    // Only 3 still holds up 4
+    assert_eq!(report.articulation_points, vec![3]);
+    assert!(report.cannot_reach_gatekeeper.is_empty());
+    assert_eq!(report.diameter, 3);
+}
+
+#[test]
+fn test_topology_output() {
+    let report = analyse_topology(&line_state(), &Config::default(), 2);
+
+    let text = topology_to_text(&report);
+    assert!(text.contains("WARNING cannot reach the gatekeeper: [4]"));
+    assert!(text.contains("  4: unreachable"));
+    assert!(text.contains("  Matrix: [3], nearest 2 hops"));
+
+    let json: serde_json::Value = serde_json::from_str(&topology_to_json(&report)).unwrap();
+    assert_eq!(json["diameter"], 3);
+    assert_eq!(json["articulation_points"], serde_json::json!([0, 2]));
+}
+
+/// Joins workers `0..n` into a line, closing it into a ring if asked
+fn chain(n: u32, ring: bool) -> Adjacency {
+    let mut both: Adjacency = (0..n).map(|id| (id, vec![])).collect();
+    let last = if ring { n } else { n - 1 };
+    for i in 0..last {
+        let j = (i + 1) % n;
+        both.get_mut(&i).unwrap().push(j);
+        both.get_mut(&j).unwrap().push(i);
+    }
+    for nbrs in both.values_mut() {
+        nbrs.sort();
+    }
+    return both;
+}
+
+#[test]
+fn test_articulation_points_of_a_long_chain() {
+    // Deep enough to overflow the stack if the search recursed
+    let n = 100_000;
+    let points = find_articulation_points(&chain(n, false));
+    assert_eq!(points, (1..n - 1).collect::<Vec<u32>>());
+
+    assert!(find_articulation_points(&chain(n, true)).is_empty());
+}
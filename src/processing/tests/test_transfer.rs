@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use super::super::simulator::{apply_turn, simulate, InTransit};
+use super::super::transfer::TransferCosts;
+use super::super::utils::{
+    build_neighbor_graph, determine_actions, get_ability_map, make_nom, make_pass,
+};
+use crate::models::action::ActionType;
+use crate::models::config::{Config, EdgeCost, TransferConfig};
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+/// A General worker holding a Blue pearl, next to an empty Matrix
+/// gatekeeper that would take 2 turns over it instead of 20
+fn transfer_state() -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![Pearl {
+                    id: 7,
+                    layers: vec![Layer {
+                        color: String::from("Blue"),
+                        thickness: 20,
+                        original_thickness: None,
+                    }],
+                }],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![],
+                flavor: String::from("Matrix"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+fn transfer_config(cost: u32, edges: Vec<EdgeCost>) -> Config {
+    return Config {
+        gatekeeper_id: 1,
+        transfer: TransferConfig {
+            cost: cost,
+            edges: edges,
+        },
+        ..Default::default()
+    };
+}
+
+#[test]
+fn test_transfer_costs() {
+    let costs = TransferCosts::new(&TransferConfig {
+        cost: 1,
+        edges: vec![EdgeCost {
+            workers: [0, 1],
+            cost: 3,
+        }],
+    });
+
+    assert_eq!(costs.cost(0, 1), 3);
+    assert_eq!(costs.cost(1, 0), 3);
+    assert_eq!(costs.cost(1, 2), 1);
+    assert!(!costs.is_free());
+    assert!(TransferCosts::uniform(0).is_free());
+}
+
+#[test]
+fn test_transfer_config_from_json() {
+    let config: Config =
+        serde_json::from_str(r#"{"transfer": {"edges": [{"workers": [0, 1], "cost": 3}]}}"#)
+            .unwrap();
+
+    assert_eq!(config.transfer.cost, 0);
+    assert_eq!(config.transfer.edges[0].workers, [0, 1]);
+}
+
+#[test]
+fn test_pass_pays_transfer_cost() {
+    let state = transfer_state();
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let decide = |config: &Config| {
+        return determine_actions(
+            &state,
+            &get_ability_map(),
+            &graph,
+            &mut HashMap::new(),
+            config,
+        );
+    };
+
+    // 2 turns there plus 17 in transit still beats 20 here by a turn
+    let actions = decide(&transfer_config(17, vec![]));
+    assert_eq!(actions[&0], make_pass(0, 7, 1));
+
+    // But 18 in transit on that edge doesn't
+    let edge = EdgeCost {
+        workers: [1, 0],
+        cost: 18,
+    };
+    let actions = decide(&transfer_config(0, vec![edge]));
+    assert_eq!(actions[&0], make_nom(0, 7));
+}
+
+#[test]
+fn test_pearls_in_transit() {
+    let state = transfer_state();
+    let mut acts: HashMap<u32, ActionType> = HashMap::new();
+    acts.insert(0, make_pass(0, 7, 1));
+    let costs = TransferCosts::uniform(2);
+    let mut in_transit: Vec<InTransit> = Vec::new();
+
+    let next = apply_turn(
+        &state,
+        &acts,
+        &get_ability_map(),
+        1,
+        &costs,
+        &mut in_transit,
+        &mut HashMap::new(),
+    );
+    assert!(next.workers.iter().all(|w| w.desk.is_empty()));
+    assert_eq!(in_transit.len(), 1);
+    assert_eq!(in_transit[0].turns_left, 2);
+
+    let next = apply_turn(
+        &next,
+        &HashMap::new(),
+        &get_ability_map(),
+        1,
+        &costs,
+        &mut in_transit,
+        &mut HashMap::new(),
+    );
+    assert_eq!(in_transit[0].turns_left, 1);
+
+    let next = apply_turn(
+        &next,
+        &HashMap::new(),
+        &get_ability_map(),
+        1,
+        &costs,
+        &mut in_transit,
+        &mut HashMap::new(),
+    );
+    assert!(in_transit.is_empty());
+    assert_eq!(next.workers[1].desk[0].id, 7);
+}
+
+#[test]
+fn test_simulate_waits_for_pearls_in_transit() {
+    let config = transfer_config(3, vec![]);
+    let (free_turns, _) = simulate(
+        &transfer_state(),
+        &get_ability_map(),
+        &transfer_config(0, vec![]),
+        100,
+    );
+    let (turns, end) = simulate(&transfer_state(), &get_ability_map(), &config, 100);
+
+    assert_eq!(end.score, 1);
+    assert_eq!(turns.len(), free_turns.len() + 3);
+}
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
 use super::super::utils::{
-    build_neighbor_graph, get_empty_neighbors, get_time_to_process, get_worker_ids,
-    get_worker_pearl_counts, make_nom, make_pass,
+    build_neighbor_graph, determine_actions, get_empty_neighbors, get_time_to_process,
+    get_worker_ids, get_worker_pearl_counts, make_nom, make_pass,
 };
 use crate::models::action::ActionType;
-use crate::models::state::{Layer, NeighborMap, Pearl, Worker, Workers};
+use crate::models::config::{Config, TieBreak};
+use crate::models::state::{Layer, NeighborMap, Pearl, State, Worker, Workers};
 use crate::processing::utils::{get_action_string, get_best_pearl_to_nom};
 use crate::{models::ability_map::AbilityMap, processing::utils::get_ability_map};
 
@@ -16,6 +17,7 @@ fn basic_pearl() -> Pearl {
         layers: vec![Layer {
             color: String::from("Green"),
             thickness: 12,
+            original_thickness: None,
         }],
     };
 
@@ -63,6 +65,20 @@ fn test_ability_map_values() {
     assert_eq!(map["Vector"]["Green"], 5);
 }
 
+#[test]
+fn test_ability_map_rate() {
+    let am: AbilityMap = get_ability_map();
+
+    assert_eq!(am.rate("Vector", "Green"), Ok(am["Vector"]["Green"]));
+    assert!(am.rate("Purple", "Green").is_err());
+    assert!(am.rate("Vector", "Purple").is_err());
+
+    // A flavor the map doesn't know can't process anything
+    let mut worker = basic_workers()[0].clone();
+    worker.flavor = String::from("Purple");
+    assert_eq!(get_time_to_process(&basic_pearl(), &worker, &am), u32::MAX);
+}
+
 #[test]
 fn test_worker_ids() {
     let ids = get_worker_ids(&basic_workers());
@@ -95,10 +111,23 @@ fn test_neighbor_graph() {
 }
 
 #[test]
-#[should_panic]
 fn test_neighbor_graph_bad_neighbor_list() {
+    // There's no worker 2, so that entry is skipped
     let nm: NeighborMap = vec![vec![0, 1], vec![1, 2]];
-    let _ng = build_neighbor_graph(&nm, &basic_workers());
+    let ng = build_neighbor_graph(&nm, &basic_workers());
+
+    assert_eq!(ng[&0], vec![1]);
+    assert_eq!(ng[&1], vec![0]);
+    assert!(ng.get(&2).is_none());
+}
+
+#[test]
+fn test_neighbor_graph_skips_short_entries() {
+    let nm: NeighborMap = vec![vec![0, 1], vec![1], vec![]];
+    let ng = build_neighbor_graph(&nm, &basic_workers());
+
+    assert_eq!(ng[&0], vec![1]);
+    assert_eq!(ng[&1], vec![0]);
 }
 
 #[test]
@@ -154,10 +183,25 @@ fn test_get_empty_neighbors() {
 fn test_get_best_pearl_to_nom() {
     let map = get_ability_map();
 
-    let bp = get_best_pearl_to_nom(&basic_workers()[1], &map);
+    let bp = get_best_pearl_to_nom(&basic_workers()[1], &map, TieBreak::First);
     assert_eq!(bp, Some(12345));
 }
 
+#[test]
+fn test_get_best_pearl_to_nom_tie_break() {
+    let map = get_ability_map();
+    let mut worker = basic_workers()[1].clone();
+    let mut twin = basic_pearl();
+    twin.id = 11111;
+    worker.desk.push(twin);
+
+    let first = get_best_pearl_to_nom(&worker, &map, TieBreak::First);
+    assert_eq!(first, Some(12345));
+
+    let lowest = get_best_pearl_to_nom(&worker, &map, TieBreak::LowestId);
+    assert_eq!(lowest, Some(11111));
+}
+
 #[test]
 fn test_get_action_string_empty() {
     let acts: HashMap<u32, ActionType> = HashMap::new();
@@ -172,3 +216,175 @@ fn test_get_action_string_basic() {
 
     assert_eq!(astr, "{\"1\":{\"Nom\":12345}}");
 }
+
+#[test]
+fn test_finished_pearls_wait_for_an_empty_desk() {
+    let finished = |id: u32, worker: u32| Worker {
+        id: worker,
+        desk: vec![Pearl {
+            id: id,
+            layers: Vec::new(),
+        }],
+        flavor: String::from("General"),
+    };
+    let state = State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: Vec::new(),
+                flavor: String::from("General"),
+            },
+            finished(100, 1),
+            finished(101, 2),
+        ],
+        neighbor_map: vec![vec![0, 1], vec![0, 2]],
+        edges: vec![],
+        score: 0,
+    };
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut pearl_paths,
+        &Config::default(),
+    );
+
+    // The gatekeeper's desk is empty, so only the first return goes ahead
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[&1], make_pass(1, 100, 0));
+}
+
+/// A worker with a finished pearl and an unfinished one on its desk
+fn mixed_desk(worker: u32, finished: u32, unfinished: u32) -> Worker {
+    return Worker {
+        id: worker,
+        desk: vec![
+            Pearl {
+                id: finished,
+                layers: Vec::new(),
+            },
+            Pearl {
+                id: unfinished,
+                ..basic_pearl()
+            },
+        ],
+        flavor: String::from("General"),
+    };
+}
+
+#[test]
+fn test_blocked_finished_pearl_does_not_idle_the_worker() {
+    let state = State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: Vec::new(),
+                flavor: String::from("General"),
+            },
+            mixed_desk(1, 100, 200),
+            mixed_desk(2, 101, 201),
+        ],
+        neighbor_map: vec![vec![0, 1], vec![0, 2]],
+        edges: vec![],
+        score: 0,
+    };
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut pearl_paths,
+        &Config::default(),
+    );
+
+    // Worker 2's finished pearl waits for the gatekeeper's desk, but its
+    // unfinished one still gets worked on
+    assert_eq!(actions[&1], make_pass(1, 100, 0));
+    assert_eq!(actions[&2], make_nom(2, 201));
+}
+
+#[test]
+fn test_finished_pearl_with_no_route_home_does_not_idle_the_worker() {
+    let state = State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: Vec::new(),
+                flavor: String::from("General"),
+            },
+            mixed_desk(1, 100, 200),
+            Worker {
+                id: 2,
+                desk: vec![Pearl {
+                    id: 101,
+                    layers: Vec::new(),
+                }],
+                flavor: String::from("General"),
+            },
+        ],
+        neighbor_map: vec![vec![1, 2]],
+        edges: vec![],
+        score: 0,
+    };
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+    let mut pearl_paths: HashMap<u32, Vec<u32>> = HashMap::new();
+
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut pearl_paths,
+        &Config::default(),
+    );
+
+    // Neither can reach the gatekeeper. Worker 1 still noms its unfinished
+    // pearl, and worker 2, with only a finished pearl, has nothing to do.
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[&1], make_nom(1, 200));
+}
+
+#[test]
+fn test_untracked_finished_pearl_takes_the_quickest_route_home() {
+    let state = State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: Vec::new(),
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 1,
+                desk: Vec::new(),
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 2,
+                desk: vec![Pearl {
+                    id: 100,
+                    layers: Vec::new(),
+                }],
+                flavor: String::from("General"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1], vec![1, 2]],
+        edges: vec![],
+        score: 0,
+    };
+    let graph = build_neighbor_graph(&state.neighbor_map, &state.workers);
+
+    // Nobody recorded how pearl 100 got to worker 2
+    let actions = determine_actions(
+        &state,
+        &get_ability_map(),
+        &graph,
+        &mut HashMap::new(),
+        &Config::default(),
+    );
+
+    assert_eq!(actions[&2], make_pass(2, 100, 1));
+}
@@ -0,0 +1,74 @@
+use super::super::render::{render_state_dot, render_state_text};
+use super::super::validate::validate_state;
+use crate::models::state::{Layer, Pearl, State, Worker};
+
+fn basic_state() -> State {
+    return State {
+        workers: vec![
+            Worker {
+                id: 0,
+                desk: vec![Pearl {
+                    id: 5,
+                    layers: vec![Layer {
+                        color: String::from("Red"),
+                        thickness: 3,
+                        original_thickness: None,
+                    }],
+                }],
+                flavor: String::from("General"),
+            },
+            Worker {
+                id: 1,
+                desk: vec![],
+                flavor: String::from("Vector"),
+            },
+        ],
+        neighbor_map: vec![vec![0, 1]],
+        edges: vec![],
+        score: 0,
+    };
+}
+
+#[test]
+fn test_valid_state() {
+    assert!(validate_state(&basic_state(), 0).is_empty());
+}
+
+#[test]
+fn test_missing_gatekeeper() {
+    assert_eq!(validate_state(&basic_state(), 7).len(), 1);
+}
+
+#[test]
+fn test_bad_neighbor_entries() {
+    let mut state = basic_state();
+    state.neighbor_map.push(vec![0]);
+    state.neighbor_map.push(vec![1, 9]);
+
+    assert_eq!(validate_state(&state, 0).len(), 2);
+}
+
+#[test]
+fn test_unknown_flavor_and_color() {
+    let mut state = basic_state();
+    state.workers[1].flavor = String::from("Tensor");
+    state.workers[0].desk[0].layers[0].color = String::from("Pink");
+
+    assert_eq!(validate_state(&state, 0).len(), 2);
+}
+
+#[test]
+fn test_render_text() {
+    let text = render_state_text(&basic_state(), 0);
+
+    assert!(text.starts_with("score 0\n"));
+    assert!(text.contains("*   0 General  -> [1]: 5 [Red 3]"));
+}
+
+#[test]
+fn test_render_dot() {
+    let dot = render_state_dot(&basic_state(), 0);
+
+    assert!(dot.contains("0 [shape=box"));
+    assert!(dot.contains("0 -- 1;"));
+}
@@ -0,0 +1,393 @@
+use serde::Serialize;
+use serde_json;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::utils::build_state_graph;
+use crate::models;
+use models::ability_map::FLAVORS;
+use models::config::Config;
+use models::state::{NeighborGraph, State};
+
+/// Who each worker leads to, sorted by ID.
+pub type Adjacency = HashMap<u32, Vec<u32>>;
+
+/// How many hops a worker is from the gatekeeper.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WorkerDistance {
+    pub worker_id: u32,
+    /// None if the gatekeeper can't pass pearls to the worker at all
+    pub distance: Option<u32>,
+}
+
+/// Which workers of one flavor the gatekeeper can get pearls to.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FlavorReach {
+    pub flavor: String,
+    /// The workers of this flavor within the hop limit, in ID order
+    pub within_hops: Vec<u32>,
+    /// How many hops away the nearest worker of this flavor is, if any can
+    /// be reached
+    pub nearest: Option<u32>,
+}
+
+/// The shape of a floor, and its weak spots.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TopologyReport {
+    pub gatekeeper_id: u32,
+    /// Groups of workers joined by edges in either direction, each in ID
+    /// order, with the gatekeeper's group first
+    pub components: Vec<Vec<u32>>,
+    /// Workers that can't get a finished pearl back to the gatekeeper
+    pub cannot_reach_gatekeeper: Vec<u32>,
+    /// Workers the gatekeeper can't pass pearls to
+    pub unreachable_from_gatekeeper: Vec<u32>,
+    /// The most hops between any two workers in the same component,
+    /// ignoring the direction of edges
+    pub diameter: u32,
+    /// How many hops the gatekeeper's pearls take to reach each worker,
+    /// following the direction of edges
+    pub distances: Vec<WorkerDistance>,
+    /// Workers whose loss would split their component, in ID order
+    pub articulation_points: Vec<u32>,
+    /// The hop limit `flavors` was worked out for
+    pub hops: u32,
+    pub flavors: Vec<FlavorReach>,
+}
+
+/// Counts hops outward from a worker with a breadth first search.
+///
+/// # Arguments
+///
+/// * `start` - The ID of the worker to start from
+/// * `adjacency` - Who each worker leads to
+///
+/// # Returns
+///
+/// * `HashMap<u32, u32>` - The hops to every worker reached, including
+///                         `start` at 0
+fn hop_distances(start: u32, adjacency: &Adjacency) -> HashMap<u32, u32> {
+    let mut distances: HashMap<u32, u32> = HashMap::new();
+    let mut queue: VecDeque<u32> = VecDeque::new();
+
+    if adjacency.contains_key(&start) {
+        distances.insert(start, 0);
+        queue.push_back(start);
+    }
+
+    while let Some(id) = queue.pop_front() {
+        let d = distances[&id];
+        for nbr in adjacency.get(&id).map(|n| n.as_slice()).unwrap_or(&[]) {
+            if !distances.contains_key(nbr) {
+                distances.insert(*nbr, d + 1);
+                queue.push_back(*nbr);
+            }
+        }
+    }
+
+    return distances;
+}
+
+/// Builds three views of the graph: the edges as they go, the edges
+/// reversed, and every edge going both ways. Neighbor lists are sorted so
+/// the report is the same from run to run.
+fn adjacency_views(neighbor_graph: &NeighborGraph) -> (Adjacency, Adjacency, Adjacency) {
+    let mut forward: Adjacency = HashMap::new();
+    let mut backward: Adjacency = HashMap::new();
+    let mut both: Adjacency = HashMap::new();
+
+    for id in neighbor_graph.worker_ids() {
+        forward.entry(id).or_default();
+        backward.entry(id).or_default();
+        both.entry(id).or_default();
+    }
+
+    for from in neighbor_graph.worker_ids() {
+        for &to in &neighbor_graph[&from] {
+            if from == to {
+                continue;
+            }
+            forward.entry(from).or_default().push(to);
+            backward.entry(to).or_default().push(from);
+            both.entry(from).or_default().push(to);
+            both.entry(to).or_default().push(from);
+        }
+    }
+
+    for view in [&mut forward, &mut backward, &mut both] {
+        for nbrs in view.values_mut() {
+            nbrs.sort();
+            nbrs.dedup();
+        }
+    }
+
+    return (forward, backward, both);
+}
+
+/// Finds the workers whose removal would split the component they are in,
+/// with a depth first search that tracks the earliest worker each subtree
+/// can get back to without going through its parent. The search keeps its
+/// own stack, so a long chain of workers can't overflow the thread's.
+///
+/// # Arguments
+///
+/// * `both` - Who each worker is joined to, in either direction
+///
+/// # Returns
+///
+/// * `Vec<u32>` - The IDs of the articulation points, sorted
+pub fn find_articulation_points(both: &Adjacency) -> Vec<u32> {
+    /// A worker the search is part way through
+    struct Frame {
+        id: u32,
+        parent: Option<u32>,
+        /// How many of its neighbors have been looked at
+        next: usize,
+        /// How many workers were first reached from it
+        children: u32,
+    }
+
+    let mut order: HashMap<u32, u32> = HashMap::new();
+    let mut low: HashMap<u32, u32> = HashMap::new();
+    let mut points: HashSet<u32> = HashSet::new();
+
+    let mut ids: Vec<u32> = both.keys().copied().collect();
+    ids.sort();
+    for root in ids {
+        if order.contains_key(&root) {
+            continue;
+        }
+
+        order.insert(root, order.len() as u32);
+        low.insert(root, order[&root]);
+        let mut stack: Vec<Frame> = vec![Frame {
+            id: root,
+            parent: None,
+            next: 0,
+            children: 0,
+        }];
+
+        while let Some(frame) = stack.last_mut() {
+            let id = frame.id;
+
+            if let Some(&nbr) = both[&id].get(frame.next) {
+                frame.next += 1;
+                if Some(nbr) == frame.parent {
+                    continue;
+                }
+
+                match order.get(&nbr).copied() {
+                    Some(o) => {
+                        let l = low[&id].min(o);
+                        low.insert(id, l);
+                    }
+                    None => {
+                        frame.children += 1;
+                        order.insert(nbr, order.len() as u32);
+                        low.insert(nbr, order[&nbr]);
+                        stack.push(Frame {
+                            id: nbr,
+                            parent: Some(id),
+                            next: 0,
+                            children: 0,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            // Every neighbor has been looked at, so hand what this subtree
+            // can reach back up to the parent
+            let done = match stack.pop() {
+                Some(f) => f,
+                None => break,
+            };
+            match stack.last() {
+                None => {
+                    if done.children > 1 {
+                        points.insert(done.id);
+                    }
+                }
+                Some(parent) => {
+                    let l = low[&parent.id].min(low[&done.id]);
+                    low.insert(parent.id, l);
+                    if parent.parent.is_some() && low[&done.id] >= order[&parent.id] {
+                        points.insert(parent.id);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut points: Vec<u32> = points.into_iter().collect();
+    points.sort();
+    return points;
+}
+
+/// Analyses the layout of a floor: how it splits into components, who is
+/// cut off from the gatekeeper, how far apart workers are, which workers
+/// the floor can't afford to lose, and which flavors the gatekeeper can get
+/// pearls to quickly. Distances are counted in passes, whatever the edges
+/// cost, and closed edges don't count.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state whose floor to analyse
+/// * `config` - The config, for the gatekeeper's ID
+/// * `hops` - How many hops from the gatekeeper to look for flavors within
+///
+/// # Returns
+///
+/// * `TopologyReport` - The analysis of the floor
+pub fn analyse_topology(state: &State, config: &Config, hops: u32) -> TopologyReport {
+    let gatekeeper_id = config.gatekeeper_id;
+    let neighbor_graph = build_state_graph(state, &config.transfer);
+    let (forward, backward, both) = adjacency_views(&neighbor_graph);
+    let ids = neighbor_graph.worker_ids();
+
+    let out = hop_distances(gatekeeper_id, &forward);
+    let home = hop_distances(gatekeeper_id, &backward);
+
+    let mut components: Vec<Vec<u32>> = Vec::new();
+    let mut placed: HashSet<u32> = HashSet::new();
+    let mut diameter: u32 = 0;
+
+    for &id in &ids {
+        if placed.contains(&id) {
+            continue;
+        }
+
+        let mut component: Vec<u32> = hop_distances(id, &both).into_keys().collect();
+        component.sort();
+        for member in &component {
+            placed.insert(*member);
+            let farthest = hop_distances(*member, &both).into_values().max();
+            diameter = diameter.max(farthest.unwrap_or(0));
+        }
+        components.push(component);
+    }
+    components.sort_by_key(|c| (!c.contains(&gatekeeper_id), c.first().copied()));
+
+    let mut flavors: Vec<FlavorReach> = Vec::new();
+    for flavor in FLAVORS {
+        let mut within_hops: Vec<u32> = Vec::new();
+        let mut nearest: Option<u32> = None;
+
+        for w in &state.workers {
+            if w.flavor != flavor {
+                continue;
+            }
+            if let Some(&d) = out.get(&w.id) {
+                if d <= hops {
+                    within_hops.push(w.id);
+                }
+                nearest = Some(nearest.map_or(d, |n| n.min(d)));
+            }
+        }
+
+        within_hops.sort();
+        flavors.push(FlavorReach {
+            flavor: flavor.to_string(),
+            within_hops: within_hops,
+            nearest: nearest,
+        });
+    }
+
+    return TopologyReport {
+        gatekeeper_id: gatekeeper_id,
+        components: components,
+        cannot_reach_gatekeeper: ids
+            .iter()
+            .filter(|id| !home.contains_key(id))
+            .copied()
+            .collect(),
+        unreachable_from_gatekeeper: ids
+            .iter()
+            .filter(|id| !out.contains_key(id))
+            .copied()
+            .collect(),
+        diameter: diameter,
+        distances: ids
+            .iter()
+            .map(|id| WorkerDistance {
+                worker_id: *id,
+                distance: out.get(id).copied(),
+            })
+            .collect(),
+        articulation_points: find_articulation_points(&both),
+        hops: hops,
+        flavors: flavors,
+    };
+}
+
+/// Writes a topology report out as readable text.
+///
+/// # Arguments
+///
+/// * `report` - A reference to the report to write
+///
+/// # Returns
+///
+/// * `String` - The report, one fact per line
+pub fn topology_to_text(report: &TopologyReport) -> String {
+    let mut out = format!("gatekeeper {}\n", report.gatekeeper_id);
+
+    let components: Vec<String> = report
+        .components
+        .iter()
+        .map(|c| format!("{:?}", c))
+        .collect();
+    out += &format!(
+        "components {}: {}\n",
+        report.components.len(),
+        components.join(" ")
+    );
+    if !report.cannot_reach_gatekeeper.is_empty() {
+        out += &format!(
+            "WARNING cannot reach the gatekeeper: {:?}\n",
+            report.cannot_reach_gatekeeper
+        );
+    }
+    if !report.unreachable_from_gatekeeper.is_empty() {
+        out += &format!(
+            "WARNING the gatekeeper cannot reach: {:?}\n",
+            report.unreachable_from_gatekeeper
+        );
+    }
+    out += &format!("diameter {}\n", report.diameter);
+    out += &format!("articulation points {:?}\n", report.articulation_points);
+
+    out += "distance from the gatekeeper:\n";
+    for d in &report.distances {
+        match d.distance {
+            Some(h) => out += &format!("  {}: {}\n", d.worker_id, h),
+            None => out += &format!("  {}: unreachable\n", d.worker_id),
+        }
+    }
+
+    out += &format!("flavors within {} hops:\n", report.hops);
+    for f in &report.flavors {
+        let nearest = match f.nearest {
+            Some(n) => format!("nearest {} hops", n),
+            None => String::from("unreachable"),
+        };
+        out += &format!("  {}: {:?}, {}\n", f.flavor, f.within_hops, nearest);
+    }
+
+    return out;
+}
+
+/// Writes a topology report out as JSON.
+///
+/// # Arguments
+///
+/// * `report` - A reference to the report to write
+///
+/// # Returns
+///
+/// * `String` - The report as a single line of JSON
+pub fn topology_to_json(report: &TopologyReport) -> String {
+    return match serde_json::to_string(report) {
+        Ok(s) => s,
+        Err(e) => panic!("Topology Error: could not write report: {}", e),
+    };
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::models;
+use models::config::TransferConfig;
+use models::state::NeighborGraph;
+
+/// How many turns each pass costs a pearl while it is in transit, on top of
+/// the turn spent passing it. Built from the `transfer` section of the
+/// config and the state's edges, and shared by the strategies, planners
+/// and the simulator.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferCosts {
+    default: u32,
+    edges: HashMap<(u32, u32), u32>,
+    capacities: HashMap<(u32, u32), u32>,
+}
+
+impl TransferCosts {
+    /// Reads the costs from the config. Each edge cost applies in both
+    /// directions, and a later entry for the same edge wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The `transfer` section of the config
+    ///
+    /// # Returns
+    ///
+    /// * `TransferCosts` - The cost of a pass over every edge
+    pub fn new(config: &TransferConfig) -> TransferCosts {
+        let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+        for e in &config.edges {
+            let [a, b] = e.workers;
+            edges.insert((a, b), e.cost);
+            edges.insert((b, a), e.cost);
+        }
+
+        return TransferCosts {
+            default: config.cost,
+            edges: edges,
+            capacities: HashMap::new(),
+        };
+    }
+
+    /// The same cost for a pass over any edge
+    pub fn uniform(cost: u32) -> TransferCosts {
+        return TransferCosts {
+            default: cost,
+            edges: HashMap::new(),
+            capacities: HashMap::new(),
+        };
+    }
+
+    /// Applies the costs and capacities carried by a neighbor graph's
+    /// edges. Their costs take precedence over the config, and edges
+    /// without a cost keep theirs.
+    ///
+    /// # Arguments
+    ///
+    /// * `neighbor_graph` - The graph whose edge costs to apply
+    ///
+    /// # Returns
+    ///
+    /// * `TransferCosts` - The costs, with the graph's edges applied
+    pub fn with_graph(mut self, neighbor_graph: &NeighborGraph) -> TransferCosts {
+        for (&edge, attributes) in neighbor_graph.attributed_edges() {
+            if let Some(cost) = attributes.cost {
+                self.edges.insert(edge, cost);
+            }
+            if let Some(capacity) = attributes.capacity {
+                self.capacities.insert(edge, capacity);
+            }
+        }
+
+        return self;
+    }
+
+    /// The cost of passing a pearl from one worker to another
+    ///
+    /// # Arguments
+    ///
+    /// * `from_id` - The ID of the worker passing the pearl
+    /// * `to_id` - The ID of the worker receiving it
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - How many turns the pearl spends in transit
+    pub fn cost(&self, from_id: u32, to_id: u32) -> u32 {
+        return self
+            .edges
+            .get(&(from_id, to_id))
+            .copied()
+            .unwrap_or(self.default);
+    }
+
+    /// The most pearls that can be in transit from one worker to another at
+    /// once, or None if there is no limit
+    pub fn capacity(&self, from_id: u32, to_id: u32) -> Option<u32> {
+        return self.capacities.get(&(from_id, to_id)).copied();
+    }
+
+    /// Whether every pass is free, i.e. pearls are never in transit
+    pub fn is_free(&self) -> bool {
+        return self.default == 0 && self.edges.values().all(|&c| c == 0);
+    }
+}
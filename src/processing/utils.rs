@@ -1,9 +1,13 @@
 use crate::models;
 use crate::models::action::ActionType;
-use crate::models::state::{NeighborGraph, Pearl, State, Worker, WorkerPearlIDs};
+use crate::models::state::{EdgeAttributes, NeighborGraph, Pearl, State, Worker, WorkerPearlIDs};
+use crate::processing::gatekeeper::{determine_gatekeeper_action, get_next_hops_home};
+use crate::processing::transfer::TransferCosts;
+use log::{debug, error, trace, warn};
 use models::ability_map::AbilityMap;
 use models::action::{Nom, Pass};
-use models::state::{NeighborMap, Workers};
+use models::config::{Config, StrategyConfig, TieBreak, TransferConfig};
+use models::state::{Edges, NeighborMap, Workers};
 use serde_json;
 use std::collections::HashMap;
 
@@ -88,10 +92,32 @@ pub fn get_worker_pearl_counts(workers: &Workers) -> HashMap<u32, u32> {
     return counts;
 }
 
-/// This method will build a HashMap where the keys are the nautiloid IDs,
-/// and the values are a Vec<u32> of the IDs of all the nautiloids to which
-/// they are adjacent. This is intended to make looking up neighbors easier
-/// when deciding to pass a pearl or not.
+/// How far along a pearl on a worker's desk is, for logging
+fn pearl_progress(worker: &Worker, pearl_id: u32) -> f64 {
+    return worker
+        .desk
+        .iter()
+        .find(|p| p.id == pearl_id)
+        .map_or(0.0, |p| p.progress());
+}
+
+/// Takes one pearl off a worker's count, leaving it at zero rather than
+/// underflowing if the counts have gone wrong.
+///
+/// # Arguments
+///
+/// * `pearl_counts` - The number of pearls each worker has
+/// * `worker_id` - The ID of the worker passing a pearl away
+fn decrement_count(pearl_counts: &mut HashMap<u32, u32>, worker_id: u32) {
+    if let Some(count) = pearl_counts.get_mut(&worker_id) {
+        *count = count.saturating_sub(1);
+    }
+}
+
+/// This method will build a NeighborGraph that maps each nautiloid ID to
+/// the IDs of all the nautiloids to which they are adjacent. Every pair in
+/// the neighbor map can pass both ways. This is intended to make looking up
+/// neighbors easier when deciding to pass a pearl or not.
 ///
 /// With this method, we can construct the map only one time, instead of
 /// having to iterate over the entire neighbor map each time we want to
@@ -108,37 +134,143 @@ pub fn get_worker_pearl_counts(workers: &Workers) -> HashMap<u32, u32> {
 ///
 /// # Returns
 ///
-/// * `HashMap<u32, Vec<u32>>` - A HashMap where the keys are nautiloid IDs,
-///                              and the values are vectors of the IDs of
-///                              the neighbor nautiloids.
+/// * `NeighborGraph` - The neighbors of every nautiloid, by ID
 pub fn build_neighbor_graph(neighbors: &NeighborMap, workers: &Workers) -> NeighborGraph {
-    let mut neighbor_map: HashMap<u32, Vec<u32>> = HashMap::new();
+    return build_neighbor_graph_with_edges(neighbors, &Vec::new(), workers);
+}
+
+/// Builds the neighbor graph like `build_neighbor_graph`, then applies the
+/// attributes of any listed edges. An edge joins its workers even if the
+/// neighbor map doesn't, a one-way edge removes the pass back the other
+/// way, and an edge with a capacity of 0 is left out entirely.
+///
+/// # Arguments
+///
+/// * `neighbors` - This is the NeighborMap that is read directly from the
+///                 raw JSON file
+/// * `edges` - The attributes of particular edges
+/// * `workers` - The workers on the floor
+///
+/// # Returns
+///
+/// * `NeighborGraph` - The directed graph of who can pass to whom
+pub fn build_neighbor_graph_with_edges(
+    neighbors: &NeighborMap,
+    edges: &Edges,
+    workers: &Workers,
+) -> NeighborGraph {
+    let mut neighbor_graph = NeighborGraph::new();
     let worker_ids = get_worker_ids(workers);
 
     for id in &worker_ids {
-        let mut nb_list: Vec<u32> = Vec::new();
-
-        for pair in neighbors {
-            // check to make sure the
-            for ind in [0, 1] {
-                if !worker_ids.contains(&pair[ind]) {
-                    panic!(
-                        "Neighbor Graph Error: {} is not a valid worker ID",
-                        &pair[ind]
-                    );
-                }
+        neighbor_graph.add_worker(*id);
+    }
+
+    for pair in neighbors {
+        // Entries that aren't pairs don't join anyone, so skip them
+        if pair.len() != 2 {
+            error!(entry:? = pair; "neighbor map entry is not a pair");
+            continue;
+        }
+        // As are entries naming workers that aren't on the floor
+        if let Err(e) = check_graph_workers(&worker_ids, pair) {
+            error!(entry:? = pair, error = e.as_str(); "neighbor map names an unknown worker");
+            continue;
+        }
+
+        neighbor_graph.add_edge(pair[0], pair[1]);
+        neighbor_graph.add_edge(pair[1], pair[0]);
+    }
+
+    for edge in edges {
+        let [from, to] = edge.workers;
+        if let Err(e) = check_graph_workers(&worker_ids, &edge.workers) {
+            error!(edge:? = edge.workers, error = e.as_str(); "edge names an unknown worker");
+            continue;
+        }
+
+        let attributes = EdgeAttributes {
+            cost: edge.cost,
+            capacity: edge.capacity,
+        };
+        let mut directions = vec![(from, to)];
+        if edge.one_way {
+            neighbor_graph.remove_edge(to, from);
+        } else {
+            directions.push((to, from));
+        }
+
+        for (a, b) in directions {
+            if edge.capacity == Some(0) {
+                neighbor_graph.remove_edge(a, b);
+            } else {
+                neighbor_graph.add_edge(a, b);
+                neighbor_graph.set_attributes(a, b, attributes);
             }
+        }
+    }
+
+    debug!(workers = worker_ids.len(), pairs = neighbors.len(), edges = edges.len(); "built neighbor graph");
 
-            if id == &pair[0] {
-                nb_list.push(pair[1]);
-            } else if id == &pair[1] {
-                nb_list.push(pair[0]);
+    return neighbor_graph;
+}
+
+/// Builds the neighbor graph of a state, with the edge attributes it lists.
+/// Edges without a cost of their own take the cost in the config.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state of the pipeline
+/// * `transfer` - The transfer costs from the config
+///
+/// # Returns
+///
+/// * `NeighborGraph` - The directed graph of who can pass to whom
+pub fn build_state_graph(state: &State, transfer: &TransferConfig) -> NeighborGraph {
+    let mut neighbor_graph =
+        build_neighbor_graph_with_edges(&state.neighbor_map, &state.edges, &state.workers);
+    let config_costs = TransferCosts::new(transfer);
+
+    if !config_costs.is_free() {
+        for from in neighbor_graph.worker_ids() {
+            for to in neighbor_graph[&from].clone() {
+                let mut attributes = neighbor_graph
+                    .attributes(from, to)
+                    .copied()
+                    .unwrap_or_default();
+                if attributes.cost.is_none() {
+                    attributes.cost = Some(config_costs.cost(from, to));
+                    neighbor_graph.set_attributes(from, to, attributes);
+                }
             }
         }
-        neighbor_map.insert(*id, nb_list);
     }
 
-    return neighbor_map;
+    return neighbor_graph;
+}
+
+/// Checks that a neighbor map entry or edge only names workers that are on
+/// the floor.
+///
+/// # Arguments
+///
+/// * `worker_ids` - The IDs of the workers on the floor
+/// * `pair` - The worker IDs the entry or edge joins
+///
+/// # Returns
+///
+/// * `Result<(), String>` - An error naming the first unknown worker
+fn check_graph_workers(worker_ids: &[u32], pair: &[u32]) -> Result<(), String> {
+    for id in pair {
+        if !worker_ids.contains(id) {
+            return Err(format!(
+                "Neighbor Graph Error: {} is not a valid worker ID",
+                id
+            ));
+        }
+    }
+
+    return Ok(());
 }
 
 /// Takes information about a worker passing a pearl to another worker
@@ -195,11 +327,12 @@ pub fn get_time_to_process(pearl: &Pearl, worker: &Worker, ability_map: &Ability
     let mut total_time: u32 = 0;
 
     for layer in &pearl.layers {
-        let layer_time = ((layer.thickness as f32)
-            / (ability_map[&worker.flavor][&layer.color] as f32))
-            .ceil() as u32;
+        // A flavor or color the map doesn't know can't be processed at all,
+        // the same as a rate of 0
+        let rate = ability_map.rate(&worker.flavor, &layer.color).unwrap_or(0);
+        let layer_time = ((layer.thickness as f32) / (rate as f32)).ceil() as u32;
 
-        total_time += layer_time;
+        total_time = total_time.saturating_add(layer_time);
     }
 
     return total_time;
@@ -211,7 +344,7 @@ pub fn get_time_to_process(pearl: &Pearl, worker: &Worker, ability_map: &Ability
 ///
 /// * `worker` - A reference to the worker in question
 /// * `pearl_counts` - The number of pearls each worker has
-/// * `neighbor_graph` - A HashMap detailing the neighbors of each worker
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
 ///
 /// # Returns
 ///
@@ -221,15 +354,91 @@ pub fn get_empty_neighbors(
     pearl_counts: &HashMap<u32, u32>,
     neighbor_graph: &NeighborGraph,
 ) -> Vec<u32> {
-    let mut empty_nbrs = neighbor_graph.get(&worker.id).unwrap().clone();
-    empty_nbrs.retain(|&i| pearl_counts[&i] == 0);
+    let mut empty_nbrs = neighbor_graph.get(&worker.id).cloned().unwrap_or_default();
+    empty_nbrs.retain(|i| pearl_counts.get(i) == Some(&0));
 
     return empty_nbrs;
 }
 
+/// Decides whether a newly found option should replace the current best
+/// option when the two are otherwise equally good.
+///
+/// # Arguments
+///
+/// * `tie_break` - The tie-break rule in use
+/// * `current_id` - The pearl ID of the current best option
+/// * `new_id` - The pearl ID of the newly found option
+///
+/// # Returns
+///
+/// * `bool` - True if the new option should be kept instead
+pub fn prefer_new_option(tie_break: TieBreak, current_id: u32, new_id: u32) -> bool {
+    return match tie_break {
+        TieBreak::First => false,
+        TieBreak::Last => true,
+        TieBreak::LowestId => new_id < current_id,
+        TieBreak::HighestId => new_id > current_id,
+    };
+}
+
+/// Decides whether passing a pearl to an empty neighbor is worth it. The
+/// neighbor's time includes the turns the pearl spends in transit.
+///
+/// # Arguments
+///
+/// * `holder_time` - How long the worker holding the pearl would take
+/// * `neighbor_time` - How long the neighbor would take
+/// * `transfer_cost` - How many turns the pass leaves the pearl in transit
+/// * `desk_len` - How many pearls the holding worker has on their desk
+/// * `partial` - Whether the pearl is part way through a layer
+/// * `strategy` - The thresholds to decide with
+///
+/// # Returns
+///
+/// * `bool` - True if the pass qualifies
+pub fn pass_qualifies(
+    holder_time: u32,
+    neighbor_time: u32,
+    transfer_cost: u32,
+    desk_len: usize,
+    partial: bool,
+    strategy: &StrategyConfig,
+) -> bool {
+    let neighbor_time = neighbor_time.saturating_add(transfer_cost);
+    let extra_gain = if partial {
+        strategy.partial_pass_gain
+    } else {
+        0
+    };
+
+    // Only pass to a neighbor if they can actually process it better, OR if
+    // the worker has extra pearls to work on.
+    let faster = neighbor_time
+        .saturating_add(strategy.min_pass_gain)
+        .saturating_add(extra_gain)
+        <= holder_time;
+    let spare =
+        neighbor_time == holder_time && extra_gain == 0 && desk_len >= strategy.equal_pass_min_desk;
+
+    return faster || spare;
+}
+
 /// Given the state, and a particular worker, determine the best option
 /// for passing a pearl to a neighbor.
 ///
+/// A pass qualifies when the neighbor, counting the turns the pearl spends
+/// in transit, is at least `min_pass_gain` turns faster, or exactly as fast
+/// while the worker has at least `equal_pass_min_desk` pearls. A pearl part
+/// way through a layer also needs the neighbor to be `partial_pass_gain`
+/// turns faster still. When several passes qualify the `pass_tie_break`
+/// rule picks between them. Finished pearls are never passed here, as they
+/// only head home.
+///
+/// Edge capacity isn't checked here, since a state doesn't show the pearls
+/// already in transit. An edge with a capacity of 0 isn't in the graph at
+/// all, and a pass over a full edge is held back by `apply_turn`, which
+/// also takes back the step this pass adds to the pearl's path.
+///
 /// # Arguments
 ///
 /// * `state` - A reference to the state of the pipeline
@@ -237,7 +446,10 @@ pub fn get_empty_neighbors(
 /// * `pearl_counts` - A reference to the pearl counts of the workers
 /// * `ability_map` - A reference to the ability map describing how different
 ///                   flavors of worker can process different colors of pearls
-/// * `neighbor_graph` - A HashMap detailing the neighbors of each worker
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
+/// * `transfer_costs` - How long a pass to each neighbor leaves a pearl in
+///                      transit
+/// * `strategy` - The thresholds and tie-break rules to decide with
 ///
 /// # Returns
 ///
@@ -251,26 +463,46 @@ pub fn get_best_neighbor(
     pearl_counts: &HashMap<u32, u32>,
     ability_map: &AbilityMap,
     neighbor_graph: &NeighborGraph,
+    transfer_costs: &TransferCosts,
+    strategy: &StrategyConfig,
 ) -> Option<WorkerPearlIDs> {
     let empty_neighbors = get_empty_neighbors(worker, pearl_counts, neighbor_graph);
     let mut best_pair: Option<WorkerPearlIDs> = None;
 
     for e_nbr_id in &empty_neighbors {
         for p in &worker.desk {
+            // Finished pearls only ever head home
+            if p.layers.len() == 0 {
+                continue;
+            }
+
             let best_time = get_time_to_process(p, worker, ability_map);
 
             let mut nbr = state.workers.clone();
             nbr.retain(|w| &w.id == e_nbr_id);
 
             let time = get_time_to_process(p, &nbr[0], ability_map);
-
-            // Only pass to a neighbor if they can actually process it better, OR if
-            // the worker has extra pearls to work on.
-            if (time < best_time) || (time == best_time && worker.desk.len() > 1) {
-                best_pair = Some(WorkerPearlIDs {
-                    worker_id: *e_nbr_id,
-                    pearl_id: p.id,
-                });
+            let transfer = transfer_costs.cost(worker.id, *e_nbr_id);
+
+            if pass_qualifies(
+                best_time,
+                time,
+                transfer,
+                worker.desk.len(),
+                p.is_partial(),
+                strategy,
+            ) {
+                let replace = match &best_pair {
+                    Some(bp) => prefer_new_option(strategy.pass_tie_break, bp.pearl_id, p.id),
+                    None => true,
+                };
+
+                if replace {
+                    best_pair = Some(WorkerPearlIDs {
+                        worker_id: *e_nbr_id,
+                        pearl_id: p.id,
+                    });
+                }
             }
         }
     }
@@ -285,12 +517,17 @@ pub fn get_best_neighbor(
 /// * `worker` - A reference to the worker deciding which pearl to nom
 /// * `ability_map` - A reference to the ability map describing how different
 ///                   flavors of worker can process different colors of pearls
+/// * `tie_break` - Which pearl to pick when several are equally fast
 ///
 /// # Returns
 ///
 /// * `u32` - The ID of the optimal pearl for the worker to nom
 ///
-pub fn get_best_pearl_to_nom(worker: &Worker, ability_map: &AbilityMap) -> Option<u32> {
+pub fn get_best_pearl_to_nom(
+    worker: &Worker,
+    ability_map: &AbilityMap,
+    tie_break: TieBreak,
+) -> Option<u32> {
     // Remove any finished pearls first
     let mut unfinished_pearls = worker.desk.clone();
     unfinished_pearls.retain(|p| p.layers.len() != 0);
@@ -303,7 +540,10 @@ pub fn get_best_pearl_to_nom(worker: &Worker, ability_map: &AbilityMap) -> Optio
 
         match best_time {
             Some(bt) => {
-                if time < bt {
+                let tied =
+                    time == bt && prefer_new_option(tie_break, best_pearl_id.unwrap(), pearl.id);
+
+                if time < bt || tied {
                     best_pearl_id = Some(pearl.id);
                     best_time = Some(time);
                 }
@@ -325,7 +565,14 @@ pub fn get_best_pearl_to_nom(worker: &Worker, ability_map: &AbilityMap) -> Optio
 /// action each nautiloid should take.
 ///
 /// If a worker does not currently have a pearl, then they will not be
-/// assigned an action to take.
+/// assigned an action to take. The gatekeeper's intake is handled
+/// separately by `determine_gatekeeper_action`.
+///
+/// A worker with an empty desk is never sent more than one pearl in a turn.
+/// If a finished pearl's next stop home has already been sent a pearl, it
+/// waits until the next turn, and a finished pearl with no route home at all
+/// is held where it is. Either way, the worker holding it still passes or
+/// noms its other pearls.
 ///
 /// # Arguments
 ///
@@ -335,9 +582,11 @@ pub fn get_best_pearl_to_nom(worker: &Worker, ability_map: &AbilityMap) -> Optio
 /// * `ability_map` - A reference to an AbilityMap that will describe how
 ///                   quickly each flavor of nautiloid can process each
 ///                   color of pearl
-/// * `neighbor_graph` - A HashMap detailing the neighbors of each worker
+/// * `neighbor_graph` - The graph detailing who each worker can pass to
 /// * `pearl_paths` - A HashMap detailing how each pearl has been passed
 ///                   around by the nautiloids
+/// * `config` - The config for this run, with the gatekeeper's ID and the
+///              strategy's tunable parameters
 ///
 /// # Returns
 ///
@@ -350,38 +599,105 @@ pub fn determine_actions(
     ability_map: &AbilityMap,
     neighbor_graph: &NeighborGraph,
     pearl_paths: &mut HashMap<u32, Vec<u32>>,
+    config: &Config,
 ) -> HashMap<u32, ActionType> {
+    let transfer_costs = TransferCosts::new(&config.transfer).with_graph(neighbor_graph);
     let current_pearl_counts = get_worker_pearl_counts(&state.workers);
     let mut next_pearl_counts = get_worker_pearl_counts(&state.workers);
     let mut actions: HashMap<u32, ActionType> = HashMap::new();
+    // Only worked out if a finished pearl needs it, then shared for the turn
+    let mut hops_home: Option<HashMap<u32, u32>> = None;
 
     let mut workers_with_pearls = state.workers.clone();
-    workers_with_pearls.retain(|w| current_pearl_counts[&w.id] != 0);
+    workers_with_pearls.retain(|w| !w.desk.is_empty());
+    let workers_with_pearls_count = workers_with_pearls.len();
 
     for wrkr in workers_with_pearls {
-        // First, check for finished pearls to pass back
-        // Don't do this for the gate keeper, finished pearls stay there
+        // The gate keeper follows its own intake policy
+        if wrkr.id == config.gatekeeper_id {
+            let gk_action = determine_gatekeeper_action(
+                state,
+                &wrkr,
+                &next_pearl_counts,
+                ability_map,
+                neighbor_graph,
+                config,
+            );
+
+            if let Some(ActionType::Pass(p)) = &gk_action {
+                pearl_paths
+                    .entry(p.pearl_id)
+                    .or_insert(Vec::new())
+                    .push(wrkr.id);
+
+                decrement_count(&mut next_pearl_counts, wrkr.id);
+                *next_pearl_counts.entry(p.to_id).or_insert(0) += 1;
+            }
+
+            trace!(worker = wrkr.id, action:? = gk_action; "gatekeeper decided");
+            if let Some(a) = gk_action {
+                actions.insert(wrkr.id, a);
+            }
+            continue;
+        }
+
+        // First, check for finished pearls to pass back. One that can't be
+        // passed this turn stays put, but doesn't hold up the others.
         let mut already_passed = false;
 
-        if wrkr.id != 0 {
-            for prl in &wrkr.desk {
-                if prl.layers.len() == 0 && !already_passed {
-                    // Send the pearl back the way it came
-                    let old_path = pearl_paths.get_mut(&prl.id);
-                    let next_worker;
+        for prl in &wrkr.desk {
+            if prl.layers.len() == 0 && !already_passed {
+                // Send the pearl back the way it came
+                let mut old_path = pearl_paths.get(&prl.id).and_then(|pth| pth.last().copied());
+                if let Some(id) = old_path {
+                    // The way it came may be one-way, in which case forget
+                    // it and head home by the shortest route instead
+                    if !neighbor_graph.has_edge(wrkr.id, id) {
+                        pearl_paths.remove(&prl.id);
+                        old_path = None;
+                    }
+                }
+                let next_worker;
 
-                    match old_path {
-                        Some(pth) => {
-                            next_worker = pth.pop().unwrap();
-                        }
-                        None => {
-                            panic!("PEarl Path Error: Could not retrieve pearl's path back to the gate keeper.");
+                match old_path {
+                    Some(id) => {
+                        next_worker = id;
+                    }
+                    None => {
+                        // The pearl arrived before we started tracking it, so
+                        // take the shortest route home instead
+                        let hops = hops_home.get_or_insert_with(|| {
+                            get_next_hops_home(config.gatekeeper_id, neighbor_graph)
+                        });
+                        match hops.get(&wrkr.id).copied() {
+                            Some(id) => {
+                                next_worker = id;
+                            }
+                            None => {
+                                // Nobody can take it home, so keep hold of it
+                                warn!(worker = wrkr.id, pearl = prl.id; "no route home for finished pearl");
+                                continue;
+                            }
                         }
                     }
+                }
+
+                // An empty desk can only be sent one pearl a turn, so hold on
+                // to the pearl if another worker got there first
+                let was_empty = current_pearl_counts.get(&next_worker) == Some(&0);
+                if was_empty && next_pearl_counts.get(&next_worker) != Some(&0) {
+                    debug!(worker = wrkr.id, pearl = prl.id, to = next_worker; "holding finished pearl, desk already taken");
+                    continue;
+                }
 
-                    actions.insert(wrkr.id, make_pass(wrkr.id, prl.id, next_worker));
-                    already_passed = true;
+                if old_path.is_some() {
+                    pearl_paths.get_mut(&prl.id).and_then(|pth| pth.pop());
                 }
+
+                trace!(worker = wrkr.id, pearl = prl.id, to = next_worker; "returning finished pearl");
+                actions.insert(wrkr.id, make_pass(wrkr.id, prl.id, next_worker));
+                *next_pearl_counts.entry(next_worker).or_insert(0) += 1;
+                already_passed = true;
             }
         }
 
@@ -396,10 +712,14 @@ pub fn determine_actions(
                 &next_pearl_counts,
                 ability_map,
                 neighbor_graph,
+                &transfer_costs,
+                &config.strategy,
             );
 
             match best_nbr {
                 Some(bn) => {
+                    let progress = pearl_progress(&wrkr, bn.pearl_id);
+                    trace!(worker = wrkr.id, pearl = bn.pearl_id, to = bn.worker_id, progress = progress; "passing pearl");
                     actions.insert(wrkr.id, make_pass(wrkr.id, bn.pearl_id, bn.worker_id));
 
                     // Update the pearl counts and paths, so the other workers
@@ -409,17 +729,21 @@ pub fn determine_actions(
                         .or_insert(Vec::new())
                         .push(wrkr.id);
 
-                    next_pearl_counts.insert(wrkr.id, next_pearl_counts[&wrkr.id] - 1);
-                    next_pearl_counts.insert(bn.worker_id, next_pearl_counts[&bn.worker_id] + 1);
+                    decrement_count(&mut next_pearl_counts, wrkr.id);
+                    *next_pearl_counts.entry(bn.worker_id).or_insert(0) += 1;
                 }
                 None => {
-                    let best_pearl = get_best_pearl_to_nom(&wrkr, ability_map);
+                    let best_pearl =
+                        get_best_pearl_to_nom(&wrkr, ability_map, config.strategy.nom_tie_break);
                     match best_pearl {
                         Some(bp) => {
+                            let progress = pearl_progress(&wrkr, bp);
+                            trace!(worker = wrkr.id, pearl = bp, progress = progress; "nomming pearl");
                             actions.insert(wrkr.id, make_nom(wrkr.id, bp));
                         }
                         None => {
-                            panic!("Nom Error: Could not find a best pearl to Nom");
+                            // Only finished pearls that can't go home yet
+                            trace!(worker = wrkr.id; "nothing to do but hold finished pearls");
                         }
                     }
                 }
@@ -427,6 +751,13 @@ pub fn determine_actions(
         }
     }
 
+    debug!(
+        workers = state.workers.len(),
+        busy = workers_with_pearls_count,
+        actions = actions.len();
+        "determined actions"
+    );
+
     return actions;
 }
 
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use crate::models;
+use models::ability_map::{COLORS, FLAVORS};
+use models::state::State;
+
+/// Checks a state for the problems that would otherwise cause the pipeline
+/// to panic or misbehave part way through a game.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the state to check
+/// * `gatekeeper_id` - The ID the gatekeeper is expected to have
+///
+/// # Returns
+///
+/// * `Vec<String>` - A description of every problem found, empty if the
+///                   state is valid
+pub fn validate_state(state: &State, gatekeeper_id: u32) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+    let mut worker_ids: HashSet<u32> = HashSet::new();
+    let mut pearl_ids: HashSet<u32> = HashSet::new();
+
+    for w in &state.workers {
+        if !worker_ids.insert(w.id) {
+            problems.push(format!("worker {} appears more than once", w.id));
+        }
+
+        if !FLAVORS.contains(&w.flavor.as_str()) {
+            problems.push(format!("worker {} has unknown flavor {}", w.id, w.flavor));
+        }
+
+        for p in &w.desk {
+            if !pearl_ids.insert(p.id) {
+                problems.push(format!("pearl {} appears more than once", p.id));
+            }
+
+            for l in &p.layers {
+                if !COLORS.contains(&l.color.as_str()) {
+                    problems.push(format!("pearl {} has unknown color {}", p.id, l.color));
+                }
+                if l.thickness == 0 {
+                    problems.push(format!("pearl {} has a layer with no thickness", p.id));
+                }
+            }
+        }
+    }
+
+    if !worker_ids.contains(&gatekeeper_id) {
+        problems.push(format!("there is no gatekeeper with ID {}", gatekeeper_id));
+    }
+
+    for pair in &state.neighbor_map {
+        if pair.len() != 2 {
+            problems.push(format!("neighbor entry {:?} is not a pair", pair));
+            continue;
+        }
+
+        for id in pair {
+            if !worker_ids.contains(id) {
+                problems.push(format!(
+                    "neighbor entry {:?} names unknown worker {}",
+                    pair, id
+                ));
+            }
+        }
+    }
+
+    for edge in &state.edges {
+        for id in &edge.workers {
+            if !worker_ids.contains(id) {
+                problems.push(format!(
+                    "edge {:?} names unknown worker {}",
+                    edge.workers, id
+                ));
+            }
+        }
+    }
+
+    return problems;
+}
@@ -0,0 +1,169 @@
+//! Runs the built binary, to check what the command line does end to end:
+//! which subcommand runs, what it prints and the exit code it leaves.
+
+// The codebase favours explicit returns
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Runs `atlantis` from the crate root, so it picks up `atlantis.json`,
+/// feeding it `stdin`.
+fn atlantis(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_atlantis"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("ATLANTIS_LOG", "off")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    return child.wait_with_output().unwrap();
+}
+
+fn test_data(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/static_files/test_data")
+        .join(name);
+}
+
+fn stdout(output: &Output) -> String {
+    return String::from_utf8_lossy(&output.stdout).to_string();
+}
+
+fn stderr(output: &Output) -> String {
+    return String::from_utf8_lossy(&output.stderr).to_string();
+}
+
+#[test]
+fn test_help_and_version() {
+    let help = atlantis(&["--help"], "");
+    assert_eq!(help.status.code(), Some(0));
+    assert!(stdout(&help).contains("Usage: atlantis"));
+
+    let version = atlantis(&["-V"], "");
+    assert_eq!(version.status.code(), Some(0));
+    assert!(stdout(&version).starts_with("atlantis "));
+}
+
+#[test]
+fn test_bad_command_line_exits_2() {
+    for line in [
+        vec!["dance"],
+        vec!["simulate", "--dot"],
+        vec!["topology", "-", "--hops", "x"],
+        vec!["--set"],
+    ] {
+        let output = atlantis(&line, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", line);
+        assert!(stderr(&output).contains("Usage: atlantis"));
+        assert!(stdout(&output).is_empty());
+    }
+}
+
+#[test]
+fn test_failed_command_exits_1() {
+    let missing = atlantis(&["validate", "no/such/file.txt"], "");
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(stderr(&missing).contains("Could not read"));
+
+    let unknown_key = atlantis(&["--set", "no_such_key=1", "run"], "");
+    assert_eq!(unknown_key.status.code(), Some(1));
+    assert!(stderr(&unknown_key).contains("Unknown config key"));
+
+    let not_json = atlantis(&["explain", "-"], "not json\n");
+    assert_eq!(not_json.status.code(), Some(1));
+}
+
+#[test]
+fn test_run_reads_stdin() {
+    let game = fs::read_to_string(test_data("test_game.txt")).unwrap();
+    let output = atlantis(&[], &game);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).lines().count(), game.lines().count());
+}
+
+#[test]
+fn test_run_skips_blank_lines() {
+    let game = fs::read_to_string(test_data("test_game.txt")).unwrap();
+    let spaced: String = game.lines().map(|l| format!("\n{}\n  \n", l)).collect();
+    let output = atlantis(&["run"], &spaced);
+
+    // The server skips blank lines too, so both read a game the same way
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).lines().count(), game.lines().count());
+}
+
+#[test]
+fn test_overrides_reach_the_command() {
+    let game = test_data("test_game.txt");
+    let game = game.to_str().unwrap();
+
+    assert_eq!(atlantis(&["validate", game], "").status.code(), Some(0));
+
+    // There's no worker 99 to be the gatekeeper
+    let moved = atlantis(&["validate", game, "--gatekeeper-id", "99"], "");
+    assert_eq!(moved.status.code(), Some(1));
+    assert!(stdout(&moved).contains("no gatekeeper with ID 99"));
+}
+
+#[test]
+fn test_state_commands() {
+    let state = fs::read_to_string(test_data("test_actions.txt")).unwrap();
+
+    for command in ["explain", "topology", "bound"] {
+        let output = atlantis(&[command, "-", "--json"], &state);
+        assert_eq!(output.status.code(), Some(0), "{}", command);
+        let parsed: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+        assert!(parsed.is_object(), "{}", command);
+    }
+
+    let rendered = atlantis(&["render", "--dot"], &state);
+    assert_eq!(rendered.status.code(), Some(0));
+    assert!(stdout(&rendered).starts_with("graph atlantis"));
+}
+
+#[test]
+fn test_run_rejects_invalid_states() {
+    // Both used to panic deciding the turn, and exit 101
+    let unknown_worker =
+        r#"{"workers":[{"id":0,"flavor":"General","desk":[]}],"neighbor_map":[[0,7]],"score":0}"#;
+    let unknown_flavor =
+        r#"{"workers":[{"id":0,"flavor":"Purple","desk":[]}],"neighbor_map":[],"score":0}"#;
+
+    for state in [unknown_worker, unknown_flavor] {
+        let output = atlantis(&["run"], &format!("{}\n", state));
+        assert_eq!(output.status.code(), Some(1), "{}", state);
+        assert!(stderr(&output).contains("Invalid state"), "{}", state);
+    }
+}
+
+#[test]
+fn test_state_commands_reject_invalid_states() {
+    let invalid =
+        r#"{"workers":[{"id":0,"flavor":"General","desk":[]}],"neighbor_map":[[0,7]],"score":0}"#;
+
+    for command in ["explain", "topology", "advise-layout", "bound", "solve"] {
+        let from_stdin = atlantis(&[command, "-"], invalid);
+        assert_eq!(from_stdin.status.code(), Some(1), "{}", command);
+        assert!(
+            stderr(&from_stdin).contains("unknown worker 7"),
+            "{}",
+            command
+        );
+
+        let inline = atlantis(&[command, invalid], "");
+        assert_eq!(inline.status.code(), Some(1), "{}", command);
+    }
+}